@@ -0,0 +1,203 @@
+//! Module describes announcement of a device.
+//!
+//! Announcement datagram starts with magic bytes `DA`, followed by
+//! the kind of device and length prefixed strings of device id,
+//! description, room and endpoint:
+//! `[D][A][kind][id length][id]...[description length][description]...`.
+
+use std::{fmt, net::SocketAddr, str::FromStr};
+
+/// Describes magic bytes of announcement
+pub const MAGIC: [u8; 2] = *b"DA";
+
+/// Describes kind of announced device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DeviceKind {
+    /// Power switch controlled over TCP
+    PowerSwitch,
+    /// Thermometer sending measurements over UDP
+    Thermometer,
+}
+
+impl DeviceKind {
+    /// Returns code of the kind in announcement
+    pub fn code(self) -> u8 {
+        match self {
+            DeviceKind::PowerSwitch => 0,
+            DeviceKind::Thermometer => 1,
+        }
+    }
+}
+
+impl TryFrom<u8> for DeviceKind {
+    type Error = &'static str;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0 => Ok(DeviceKind::PowerSwitch),
+            1 => Ok(DeviceKind::Thermometer),
+            _ => Err("Unknown kind of device"),
+        }
+    }
+}
+
+impl FromStr for DeviceKind {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "switch" | "power-switch" => Ok(DeviceKind::PowerSwitch),
+            "thermometer" => Ok(DeviceKind::Thermometer),
+            _ => Err("Unknown kind of device"),
+        }
+    }
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceKind::PowerSwitch => write!(f, "power switch"),
+            DeviceKind::Thermometer => write!(f, "thermometer"),
+        }
+    }
+}
+
+/// Describes device announced to the multicast group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement {
+    /// Kind of the device
+    pub kind: DeviceKind,
+    /// Identifier of the device, unique among devices of the same kind
+    pub id: String,
+    /// Human readable description of the device
+    pub description: String,
+    /// Room the device is placed in, if known
+    pub room: Option<String>,
+    /// Address the device is reached at: TCP address of power switch
+    /// or UDP address of thermometer answering requests of readings.
+    /// Unspecified IP is replaced by the source of announcement.
+    pub endpoint: SocketAddr,
+}
+
+impl Announcement {
+    /// Creates new announcement of the device without room
+    pub fn new(kind: DeviceKind, id: &str, description: &str, endpoint: SocketAddr) -> Self {
+        Self {
+            kind,
+            id: id.to_owned(),
+            description: description.to_owned(),
+            room: None,
+            endpoint,
+        }
+    }
+
+    /// Encodes announcement into datagram
+    pub fn encode(&self) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.kind.code());
+
+        let endpoint = self.endpoint.to_string();
+
+        for field in [
+            self.id.as_str(),
+            self.description.as_str(),
+            self.room.as_deref().unwrap_or_default(),
+            endpoint.as_str(),
+        ] {
+            let len = u8::try_from(field.len()).map_err(|_| "Field of announcement is too long")?;
+            bytes.push(len);
+            bytes.extend_from_slice(field.as_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes announcement from datagram
+    pub fn decode(bytes: &[u8]) -> Result<Self, &'static str> {
+        let rest = bytes
+            .strip_prefix(&MAGIC)
+            .ok_or("Datagram is not an announcement")?;
+
+        let (&kind, mut rest) = rest.split_first().ok_or("Announcement is truncated")?;
+        let mut fields = Vec::with_capacity(4);
+
+        for _ in 0..4 {
+            let (&len, tail) = rest.split_first().ok_or("Announcement is truncated")?;
+            if tail.len() < len as usize {
+                return Err("Announcement is truncated");
+            }
+            let (field, tail) = tail.split_at(len as usize);
+            fields.push(std::str::from_utf8(field).map_err(|_| "Invalid field of announcement")?);
+            rest = tail;
+        }
+
+        if !rest.is_empty() {
+            return Err("Invalid announcement size");
+        }
+
+        let endpoint = fields[3]
+            .parse::<SocketAddr>()
+            .map_err(|_| "Invalid endpoint of announcement")?;
+
+        Ok(Self {
+            kind: DeviceKind::try_from(kind)?,
+            id: fields[0].to_owned(),
+            description: fields[1].to_owned(),
+            room: (!fields[2].is_empty()).then(|| fields[2].to_owned()),
+            endpoint,
+        })
+    }
+}
+
+impl fmt::Display for Announcement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} \"{}\" ({}) at {}",
+            self.kind, self.id, self.description, self.endpoint
+        )?;
+
+        if let Some(room) = &self.room {
+            write!(f, " in {room}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_announcement() {
+        let announcement = Announcement {
+            room: Some("Bathroom".to_owned()),
+            ..Announcement::new(
+                DeviceKind::PowerSwitch,
+                "switch1",
+                "Heater",
+                "127.0.0.1:53453".parse().unwrap(),
+            )
+        };
+
+        let bytes = announcement.encode().unwrap();
+
+        assert_eq!(Announcement::decode(&bytes), Ok(announcement));
+    }
+
+    #[test]
+    fn test_decode_truncated_announcement() {
+        let announcement = Announcement::new(
+            DeviceKind::Thermometer,
+            "therm1",
+            "Kitchen",
+            "[::1]:3333".parse().unwrap(),
+        );
+
+        let bytes = announcement.encode().unwrap();
+
+        assert!(Announcement::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Announcement::decode(b"TH\x00").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Module describes background thread announcing a device
+
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::announcement::Announcement;
+
+/// Describes default interval between announcements
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Describes thread which periodically sends announcement
+/// to the multicast group. Announcing stops when announcer is dropped.
+#[derive(Debug)]
+pub struct Announcer {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Announcer {
+    /// Starts announcing the device to the `group` each `interval`.
+    /// The first announcement is sent immediately.
+    pub fn start(
+        announcement: &Announcement,
+        group: SocketAddr,
+        interval: Duration,
+    ) -> io::Result<Self> {
+        let bytes = announcement
+            .encode()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let socket = match group {
+            SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+            SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+        };
+
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn(move || loop {
+            if let Err(err) = socket.send_to(&bytes, group) {
+                println!("Failed to send announcement: {err}");
+            }
+
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => return,
+            }
+        });
+
+        Ok(Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Stops announcing and waits for the thread
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
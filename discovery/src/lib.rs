@@ -0,0 +1,15 @@
+//! Discovery of smart house devices over UDP multicast.
+//!
+//! Devices periodically announce themselves to the multicast group
+//! with [`announcer::Announcer`], controllers collect announcements
+//! with [`listener::Listener`].
+
+pub mod announcement;
+pub mod announcer;
+pub mod listener;
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// Describes multicast group devices announce themselves to by default
+pub const DEFAULT_GROUP: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 42, 99)), 53535);
@@ -0,0 +1,131 @@
+//! Module describes listener of device announcements
+
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::announcement::{Announcement, DeviceKind};
+
+/// Describes socket joined to the multicast group which receives
+/// announcements of devices. Several listeners may share the same group
+/// on one host.
+#[derive(Debug)]
+pub struct Listener {
+    socket: UdpSocket,
+}
+
+impl Listener {
+    /// Creates listener joined to the multicast `group`
+    pub fn bind(group: SocketAddr) -> io::Result<Self> {
+        let (domain, any) = match group.ip() {
+            IpAddr::V4(_) => (Domain::IPV4, IpAddr::from(Ipv4Addr::UNSPECIFIED)),
+            IpAddr::V6(_) => (Domain::IPV6, IpAddr::from(Ipv6Addr::UNSPECIFIED)),
+        };
+
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::new(any, group.port()).into())?;
+
+        let socket = UdpSocket::from(socket);
+
+        match group.ip() {
+            IpAddr::V4(ip) => socket.join_multicast_v4(&ip, &Ipv4Addr::UNSPECIFIED)?,
+            IpAddr::V6(ip) => socket.join_multicast_v6(&ip, 0)?,
+        }
+
+        Ok(Self { socket })
+    }
+
+    /// Waits for the next valid announcement during `timeout`.
+    /// Returns `None` on timeout.
+    pub fn next(&self, timeout: Duration) -> io::Result<Option<Announcement>> {
+        let started = Instant::now();
+        let mut buf = [0; 1024];
+
+        loop {
+            let left = timeout.saturating_sub(started.elapsed());
+            if left.is_zero() {
+                return Ok(None);
+            }
+
+            self.socket.set_read_timeout(Some(left))?;
+
+            let (len, src_addr) = match self.socket.recv_from(&mut buf) {
+                Ok(datagram) => datagram,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(err) => return Err(err),
+            };
+
+            match Announcement::decode(&buf[..len]) {
+                Ok(mut announcement) => {
+                    if announcement.endpoint.ip().is_unspecified() {
+                        announcement.endpoint.set_ip(src_addr.ip());
+                    }
+                    return Ok(Some(announcement));
+                }
+                Err(err) => println!("Ignored datagram from {src_addr}: {err}"),
+            }
+        }
+    }
+
+    /// Collects announcements received during `duration`.
+    /// Each device is returned once with its latest announcement.
+    pub fn scan(&self, duration: Duration) -> io::Result<Vec<Announcement>> {
+        let started = Instant::now();
+        let mut found = BTreeMap::<(DeviceKind, String), Announcement>::new();
+
+        while let Some(announcement) = self.next(duration.saturating_sub(started.elapsed()))? {
+            found.insert((announcement.kind, announcement.id.clone()), announcement);
+        }
+
+        Ok(found.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::announcer::Announcer;
+
+    #[test]
+    fn test_scan_finds_announced_devices() {
+        let group: SocketAddr = "239.255.42.99:47201".parse().unwrap();
+        let listener = Listener::bind(group).unwrap();
+
+        let switch = Announcement::new(
+            DeviceKind::PowerSwitch,
+            "switch1",
+            "Heater",
+            "0.0.0.0:53453".parse().unwrap(),
+        );
+        let thermometer = Announcement::new(
+            DeviceKind::Thermometer,
+            "therm1",
+            "Kitchen",
+            "127.0.0.1:3333".parse().unwrap(),
+        );
+
+        let interval = Duration::from_millis(100);
+        let _switch = Announcer::start(&switch, group, interval).unwrap();
+        let _thermometer = Announcer::start(&thermometer, group, interval).unwrap();
+
+        let found = listener.scan(Duration::from_millis(500)).unwrap();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].id, "switch1");
+        assert!(!found[0].endpoint.ip().is_unspecified());
+        assert_eq!(found[1], thermometer);
+    }
+}
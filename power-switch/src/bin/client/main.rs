@@ -1,11 +1,9 @@
-use crate::client::Client;
 use clap::Parser;
+use power_switch::client::Client;
 use power_switch::command::Command;
 use std::error::Error;
 use std::io;
 
-mod client;
-
 /// Client program for managing the power switch
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -46,6 +44,7 @@ fn show_menu() {
     println!("2) Turn On");
     println!("3) Is Enabled");
     println!("4) Power");
+    println!("5) Energy");
     println!("_) Exit");
 }
 
@@ -57,6 +56,7 @@ fn read_input() -> Option<Command> {
         "2" => Command::TurnOn,
         "3" => Command::IsEnabled,
         "4" => Command::GetPower,
+        "5" => Command::GetEnergy,
         _ => return None,
     };
 
@@ -1,9 +1,13 @@
-use crate::server::Server;
 use clap::Parser;
+use discovery::{
+    announcement::{Announcement, DeviceKind},
+    announcer::{Announcer, DEFAULT_INTERVAL},
+    DEFAULT_GROUP,
+};
+use power_switch::metrics::{self, CONTENT_TYPE};
 use power_switch::power_switch::PowerSwitch;
-use std::error::Error;
-
-mod server;
+use power_switch::server::Server;
+use std::{error::Error, net::SocketAddr, thread};
 
 /// Server program for serving the power switch
 #[derive(Parser, Debug)]
@@ -24,6 +28,26 @@ struct Args {
     /// Power consumption of the power switch
     #[clap(short, long, default_value_t = 0.0)]
     power_consumption: f64,
+
+    /// Identifier announced for discovery, description by default
+    #[clap(short, long, value_parser)]
+    id: Option<String>,
+
+    /// Room announced for discovery
+    #[clap(long, value_parser)]
+    room: Option<String>,
+
+    /// Multicast group for announcements: <ip>:<port>
+    #[clap(long, value_parser, default_value_t = DEFAULT_GROUP)]
+    group: SocketAddr,
+
+    /// Don't announce the switch for discovery
+    #[clap(long)]
+    no_announce: bool,
+
+    /// Address for Prometheus metrics at `/metrics`: <ip>:<port>
+    #[clap(long, value_parser)]
+    metrics: Option<String>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -37,9 +61,62 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!("{power_switch}");
 
-    let server = Server::new(args.address, power_switch)?;
+    let server = Server::new(&args.address, power_switch)?;
+
+    let _announcer = if args.no_announce {
+        None
+    } else {
+        let announcement = Announcement {
+            room: args.room.clone(),
+            ..Announcement::new(
+                DeviceKind::PowerSwitch,
+                args.id.as_deref().unwrap_or(&args.description),
+                &args.description,
+                server.local_addr()?,
+            )
+        };
+
+        println!("Announcing to {}: {announcement}", args.group);
+
+        Some(Announcer::start(
+            &announcement,
+            args.group,
+            DEFAULT_INTERVAL,
+        )?)
+    };
+
+    if let Some(address) = &args.metrics {
+        serve_metrics(address, args.id.unwrap_or(args.description), &server)?;
+        println!("Serving metrics at http://{address}/metrics");
+    }
 
     server.run()?;
 
     Ok(())
 }
+
+/// Serves metrics of the `server` in the background
+fn serve_metrics(address: &str, id: String, server: &Server) -> Result<(), Box<dyn Error>> {
+    let http = tiny_http::Server::http(address).map_err(|err| err.to_string())?;
+    let power_switch = server.power_switch();
+    let stats = server.stats();
+    let header = tiny_http::Header::from_bytes("Content-Type", CONTENT_TYPE)
+        .map_err(|_| "Invalid header")?;
+
+    thread::spawn(move || {
+        for request in http.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let body = metrics::render(&id, &power_switch.lock().unwrap(), &stats);
+                tiny_http::Response::from_string(body).with_header(header.clone())
+            } else {
+                tiny_http::Response::from_string("Not found").with_status_code(404)
+            };
+
+            if let Err(err) = request.respond(response) {
+                println!("Failed to send metrics: {err}");
+            }
+        }
+    });
+
+    Ok(())
+}
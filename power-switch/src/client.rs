@@ -0,0 +1,41 @@
+//! Module describes client for remote control of power switch
+
+use crate::command::Command;
+use crate::response::Response;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Describes client connected to power switch server
+pub struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    /// Connects to power switch server at `server_address`
+    pub fn new(server_address: impl ToSocketAddrs) -> Result<Self, Box<dyn Error>> {
+        let stream = TcpStream::connect(server_address)?;
+        Ok(Self { stream })
+    }
+
+    /// Connects to power switch server at `server_address`,
+    /// connecting and each command take no longer than `timeout`
+    pub fn with_timeout(
+        server_address: SocketAddr,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
+        let stream = TcpStream::connect_timeout(&server_address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Self { stream })
+    }
+
+    /// Sends `command` to the switch and returns its response
+    pub fn run_command(&mut self, command: Command) -> Result<Response, Box<dyn Error>> {
+        self.stream.write_all(&[command.into()])?;
+        let mut buffer = [0u8; 9];
+        self.stream.read_exact(&mut buffer)?;
+        Ok(buffer.into())
+    }
+}
@@ -6,6 +6,7 @@ pub enum Command {
     TurnOn,
     IsEnabled,
     GetPower,
+    GetEnergy,
     Unknown,
 }
 
@@ -16,6 +17,7 @@ impl From<u8> for Command {
             1 => Self::TurnOn,
             2 => Self::IsEnabled,
             3 => Self::GetPower,
+            4 => Self::GetEnergy,
             _ => Self::Unknown,
         }
     }
@@ -28,6 +30,7 @@ impl From<Command> for u8 {
             Command::TurnOn => 1,
             Command::IsEnabled => 2,
             Command::GetPower => 3,
+            Command::GetEnergy => 4,
             Command::Unknown => 255,
         }
     }
@@ -1,6 +1,9 @@
 #[macro_use]
 extern crate enum_display_derive;
 
+pub mod client;
 pub mod command;
+pub mod metrics;
 pub mod power_switch;
 pub mod response;
+pub mod server;
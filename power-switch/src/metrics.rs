@@ -0,0 +1,96 @@
+//! Module describes metrics of power switch server
+//! in Prometheus text exposition format
+
+use crate::power_switch::PowerSwitch;
+use crate::server::ServerStats;
+use std::fmt::Write;
+
+/// Describes content type of rendered metrics
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Renders state of `power_switch` and counters of its server
+/// labelled by identifier of the switch
+pub fn render(id: &str, power_switch: &PowerSwitch, stats: &ServerStats) -> String {
+    let labels = format!(r#"{{switch="{}"}}"#, escape_label(id));
+    let metrics: [(&str, &str, &str, f64); 7] = [
+        (
+            "power_switch_enabled",
+            "gauge",
+            "1 if the switch is on",
+            f64::from(u8::from(power_switch.is_enabled())),
+        ),
+        (
+            "power_switch_power_watts",
+            "gauge",
+            "Current power consumption",
+            if power_switch.is_enabled() {
+                power_switch.power_consumption()
+            } else {
+                0.0
+            },
+        ),
+        (
+            "power_switch_energy_watt_hours_total",
+            "counter",
+            "Energy consumed by the switch",
+            power_switch.energy(),
+        ),
+        (
+            "power_switch_connections_total",
+            "counter",
+            "Accepted connections of clients",
+            stats.connections() as f64,
+        ),
+        (
+            "power_switch_active_connections",
+            "gauge",
+            "Currently connected clients",
+            stats.active_connections() as f64,
+        ),
+        (
+            "power_switch_commands_total",
+            "counter",
+            "Processed commands",
+            stats.commands() as f64,
+        ),
+        (
+            "power_switch_unknown_commands_total",
+            "counter",
+            "Processed commands unknown to the switch",
+            stats.unknown_commands() as f64,
+        ),
+    ];
+
+    let mut output = String::new();
+    for (name, kind, help, value) in metrics {
+        _ = writeln!(output, "# HELP {name} {help}");
+        _ = writeln!(output, "# TYPE {name} {kind}");
+        _ = writeln!(output, "{name}{labels} {value}");
+    }
+    output
+}
+
+/// Escapes value of label according to the exposition format
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power_switch::SwitchState;
+
+    #[test]
+    fn test_render() {
+        let power_switch = PowerSwitch::from_settings("Heater", SwitchState::On, 1500.0);
+        let metrics = render("hall \"heater\"", &power_switch, &ServerStats::default());
+
+        assert!(metrics.contains("# TYPE power_switch_enabled gauge\n"));
+        assert!(metrics.contains("power_switch_enabled{switch=\"hall \\\"heater\\\"\"} 1\n"));
+        assert!(metrics.contains("power_switch_power_watts{switch=\"hall \\\"heater\\\"\"} 1500\n"));
+        assert!(metrics.contains("power_switch_commands_total{switch=\"hall \\\"heater\\\"\"} 0\n"));
+    }
+}
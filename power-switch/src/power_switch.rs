@@ -3,6 +3,7 @@
 use crate::command::Command;
 use crate::response::Response;
 use std::fmt::{self, Display};
+use std::time::Instant;
 
 /// Describes state of power switch
 #[derive(Debug, Display, Clone)]
@@ -29,6 +30,10 @@ pub struct PowerSwitch {
     state: SwitchState,
     description: String,
     power_consumption: f64,
+    // Energy consumed before the switch was turned on the last time, in watt-hours
+    energy: f64,
+    // Time the switch was turned on, `None` if it is off
+    on_since: Option<Instant>,
 }
 
 impl PowerSwitch {
@@ -38,15 +43,24 @@ impl PowerSwitch {
             state: SwitchState::Off,
             description: String::from(description),
             power_consumption: 0.0,
+            energy: 0.0,
+            on_since: None,
         }
     }
 
     /// Creates new switch with full setting
     pub fn from_settings(description: &str, state: SwitchState, power_consumption: f64) -> Self {
+        let on_since = match state {
+            SwitchState::On => Some(Instant::now()),
+            SwitchState::Off => None,
+        };
+
         Self {
             state,
             description: String::from(description),
             power_consumption,
+            energy: 0.0,
+            on_since,
         }
     }
 
@@ -57,23 +71,47 @@ impl PowerSwitch {
 
     /// Switches state of the switch according `state` arg
     pub fn turn(&mut self, state: SwitchState) {
+        match (&self.state, &state) {
+            (SwitchState::Off, SwitchState::On) => self.on_since = Some(Instant::now()),
+            (SwitchState::On, SwitchState::Off) => {
+                self.energy = self.energy();
+                self.on_since = None;
+            }
+            _ => {}
+        }
+
         self.state = state;
     }
 
+    /// Returns `true` if the switch is on
+    pub fn is_enabled(&self) -> bool {
+        matches!(self.state, SwitchState::On)
+    }
+
     /// Returns current power consumption of the switch
     pub fn power_consumption(&self) -> f64 {
         self.power_consumption
     }
 
+    /// Returns energy consumed by the switch since its creation in watt-hours
+    pub fn energy(&self) -> f64 {
+        let hours = self
+            .on_since
+            .map(|since| since.elapsed().as_secs_f64() / 3600.0)
+            .unwrap_or_default();
+
+        self.energy + self.power_consumption * hours
+    }
+
     /// Process commands for the switch
     pub fn process_command(&mut self, command: Command) -> Response {
         match command {
             Command::TurnOn => {
-                self.state = SwitchState::On;
+                self.turn(SwitchState::On);
                 Response::Ok
             }
             Command::TurnOff => {
-                self.state = SwitchState::Off;
+                self.turn(SwitchState::Off);
                 Response::Ok
             }
             Command::IsEnabled => match self.state {
@@ -84,6 +122,7 @@ impl PowerSwitch {
                 SwitchState::On => Response::Power(self.power_consumption),
                 SwitchState::Off => Response::Power(0.0),
             },
+            Command::GetEnergy => Response::Energy(self.energy()),
             Command::Unknown => {
                 println!("Unknown command received");
                 Response::Unknown
@@ -117,4 +156,23 @@ mod tests {
 
         assert_eq!(power_switch_info, POWER_SWITCH_INFO);
     }
+
+    #[test]
+    fn test_energy_is_consumed_while_on() {
+        let mut power_switch = PowerSwitch::from_settings("Heater", SwitchState::Off, 3600.0);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(power_switch.energy(), 0.0);
+
+        power_switch.process_command(Command::TurnOn);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        power_switch.process_command(Command::TurnOff);
+
+        // 3600 W during 20 ms is 0.02 Wh
+        let energy = power_switch.energy();
+        assert!(energy >= 0.02, "{energy}");
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(power_switch.energy(), energy);
+    }
 }
@@ -8,6 +8,8 @@ pub enum Response {
     Enabled,
     Disabled,
     Power(f64),
+    /// Consumed energy in watt-hours
+    Energy(f64),
     Unknown,
 }
 
@@ -22,6 +24,11 @@ impl From<[u8; 9]> for Response {
                 buf.copy_from_slice(&bytes[1..]);
                 Self::Power(f64::from_be_bytes(buf))
             }
+            [4, ..] => {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(&bytes[1..]);
+                Self::Energy(f64::from_be_bytes(buf))
+            }
             _ => Self::Unknown,
         }
     }
@@ -38,6 +45,10 @@ impl From<Response> for [u8; 9] {
                 buffer[0] = 3;
                 buffer[1..].copy_from_slice(&pwr.to_be_bytes())
             }
+            Response::Energy(energy) => {
+                buffer[0] = 4;
+                buffer[1..].copy_from_slice(&energy.to_be_bytes())
+            }
             Response::Unknown => buffer[0] = 255,
         };
         buffer
@@ -51,6 +62,7 @@ impl fmt::Display for Response {
             Response::Enabled => write!(f, "Enabled"),
             Response::Disabled => write!(f, "Disabled"),
             Response::Power(power) => write!(f, "Power: {}", power),
+            Response::Energy(energy) => write!(f, "Energy: {} Wh", energy),
             Response::Unknown => write!(f, "Unknown"),
         }
     }
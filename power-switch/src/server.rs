@@ -0,0 +1,173 @@
+//! Module describes server which serves power switch over TCP
+
+use crate::command::Command;
+use crate::power_switch::PowerSwitch;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Describes counters of the server
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    connections: AtomicU64,
+    active_connections: AtomicU64,
+    commands: AtomicU64,
+    unknown_commands: AtomicU64,
+}
+
+impl ServerStats {
+    /// Returns number of accepted connections
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    /// Returns number of currently connected clients
+    pub fn active_connections(&self) -> u64 {
+        self.active_connections.load(Ordering::Relaxed)
+    }
+
+    /// Returns number of processed commands
+    pub fn commands(&self) -> u64 {
+        self.commands.load(Ordering::Relaxed)
+    }
+
+    /// Returns number of processed commands unknown to the switch
+    pub fn unknown_commands(&self) -> u64 {
+        self.unknown_commands.load(Ordering::Relaxed)
+    }
+}
+
+/// Describes server which processes commands of clients for power switch
+pub struct Server {
+    tcp: TcpListener,
+    power_switch: Arc<Mutex<PowerSwitch>>,
+    stats: Arc<ServerStats>,
+}
+
+impl Server {
+    /// Creates server for `power_switch` listening at `addrs`
+    pub fn new(addrs: impl ToSocketAddrs, power_switch: PowerSwitch) -> Result<Self, &'static str> {
+        let tcp = TcpListener::bind(addrs).map_err(|_| "Failed to bind tcp listener")?;
+        Ok(Self {
+            tcp,
+            power_switch: Arc::new(Mutex::new(power_switch)),
+            stats: Arc::new(ServerStats::default()),
+        })
+    }
+
+    /// Returns the served power switch
+    pub fn power_switch(&self) -> Arc<Mutex<PowerSwitch>> {
+        self.power_switch.clone()
+    }
+
+    /// Returns counters of the server
+    pub fn stats(&self) -> Arc<ServerStats> {
+        self.stats.clone()
+    }
+
+    /// Returns address the server listens at
+    pub fn local_addr(&self) -> Result<SocketAddr, &'static str> {
+        self.tcp
+            .local_addr()
+            .map_err(|_| "Failed to get address of tcp listener")
+    }
+
+    /// Serves clients, each client in its own thread
+    pub fn run(&self) -> Result<(), &str> {
+        for connection in self.tcp.incoming() {
+            let stream = match connection {
+                Ok(stream) => stream,
+                Err(e) => {
+                    println!("Can't receive connection: {e}");
+                    continue;
+                }
+            };
+
+            let peer = stream
+                .peer_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_else(|_| "unknown".into());
+
+            println!("Client connected: {peer}");
+
+            let power_switch = self.power_switch.clone();
+            let stats = self.stats.clone();
+
+            stats.connections.fetch_add(1, Ordering::Relaxed);
+            stats.active_connections.fetch_add(1, Ordering::Relaxed);
+
+            thread::spawn(move || {
+                match handle_connection(stream, power_switch, &stats) {
+                    Ok(_) => println!("Client disconnected: {peer}"),
+                    Err(e) => println!("Client {peer}: {e}"),
+                };
+
+                stats.active_connections.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    power_switch: Arc<Mutex<PowerSwitch>>,
+    stats: &ServerStats,
+) -> Result<(), &'static str> {
+    let mut in_buffer = [0u8];
+    while stream.read_exact(&mut in_buffer).is_ok() {
+        stats.commands.fetch_add(1, Ordering::Relaxed);
+        if let Command::Unknown = in_buffer[0].into() {
+            stats.unknown_commands.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let response = {
+            let mut ps = power_switch.lock().unwrap();
+            ps.process_command(in_buffer[0].into())
+        };
+
+        let response_buf: [u8; 9] = response.into();
+        if stream.write_all(&response_buf).is_err() {
+            return Err("Failed to send response");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_stats_count_connections_and_commands() {
+        let server = Server::new("127.0.0.1:0", PowerSwitch::new("Heater")).unwrap();
+        let address = server.local_addr().unwrap();
+        let stats = server.stats();
+        thread::spawn(move || {
+            _ = server.run();
+        });
+
+        {
+            let mut client = Client::new(address).unwrap();
+            client.run_command(Command::TurnOn).unwrap();
+            client.run_command(Command::GetPower).unwrap();
+            client.run_command(Command::Unknown).unwrap();
+            assert_eq!(stats.connections(), 1);
+        }
+
+        // Connection is closed by the server thread after the client is dropped
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while stats.active_connections() > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(stats.active_connections(), 0);
+        assert_eq!(stats.commands(), 3);
+        assert_eq!(stats.unknown_commands(), 1);
+    }
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use discovery::DEFAULT_GROUP;
+use smart_house::{device_discovery::DeviceDiscovery, errors, smart_house::SmartHouse};
+
+fn main() -> errors::Result<()> {
+    let mut discovery = DeviceDiscovery::new(DEFAULT_GROUP)?;
+
+    println!("Scanning {DEFAULT_GROUP}...");
+
+    for device in discovery.scan(Duration::from_secs(5))? {
+        println!("Found {device}");
+    }
+
+    let mut smart_house = SmartHouse::new("Discovered house");
+
+    for (room_name, device_name) in discovery.add_to_house(&mut smart_house, "Unassigned") {
+        println!("Added {device_name} to {room_name}");
+    }
+
+    Ok(())
+}
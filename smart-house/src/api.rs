@@ -0,0 +1,1279 @@
+//! Module describes HTTP REST API of the smart house.
+//!
+//! Endpoints:
+//! - `GET /rooms`, `POST /rooms` with `{"name": ...}`
+//! - `GET /rooms/{room}`, `DELETE /rooms/{room}`,
+//!   `PATCH /rooms/{room}` with `{"name": ...}` renames the room
+//! - `GET /rooms/{room}/devices`, `POST /rooms/{room}/devices`
+//!   with `{"name": ..., "binding": {"kind": "power_switch", "address": ...}}`
+//! - `GET /rooms/{room}/devices/{device}`, `DELETE /rooms/{room}/devices/{device}`
+//! - `PATCH /rooms/{room}/devices/{device}` with `{"name": ..., "room": ...}`
+//!   renames the device or moves it to another room, both fields are optional.
+//!   Groups, scenes, power budget, automation rules and scheduled jobs
+//!   follow renamed devices.
+//! - `GET /rooms/{room}/devices/{device}/state`
+//! - `POST /rooms/{room}/devices/{device}/on`, `POST /rooms/{room}/devices/{device}/off`
+//! - `GET /devices` lists devices of all rooms with their stable identifiers
+//! - `GET /devices/{id}`, `PATCH /devices/{id}`, `GET /devices/{id}/state`,
+//!   `POST /devices/{id}/on`, `POST /devices/{id}/off` address device by identifier,
+//!   which doesn't change when device is renamed or moved
+//! - `GET /report?from={ms}&to={ms}` includes cost of energy consumed by switches
+//!   over the period if history is recorded and tariff is set,
+//!   period is the whole history by default
+//! - `GET /costs?from={ms}&to={ms}&format=csv` returns cost of energy per switch,
+//!   room and house in JSON or CSV
+//! - `GET /groups`, `GET /groups/{group}`, `DELETE /groups/{group}`
+//! - `POST /groups/{group}/devices` with `{"room": ..., "device": ...}`,
+//!   `DELETE /groups/{group}/devices/{room}/{device}`
+//! - `GET /groups/{group}/summary` returns aggregated states of devices of the group
+//! - `POST /groups/{group}/on`, `POST /groups/{group}/off` switch all power switches
+//!   of the group and return result of each device
+//! - `GET /budget` returns power budget with switches turned off to keep it,
+//!   `PUT /budget` with `{"limit": ..., "margin": ..., "priorities": [{"room": ..., "device": ..., "priority": ...}]}`,
+//!   `DELETE /budget`
+//! - `GET /scenes`, `POST /scenes` with `{"name": ..., "states": [{"room": ..., "device": ..., "enabled": ...}]}`
+//! - `GET /scenes/{scene}`, `DELETE /scenes/{scene}`
+//! - `POST /scenes/{scene}/apply?rollback=true` switches devices of the scene
+//!   and returns result of each device, rollback is optional
+//! - `GET /events?room={room}&device={device}` streams changes of device
+//!   states as Server-Sent Events, both filters are optional
+//! - `GET /metrics` exports the last observed states of devices
+//!   in Prometheus text format
+//! - `GET /history?room={room}&device={device}&metric={metric}&from={ms}&to={ms}&step={ms}`
+//!   returns recorded series of samples if history is enabled, all parameters are optional,
+//!   history of renamed and moved devices is found by their current names
+//! - `GET /automation/log` returns the latest fired rules and ran jobs
+//!   if automation or scheduler is enabled
+//! - `GET /schedule` returns jobs with times of their last and next runs,
+//!   `POST /schedule/jobs` with job, `DELETE /schedule/jobs/{name}`
+//!   if scheduler is enabled
+//!
+//! Errors are returned as `{"error": ...}` with corresponding status code.
+//! If house file is set, the house is saved to it after each change made
+//! through the API, so identifiers of devices survive restart.
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{mpsc::RecvTimeoutError, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    automation::AuditLog,
+    errors::{self, Error},
+    events::{self, EventFilter, HouseEvents},
+    groups::{self, GroupMember},
+    house_file::HouseFile,
+    load_shedding::LoadController,
+    metrics,
+    recorder::{self, Query, SampleStore},
+    registry::{DeviceRegistry, DeviceState},
+    scenes,
+    scheduler::{self, Job, ScheduleStore},
+    smart_house::{DeviceBinding, DeviceId, GroupMembers, PowerBudget, Room, Scene, SmartHouse},
+    tariff::{self, CostReport, Tariff},
+};
+
+/// Describes error returned to HTTP client
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        let status = match err {
+            Error::DeviceNotFoundError { .. }
+            | Error::DeviceIdNotFoundError { .. }
+            | Error::RoomNotFoundError { .. }
+            | Error::GroupNotFoundError { .. }
+            | Error::SceneNotFoundError { .. } => 404,
+            Error::DeviceNotBoundError { .. }
+            | Error::RoomExistsError { .. }
+            | Error::DeviceExistsError { .. } => 409,
+            Error::UnsupportedCommandError => 400,
+            Error::DeviceUnavailableError(_) => 502,
+            _ => 500,
+        };
+
+        Self::new(status, err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::new(400, format!("Invalid request body: {err}"))
+    }
+}
+
+type ApiResult = Result<(u16, Value), ApiError>;
+
+/// Describes how often idle event stream is checked for disconnected client
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Describes body of request for adding room
+#[derive(Deserialize)]
+struct NewRoom {
+    name: String,
+}
+
+/// Describes body of request for adding device
+#[derive(Deserialize)]
+struct NewDevice {
+    name: String,
+    #[serde(default)]
+    binding: Option<DeviceBinding>,
+}
+
+/// Describes body of request for renaming device or moving it to another room
+#[derive(Deserialize)]
+struct DeviceChange {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    room: Option<String>,
+}
+
+/// Describes device of the room in responses
+#[derive(Serialize)]
+struct DeviceInfo {
+    id: Option<DeviceId>,
+    room: String,
+    name: String,
+    binding: Option<DeviceBinding>,
+}
+
+impl DeviceInfo {
+    fn new(house: &SmartHouse, room_name: &str, device_name: &str) -> Self {
+        Self {
+            id: house.device_id(room_name, device_name),
+            room: room_name.to_owned(),
+            name: device_name.to_owned(),
+            binding: house.binding(room_name, device_name).cloned(),
+        }
+    }
+}
+
+/// Describes device in the report
+#[derive(Serialize)]
+struct DeviceReport {
+    id: Option<DeviceId>,
+    name: String,
+    binding: Option<DeviceBinding>,
+    state: Option<DeviceState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Describes room in the report
+#[derive(Serialize)]
+struct RoomReport {
+    name: String,
+    devices: Vec<DeviceReport>,
+}
+
+/// Describes data shared by all handles of the server
+struct Inner {
+    http: Server,
+    house: Arc<Mutex<SmartHouse>>,
+    registry: Arc<DeviceRegistry>,
+    events: Arc<HouseEvents>,
+    history: Option<Arc<SampleStore>>,
+    audit: Option<Arc<AuditLog>>,
+    schedule: Option<Arc<ScheduleStore>>,
+    load_controller: Option<Arc<LoadController>>,
+    tariff: Option<Tariff>,
+    house_file: Option<PathBuf>,
+    /// Content of the house file written the last time
+    saved_house: Mutex<Option<HouseFile>>,
+}
+
+/// Describes HTTP server exposing the smart house.
+/// Each request is handled in its own thread.
+#[derive(Clone)]
+pub struct ApiServer {
+    inner: Arc<Inner>,
+}
+
+impl ApiServer {
+    /// Creates server listening at `address` for given `house`,
+    /// devices are reached through `registry`
+    pub fn new(
+        address: &str,
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+    ) -> errors::Result<Self> {
+        let http = Server::http(address).map_err(|err| Error::ServerError(err.to_string()))?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                http,
+                house,
+                registry,
+                events: Arc::new(HouseEvents::new()),
+                history: None,
+                audit: None,
+                schedule: None,
+                load_controller: None,
+                tariff: None,
+                house_file: None,
+                saved_house: Mutex::new(None),
+            }),
+        })
+    }
+
+    /// Serves history of device readings recorded to the `store`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_history(mut self, store: Arc<SampleStore>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("History must be set before the server is cloned")
+            .history = Some(store);
+        self
+    }
+
+    /// Serves audit log of automation rules.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_automation(mut self, audit: Arc<AuditLog>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Automation must be set before the server is cloned")
+            .audit = Some(audit);
+        self
+    }
+
+    /// Serves and changes schedule of jobs kept in the `store`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_scheduler(mut self, store: Arc<ScheduleStore>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Scheduler must be set before the server is cloned")
+            .schedule = Some(store);
+        self
+    }
+
+    /// Serves switches turned off by the `controller` to keep power budget.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_load_shedding(mut self, controller: Arc<LoadController>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Load shedding must be set before the server is cloned")
+            .load_controller = Some(controller);
+        self
+    }
+
+    /// Serves cost of energy recorded to history by the `tariff`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_tariff(mut self, tariff: Tariff) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Tariff must be set before the server is cloned")
+            .tariff = Some(tariff);
+        self
+    }
+
+    /// Saves the house to file at `path` after each change made through the API.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server has already been cloned
+    pub fn with_house_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let inner = Arc::get_mut(&mut self.inner)
+            .expect("House file must be set before the server is cloned");
+        let saved = HouseFile::from(&*inner.house.lock().unwrap());
+
+        inner.house_file = Some(path.into());
+        *inner.saved_house.get_mut().unwrap() = Some(saved);
+        self
+    }
+
+    /// Returns address the server listens at
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.inner.http.server_addr().to_ip()
+    }
+
+    /// Returns source of events streamed to clients.
+    /// Changes made through the API are published automatically,
+    /// other changes are observed by [`crate::monitor::StateMonitor`].
+    pub fn events(&self) -> Arc<HouseEvents> {
+        self.inner.events.clone()
+    }
+
+    /// Serves requests until [`ApiServer::shutdown`] is called
+    pub fn run(&self) {
+        for request in self.inner.http.incoming_requests() {
+            let server = self.clone();
+            thread::spawn(move || server.handle(request));
+        }
+    }
+
+    /// Stops serving requests
+    pub fn shutdown(&self) {
+        self.inner.http.unblock();
+    }
+
+    fn handle(&self, mut request: Request) {
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+
+        if *request.method() == Method::Get && path.trim_end_matches('/') == "/events" {
+            match parse_filter(query) {
+                Ok(filter) => self.stream_events(request, filter),
+                Err(err) => Self::respond(request, Err(err)),
+            }
+            return;
+        }
+
+        if *request.method() == Method::Get && path.trim_end_matches('/') == "/metrics" {
+            self.serve_metrics(request);
+            return;
+        }
+
+        if *request.method() == Method::Get
+            && path.trim_end_matches('/') == "/costs"
+            && query.split('&').any(|pair| pair == "format=csv")
+        {
+            match self.costs(query) {
+                Ok(report) => Self::respond_csv(request, report.to_csv()),
+                Err(err) => Self::respond(request, Err(err)),
+            }
+            return;
+        }
+
+        let mut body = String::new();
+
+        let result = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => self.route(request.method(), request.url(), &body),
+            Err(_) => Err(ApiError::new(400, "Failed to read request body")),
+        };
+
+        // The change is already made, so the failure doesn't fail the request,
+        // the house is saved again after the next change
+        if result.is_ok() && *request.method() != Method::Get {
+            if let Err(err) = self.save_house() {
+                println!("Failed to save house: {err}");
+            }
+        }
+
+        Self::respond(request, result);
+    }
+
+    /// Writes the house to the house file if it is set and the house has changed
+    fn save_house(&self) -> errors::Result<()> {
+        let Some(path) = &self.inner.house_file else {
+            return Ok(());
+        };
+
+        let house = self.inner.house.lock().unwrap();
+        let file = HouseFile::from(&*house);
+        let mut saved = self.inner.saved_house.lock().unwrap();
+
+        if saved.as_ref() != Some(&file) {
+            file.save(path)?;
+            *saved = Some(file);
+        }
+
+        Ok(())
+    }
+
+    fn respond(request: Request, result: ApiResult) {
+        let (status, value) =
+            result.unwrap_or_else(|err| (err.status, json!({ "error": err.message })));
+
+        let body = if status == 204 {
+            String::new()
+        } else {
+            value.to_string()
+        };
+
+        let header =
+            Header::from_bytes("Content-Type", "application/json").expect("Header is valid");
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+
+        if let Err(err) = request.respond(response) {
+            println!("Failed to send response: {err}");
+        }
+    }
+
+    fn respond_csv(request: Request, body: String) {
+        let header = Header::from_bytes("Content-Type", "text/csv").expect("Header is valid");
+
+        if let Err(err) = request.respond(Response::from_string(body).with_header(header)) {
+            println!("Failed to send response: {err}");
+        }
+    }
+
+    /// Responds with metrics of devices. States are not queried,
+    /// they are taken from [`HouseEvents`] updated by polling of devices.
+    fn serve_metrics(&self, request: Request) {
+        let house_name = self.inner.house.lock().unwrap().get_name().to_owned();
+        let body = metrics::render(&house_name, &self.inner.events.states(), events::now());
+
+        let header =
+            Header::from_bytes("Content-Type", metrics::CONTENT_TYPE).expect("Header is valid");
+
+        if let Err(err) = request.respond(Response::from_string(body).with_header(header)) {
+            println!("Failed to send response: {err}");
+        }
+    }
+
+    /// Writes events to the client until it disconnects.
+    /// Response is written directly to the connection,
+    /// so each event is delivered without buffering.
+    fn stream_events(&self, request: Request, filter: EventFilter) {
+        let events = self.inner.events.subscribe(filter);
+        let mut writer = request.into_writer();
+
+        let header = "HTTP/1.1 200 OK\r\n\
+                      Content-Type: text/event-stream\r\n\
+                      Cache-Control: no-cache\r\n\
+                      Connection: close\r\n\r\n";
+
+        let mut chunk = header.to_owned();
+
+        loop {
+            if writer
+                .write_all(chunk.as_bytes())
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+
+            chunk = match events.recv_timeout(KEEP_ALIVE_INTERVAL) {
+                Ok(event) => match serde_json::to_string(&event) {
+                    Ok(data) => format!("data: {data}\n\n"),
+                    Err(err) => {
+                        println!("Failed to serialize event: {err}");
+                        continue;
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_owned(),
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+        }
+    }
+
+    fn route(&self, method: &Method, url: &str, body: &str) -> ApiResult {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(decode_segment)
+            .collect::<Result<Vec<_>, _>>()?;
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        match (method, segments.as_slice()) {
+            (Method::Get, ["rooms"]) => self.rooms(),
+            (Method::Post, ["rooms"]) => self.add_room(body),
+            (Method::Get, ["rooms", room]) => self.room(room),
+            (Method::Delete, ["rooms", room]) => self.remove_room(room),
+            (Method::Patch, ["rooms", room]) => self.rename_room(room, body),
+            (Method::Get, ["rooms", room, "devices"]) => self.devices(room),
+            (Method::Post, ["rooms", room, "devices"]) => self.add_device(room, body),
+            (Method::Get, ["rooms", room, "devices", device]) => self.device(room, device),
+            (Method::Delete, ["rooms", room, "devices", device]) => {
+                self.remove_device(room, device)
+            }
+            (Method::Patch, ["rooms", room, "devices", device]) => {
+                self.change_device(room, device, body)
+            }
+            (Method::Get, ["rooms", room, "devices", device, "state"]) => self.state(room, device),
+            (Method::Post, ["rooms", room, "devices", device, "on"]) => {
+                self.turn(room, device, true)
+            }
+            (Method::Post, ["rooms", room, "devices", device, "off"]) => {
+                self.turn(room, device, false)
+            }
+            (Method::Get, ["devices"]) => self.all_devices(),
+            (Method::Get, ["devices", id]) => {
+                let (room, device) = self.locate(id)?;
+                self.device(&room, &device)
+            }
+            (Method::Patch, ["devices", id]) => {
+                let (room, device) = self.locate(id)?;
+                self.change_device(&room, &device, body)
+            }
+            (Method::Get, ["devices", id, "state"]) => {
+                let (room, device) = self.locate(id)?;
+                self.state(&room, &device)
+            }
+            (Method::Post, ["devices", id, "on"]) => {
+                let (room, device) = self.locate(id)?;
+                self.turn(&room, &device, true)
+            }
+            (Method::Post, ["devices", id, "off"]) => {
+                let (room, device) = self.locate(id)?;
+                self.turn(&room, &device, false)
+            }
+            (Method::Get, ["report"]) => self.report(query),
+            (Method::Get, ["costs"]) => Ok((200, serde_json::to_value(self.costs(query)?)?)),
+            (Method::Get, ["groups"]) => self.groups(),
+            (Method::Get, ["groups", group]) => self.group(group),
+            (Method::Delete, ["groups", group]) => self.remove_group(group),
+            (Method::Post, ["groups", group, "devices"]) => self.add_to_group(group, body),
+            (Method::Delete, ["groups", group, "devices", room, device]) => {
+                self.remove_from_group(group, room, device)
+            }
+            (Method::Get, ["groups", group, "summary"]) => self.group_summary(group),
+            (Method::Post, ["groups", group, "on"]) => self.turn_group(group, true),
+            (Method::Post, ["groups", group, "off"]) => self.turn_group(group, false),
+            (Method::Get, ["budget"]) => self.power_budget(),
+            (Method::Put, ["budget"]) => self.set_power_budget(body),
+            (Method::Delete, ["budget"]) => self.remove_power_budget(),
+            (Method::Get, ["scenes"]) => self.scenes(),
+            (Method::Post, ["scenes"]) => self.add_scene(body),
+            (Method::Get, ["scenes", scene]) => self.scene(scene),
+            (Method::Delete, ["scenes", scene]) => self.remove_scene(scene),
+            (Method::Post, ["scenes", scene, "apply"]) => self.apply_scene(scene, query),
+            (Method::Get, ["history"]) => self.history(query),
+            (Method::Get, ["automation", "log"]) => self.automation_log(),
+            (Method::Get, ["schedule"]) => self.schedule(),
+            (Method::Post, ["schedule", "jobs"]) => self.add_job(body),
+            (Method::Delete, ["schedule", "jobs", name]) => self.remove_job(name),
+            _ => Err(ApiError::new(404, "Unknown endpoint")),
+        }
+    }
+
+    fn rooms(&self) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let rooms: Vec<&Room> = house.get_rooms().values().collect();
+        Ok((200, serde_json::to_value(rooms)?))
+    }
+
+    fn add_room(&self, body: &str) -> ApiResult {
+        let new_room: NewRoom = serde_json::from_str(body)?;
+        let room = Room::new(&new_room.name);
+        let value = serde_json::to_value(&room)?;
+
+        if !self.inner.house.lock().unwrap().add_room(room) {
+            return Err(ApiError::new(409, "Room already exists"));
+        }
+
+        Ok((201, value))
+    }
+
+    fn room(&self, room_name: &str) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let room = house
+            .get_rooms()
+            .get(room_name)
+            .ok_or_else(|| room_not_found(room_name))?;
+
+        Ok((200, serde_json::to_value(room)?))
+    }
+
+    fn remove_room(&self, room_name: &str) -> ApiResult {
+        if !self.inner.house.lock().unwrap().remove_room(room_name) {
+            return Err(room_not_found(room_name).into());
+        }
+
+        Ok((204, Value::Null))
+    }
+
+    fn rename_room(&self, room_name: &str, body: &str) -> ApiResult {
+        let new_room: NewRoom = serde_json::from_str(body)?;
+        let (devices, value) = {
+            let mut house = self.inner.house.lock().unwrap();
+            let devices: Vec<_> = house
+                .devices(room_name)
+                .ok_or_else(|| room_not_found(room_name))?
+                .iter()
+                .cloned()
+                .collect();
+
+            house.rename_room(room_name, &new_room.name)?;
+
+            (
+                devices,
+                serde_json::to_value(&house.get_rooms()[&new_room.name])?,
+            )
+        };
+
+        if room_name != new_room.name {
+            for device in devices {
+                self.follow_device(room_name, &device, &new_room.name, &device);
+            }
+        }
+
+        Ok((200, value))
+    }
+
+    fn devices(&self, room_name: &str) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let devices: Vec<DeviceInfo> = house
+            .devices(room_name)
+            .ok_or_else(|| room_not_found(room_name))?
+            .iter()
+            .map(|device_name| DeviceInfo::new(&house, room_name, device_name))
+            .collect();
+
+        Ok((200, serde_json::to_value(devices)?))
+    }
+
+    fn add_device(&self, room_name: &str, body: &str) -> ApiResult {
+        let new_device: NewDevice = serde_json::from_str(body)?;
+        let mut house = self.inner.house.lock().unwrap();
+
+        if house.devices(room_name).is_none() {
+            return Err(room_not_found(room_name).into());
+        }
+
+        if !house.add_device(room_name, &new_device.name) {
+            return Err(ApiError::new(409, "Device already exists"));
+        }
+
+        if let Some(binding) = new_device.binding {
+            _ = house.bind_device(room_name, &new_device.name, binding);
+        }
+
+        let device = DeviceInfo::new(&house, room_name, &new_device.name);
+
+        Ok((201, serde_json::to_value(device)?))
+    }
+
+    fn device(&self, room_name: &str, device_name: &str) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        Self::check_device(&house, room_name, device_name)?;
+
+        let device = DeviceInfo::new(&house, room_name, device_name);
+
+        Ok((200, serde_json::to_value(device)?))
+    }
+
+    fn remove_device(&self, room_name: &str, device_name: &str) -> ApiResult {
+        let mut house = self.inner.house.lock().unwrap();
+        Self::check_device(&house, room_name, device_name)?;
+
+        _ = house.remove_device(room_name, device_name);
+
+        Ok((204, Value::Null))
+    }
+
+    fn change_device(&self, room_name: &str, device_name: &str, body: &str) -> ApiResult {
+        let change: DeviceChange = serde_json::from_str(body)?;
+        let new_room = change.room.unwrap_or_else(|| room_name.to_owned());
+        let new_name = change.name.unwrap_or_else(|| device_name.to_owned());
+
+        let device = {
+            let mut house = self.inner.house.lock().unwrap();
+            house.relocate_device(room_name, device_name, &new_room, &new_name)?;
+
+            DeviceInfo::new(&house, &new_room, &new_name)
+        };
+
+        if (room_name, device_name) != (new_room.as_str(), new_name.as_str()) {
+            self.follow_device(room_name, device_name, &new_room, &new_name);
+        }
+
+        Ok((200, serde_json::to_value(device)?))
+    }
+
+    /// Makes observed states, automation rules, scheduled jobs
+    /// and switches turned off by the budget follow renamed device
+    fn follow_device(&self, room_name: &str, device_name: &str, new_room: &str, new_name: &str) {
+        self.inner
+            .events
+            .rename(room_name, device_name, new_room, new_name);
+
+        if let Some(controller) = &self.inner.load_controller {
+            controller.rename_device(room_name, device_name, new_room, new_name);
+        }
+
+        // The device is already renamed, so the failure doesn't fail the request
+        if let Some(store) = &self.inner.schedule {
+            if let Err(err) = store.rename_device(room_name, device_name, new_room, new_name) {
+                println!("Failed to update schedule: {err}");
+            }
+        }
+    }
+
+    fn all_devices(&self) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let devices: Vec<_> = house
+            .get_device_ids()
+            .keys()
+            .map(|(room_name, device_name)| DeviceInfo::new(&house, room_name, device_name))
+            .collect();
+
+        Ok((200, serde_json::to_value(devices)?))
+    }
+
+    /// Returns room name and device name of device by its identifier
+    fn locate(&self, id: &str) -> Result<(String, String), ApiError> {
+        let device_id = DeviceId::parse_str(id)
+            .map_err(|_| ApiError::new(400, format!("Invalid device id: {id}")))?;
+        let house = self.inner.house.lock().unwrap();
+        let (room_name, device_name) = house
+            .find_device(device_id)
+            .ok_or_else(|| Error::DeviceIdNotFoundError { id: id.to_owned() })?;
+
+        Ok((room_name.to_owned(), device_name.to_owned()))
+    }
+
+    fn state(&self, room_name: &str, device_name: &str) -> ApiResult {
+        let binding = self.bound_device(room_name, device_name)?;
+        let result = self.inner.registry.state(&binding);
+        self.inner.events.observe(room_name, device_name, &result);
+
+        Ok((200, serde_json::to_value(result?)?))
+    }
+
+    fn turn(&self, room_name: &str, device_name: &str, enabled: bool) -> ApiResult {
+        let binding = self.bound_device(room_name, device_name)?;
+        let result = self.inner.registry.turn(&binding, enabled);
+
+        if !matches!(result, Err(Error::UnsupportedCommandError)) {
+            self.inner.events.observe(room_name, device_name, &result);
+        }
+
+        Ok((200, serde_json::to_value(result?)?))
+    }
+
+    fn report(&self, query: &str) -> ApiResult {
+        let costs = match (&self.inner.history, &self.inner.tariff) {
+            (Some(_), Some(_)) => Some(self.costs(query)?),
+            _ => {
+                parse_period(query)?;
+                None
+            }
+        };
+
+        let (name, mut rooms) = {
+            let house = self.inner.house.lock().unwrap();
+            let rooms: Vec<RoomReport> = house
+                .get_rooms()
+                .values()
+                .map(|room| RoomReport {
+                    name: room.get_name().to_owned(),
+                    devices: room
+                        .get_devices()
+                        .iter()
+                        .map(|device_name| DeviceReport {
+                            id: house.device_id(room.get_name(), device_name),
+                            name: device_name.clone(),
+                            binding: house.binding(room.get_name(), device_name).cloned(),
+                            state: None,
+                            error: None,
+                        })
+                        .collect(),
+                })
+                .collect();
+            (house.get_name().to_owned(), rooms)
+        };
+
+        // Devices are queried without holding the lock of the house
+        for device in rooms.iter_mut().flat_map(|room| room.devices.iter_mut()) {
+            if let Some(binding) = &device.binding {
+                match self.inner.registry.state(binding) {
+                    Ok(state) => device.state = Some(state),
+                    Err(err) => device.error = Some(err.to_string()),
+                }
+            }
+        }
+
+        let mut value = json!({ "name": name, "rooms": rooms });
+        if let Some(costs) = costs {
+            value["costs"] = serde_json::to_value(costs)?;
+        }
+
+        Ok((200, value))
+    }
+
+    fn groups(&self) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let groups: BTreeMap<&String, Vec<GroupMember>> = house
+            .get_groups()
+            .iter()
+            .map(|(group_name, members)| (group_name, group_members(members)))
+            .collect();
+
+        Ok((200, serde_json::to_value(groups)?))
+    }
+
+    fn group(&self, group_name: &str) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let members = house
+            .group(group_name)
+            .ok_or_else(|| group_not_found(group_name))?;
+
+        Ok((200, serde_json::to_value(group_members(members))?))
+    }
+
+    fn remove_group(&self, group_name: &str) -> ApiResult {
+        if !self.inner.house.lock().unwrap().remove_group(group_name) {
+            return Err(group_not_found(group_name).into());
+        }
+
+        Ok((204, Value::Null))
+    }
+
+    fn add_to_group(&self, group_name: &str, body: &str) -> ApiResult {
+        let member: GroupMember = serde_json::from_str(body)?;
+        let mut house = self.inner.house.lock().unwrap();
+        Self::check_device(&house, &member.room, &member.device)?;
+
+        if !house.add_to_group(group_name, &member.room, &member.device) {
+            return Err(ApiError::new(409, "Device is already in the group"));
+        }
+
+        Ok((201, serde_json::to_value(member)?))
+    }
+
+    fn remove_from_group(&self, group_name: &str, room_name: &str, device_name: &str) -> ApiResult {
+        let mut house = self.inner.house.lock().unwrap();
+
+        if house.group(group_name).is_none() {
+            return Err(group_not_found(group_name).into());
+        }
+
+        if !house.remove_from_group(group_name, room_name, device_name) {
+            return Err(ApiError::new(404, "Device is not in the group"));
+        }
+
+        Ok((204, Value::Null))
+    }
+
+    fn group_summary(&self, group_name: &str) -> ApiResult {
+        let summary = groups::summary(group_name, &self.inner.house, &self.inner.registry)?;
+        Ok((200, serde_json::to_value(summary)?))
+    }
+
+    fn turn_group(&self, group_name: &str, enabled: bool) -> ApiResult {
+        let report = groups::turn(
+            group_name,
+            &self.inner.house,
+            &self.inner.registry,
+            &self.inner.events,
+            enabled,
+        )?;
+        let status = if report.applied { 200 } else { 502 };
+
+        Ok((status, serde_json::to_value(report)?))
+    }
+
+    fn power_budget(&self) -> ApiResult {
+        let budget = self
+            .inner
+            .house
+            .lock()
+            .unwrap()
+            .power_budget()
+            .cloned()
+            .ok_or_else(|| ApiError::new(404, "Power budget is not set"))?;
+        let shed = self
+            .inner
+            .load_controller
+            .as_ref()
+            .map(|controller| controller.shed())
+            .unwrap_or_default();
+
+        let mut value = serde_json::to_value(budget)?;
+        value["shed"] = serde_json::to_value(shed)?;
+
+        Ok((200, value))
+    }
+
+    fn set_power_budget(&self, body: &str) -> ApiResult {
+        let budget: PowerBudget = serde_json::from_str(body)?;
+        let value = serde_json::to_value(&budget)?;
+
+        self.inner
+            .house
+            .lock()
+            .unwrap()
+            .set_power_budget(Some(budget));
+
+        Ok((200, value))
+    }
+
+    fn remove_power_budget(&self) -> ApiResult {
+        let mut house = self.inner.house.lock().unwrap();
+
+        if house.power_budget().is_none() {
+            return Err(ApiError::new(404, "Power budget is not set"));
+        }
+
+        house.set_power_budget(None);
+
+        Ok((204, Value::Null))
+    }
+
+    fn scenes(&self) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let scenes: Vec<&Scene> = house.get_scenes().values().collect();
+        Ok((200, serde_json::to_value(scenes)?))
+    }
+
+    fn add_scene(&self, body: &str) -> ApiResult {
+        let scene: Scene = serde_json::from_str(body)?;
+        let value = serde_json::to_value(&scene)?;
+
+        if !self.inner.house.lock().unwrap().add_scene(scene) {
+            return Err(ApiError::new(409, "Scene already exists"));
+        }
+
+        Ok((201, value))
+    }
+
+    fn scene(&self, scene_name: &str) -> ApiResult {
+        let house = self.inner.house.lock().unwrap();
+        let scene = house
+            .scene(scene_name)
+            .ok_or_else(|| scene_not_found(scene_name))?;
+
+        Ok((200, serde_json::to_value(scene)?))
+    }
+
+    fn remove_scene(&self, scene_name: &str) -> ApiResult {
+        if !self.inner.house.lock().unwrap().remove_scene(scene_name) {
+            return Err(scene_not_found(scene_name).into());
+        }
+
+        Ok((204, Value::Null))
+    }
+
+    fn apply_scene(&self, scene_name: &str, query: &str) -> ApiResult {
+        let mut rollback = false;
+
+        for (key, value) in parse_query(query)? {
+            match (key, value.as_str()) {
+                ("rollback", "true") => rollback = true,
+                ("rollback", "false") => rollback = false,
+                _ => return Err(ApiError::new(400, format!("Unknown parameter: {key}"))),
+            }
+        }
+
+        let scene = self
+            .inner
+            .house
+            .lock()
+            .unwrap()
+            .scene(scene_name)
+            .cloned()
+            .ok_or_else(|| scene_not_found(scene_name))?;
+
+        let report = scenes::apply(
+            &scene,
+            &self.inner.house,
+            &self.inner.registry,
+            &self.inner.events,
+            rollback,
+        );
+        let status = if report.applied { 200 } else { 502 };
+
+        Ok((status, serde_json::to_value(report)?))
+    }
+
+    /// Returns cost of energy over period of the `query`
+    fn costs(&self, query: &str) -> Result<CostReport, ApiError> {
+        let (from, to) = parse_period(query)?;
+        let store = self
+            .inner
+            .history
+            .as_ref()
+            .ok_or_else(|| ApiError::new(404, "History is not recorded"))?;
+        let tariff = self
+            .inner
+            .tariff
+            .as_ref()
+            .ok_or_else(|| ApiError::new(404, "Tariff is not set"))?;
+
+        let devices = self.inner.house.lock().unwrap().get_device_ids().clone();
+
+        Ok(tariff::costs(store, &devices, tariff, from, to)?)
+    }
+
+    fn history(&self, query: &str) -> ApiResult {
+        let store = self
+            .inner
+            .history
+            .as_ref()
+            .ok_or_else(|| ApiError::new(404, "History is not recorded"))?;
+
+        let devices = self.inner.house.lock().unwrap().get_device_ids().clone();
+        let mut series = store.query(&parse_history_query(query)?.with_devices(&devices))?;
+        recorder::follow_names(&mut series, &devices);
+
+        Ok((200, serde_json::to_value(series)?))
+    }
+
+    fn automation_log(&self) -> ApiResult {
+        let audit = self
+            .inner
+            .audit
+            .as_ref()
+            .ok_or_else(|| ApiError::new(404, "Automation is not enabled"))?;
+
+        Ok((200, serde_json::to_value(audit.entries())?))
+    }
+
+    fn schedule(&self) -> ApiResult {
+        let jobs = scheduler::jobs(self.schedule_store()?, chrono::Utc::now());
+        Ok((200, serde_json::to_value(jobs)?))
+    }
+
+    fn add_job(&self, body: &str) -> ApiResult {
+        let store = self.schedule_store()?;
+        let job: Job = serde_json::from_str(body)?;
+        let value = serde_json::to_value(&job)?;
+
+        store.add_job(job).map_err(|err| match err {
+            Error::ConfigError(message) => ApiError::new(409, message),
+            err => err.into(),
+        })?;
+
+        Ok((201, value))
+    }
+
+    fn remove_job(&self, name: &str) -> ApiResult {
+        if !self.schedule_store()?.remove_job(name)? {
+            return Err(ApiError::new(404, format!(r#"Not found job "{name}""#)));
+        }
+
+        Ok((204, Value::Null))
+    }
+
+    fn schedule_store(&self) -> Result<&ScheduleStore, ApiError> {
+        self.inner
+            .schedule
+            .as_deref()
+            .ok_or_else(|| ApiError::new(404, "Scheduler is not enabled"))
+    }
+
+    /// Returns binding of the device
+    fn bound_device(&self, room_name: &str, device_name: &str) -> Result<DeviceBinding, ApiError> {
+        let house = self.inner.house.lock().unwrap();
+        Self::check_device(&house, room_name, device_name)?;
+
+        house
+            .binding(room_name, device_name)
+            .cloned()
+            .ok_or_else(|| {
+                Error::DeviceNotBoundError {
+                    device_name: device_name.to_owned(),
+                    room_name: room_name.to_owned(),
+                }
+                .into()
+            })
+    }
+
+    fn check_device(
+        house: &SmartHouse,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<(), ApiError> {
+        let devices = house
+            .devices(room_name)
+            .ok_or_else(|| room_not_found(room_name))?;
+
+        if !devices.contains(device_name) {
+            return Err(Error::DeviceNotFoundError {
+                device_name: device_name.to_owned(),
+                room_name: room_name.to_owned(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+fn room_not_found(room_name: &str) -> Error {
+    Error::RoomNotFoundError {
+        room_name: room_name.to_owned(),
+    }
+}
+
+fn group_not_found(group_name: &str) -> Error {
+    Error::GroupNotFoundError {
+        group_name: group_name.to_owned(),
+    }
+}
+
+fn group_members(members: &GroupMembers) -> Vec<GroupMember> {
+    members
+        .iter()
+        .map(|(room, device)| GroupMember {
+            room: room.clone(),
+            device: device.clone(),
+        })
+        .collect()
+}
+
+fn scene_not_found(scene_name: &str) -> Error {
+    Error::SceneNotFoundError {
+        scene_name: scene_name.to_owned(),
+    }
+}
+
+/// Parses filter of events from query, e.g. `room=Bathroom&device=switch1`
+fn parse_filter(query: &str) -> Result<EventFilter, ApiError> {
+    let mut filter = EventFilter::default();
+
+    for (key, value) in parse_query(query)? {
+        match key {
+            "room" => filter.room = Some(value),
+            "device" => filter.device = Some(value),
+            _ => return Err(ApiError::new(400, format!("Unknown filter: {key}"))),
+        }
+    }
+
+    Ok(filter)
+}
+
+/// Parses query of history, e.g. `device=therm1&from=1700000000000&step=3600000`
+fn parse_history_query(query: &str) -> Result<Query, ApiError> {
+    let mut history_query = Query::default();
+
+    for (key, value) in parse_query(query)? {
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| ApiError::new(400, format!("Invalid number in {key}: {value}")))
+        };
+
+        match key {
+            "room" => history_query.room = Some(value.clone()),
+            "device" => history_query.device = Some(value.clone()),
+            "metric" => history_query.metric = Some(value.clone()),
+            "from" => history_query.from = Some(number()?),
+            "to" => history_query.to = Some(number()?),
+            "step" => history_query.step = Some(number()?),
+            _ => return Err(ApiError::new(400, format!("Unknown parameter: {key}"))),
+        }
+    }
+
+    Ok(history_query)
+}
+
+/// Parses period from query, e.g. `from=1000&to=2000&format=csv`.
+/// Period is the whole history until now by default.
+fn parse_period(query: &str) -> Result<(u64, u64), ApiError> {
+    let (mut from, mut to) = (0, events::now());
+
+    for (key, value) in parse_query(query)? {
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| ApiError::new(400, format!("Invalid number in {key}: {value}")))
+        };
+
+        match key {
+            "from" => from = number()?,
+            "to" => to = number()?,
+            "format" if value == "csv" || value == "json" => {}
+            _ => return Err(ApiError::new(400, format!("Unknown parameter: {key}"))),
+        }
+    }
+
+    Ok((from, to))
+}
+
+/// Splits query to pairs of keys and decoded values
+fn parse_query(query: &str) -> Result<Vec<(&str, String)>, ApiError> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((key, decode_segment(&value.replace('+', " "))?))
+        })
+        .collect()
+}
+
+/// Decodes percent-encoded segment of the path, e.g. `Dinning%20room`
+fn decode_segment(segment: &str) -> Result<String, ApiError> {
+    let invalid = || ApiError::new(400, "Invalid percent-encoding in path");
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_segment() {
+        assert_eq!(decode_segment("Dinning%20room").unwrap(), "Dinning room");
+        assert_eq!(decode_segment("%D0%9A").unwrap(), "К");
+        assert!(decode_segment("bad%2").is_err());
+        assert!(decode_segment("bad%zz").is_err());
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = parse_filter("room=Dinning+room&device=switch%201").unwrap();
+
+        assert_eq!(filter.room.as_deref(), Some("Dinning room"));
+        assert_eq!(filter.device.as_deref(), Some("switch 1"));
+        assert_eq!(parse_filter("").unwrap(), EventFilter::default());
+        assert!(parse_filter("kind=switch").is_err());
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(
+            parse_period("from=1000&to=2000&format=csv").unwrap(),
+            (1000, 2000)
+        );
+        assert_eq!(parse_period("").unwrap().0, 0);
+        assert!(parse_period("format=xml").is_err());
+        assert!(parse_period("to=now").is_err());
+    }
+
+    #[test]
+    fn test_parse_history_query() {
+        let query = parse_history_query("device=therm1&from=1000&step=60000").unwrap();
+
+        assert_eq!(query.device.as_deref(), Some("therm1"));
+        assert_eq!(query.from, Some(1000));
+        assert_eq!(query.step, Some(60000));
+        assert_eq!(query.to, None);
+        assert!(parse_history_query("from=yesterday").is_err());
+    }
+}
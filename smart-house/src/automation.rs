@@ -0,0 +1,202 @@
+//! Module describes automation of the smart house by rules, e.g.
+//!
+//! ```json
+//! {
+//!     "rules": [
+//!         {
+//!             "name": "Bathroom heating on",
+//!             "when": [{ "room": "Bathroom", "device": "therm1", "below": 19.0, "hysteresis": 0.5 }],
+//!             "then": [{ "room": "Bathroom", "device": "switch1", "turn": "on" }],
+//!             "cooldown": 300
+//!         },
+//!         {
+//!             "name": "Bathroom heating off",
+//!             "when": [{ "room": "Bathroom", "device": "therm1", "above": 22.0, "hysteresis": 0.5 }],
+//!             "then": [{ "room": "Bathroom", "device": "switch1", "turn": "off" }]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Rules are evaluated against states observed by [`HouseEvents`].
+
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{self, RecvTimeoutError, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{self, Error},
+    events::{self, EventFilter, EventKind, HouseEvents},
+    files,
+    registry::DeviceRegistry,
+    smart_house::SmartHouse,
+};
+
+pub use self::audit::{ActionOutcome, AuditEntry, AuditLog};
+pub use self::rule::{Action, Condition, Rule, RuleSet, Threshold, Turn};
+
+mod audit;
+mod rule;
+
+/// Describes how often rules are evaluated without new events,
+/// so rules fire after their cooldowns
+const TICK: Duration = Duration::from_millis(500);
+
+/// Describes configuration file of automation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutomationConfig {
+    pub rules: Vec<Rule>,
+}
+
+impl AutomationConfig {
+    /// Reads configuration from JSON file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+
+    /// Writes configuration to JSON file at `path` replacing the previous content at once
+    pub fn save(&self, path: impl AsRef<Path>) -> errors::Result<()> {
+        let path = path.as_ref();
+        let content =
+            serde_json::to_string_pretty(self).map_err(|err| Error::StorageError(err.into()))?;
+
+        files::write_atomically(path, content)
+    }
+}
+
+/// Describes thread which evaluates rules on each event of the house
+/// and performs actions of fired rules. Rules follow renamed devices
+/// and are saved if they are read from file. In dry-run mode actions
+/// are only recorded to the audit log.
+/// Evaluation stops when engine is dropped.
+pub struct AutomationEngine {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AutomationEngine {
+    /// Starts evaluating `rules` against states observed by `events`
+    pub fn start(
+        rules: RuleSet,
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        events: Arc<HouseEvents>,
+        audit: Arc<AuditLog>,
+        dry_run: bool,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let received = events.subscribe(EventFilter::default());
+
+        let handle = thread::spawn(move || {
+            let mut rules = rules;
+
+            while let Err(TryRecvError::Empty) = stopped.try_recv() {
+                let mut burst = match received.recv_timeout(TICK) {
+                    Ok(event) => vec![event],
+                    Err(RecvTimeoutError::Timeout) => Vec::new(),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                // Rules are evaluated once for a burst of events
+                burst.extend(received.try_iter());
+
+                for event in burst {
+                    if let EventKind::DeviceRenamed {
+                        new_room,
+                        new_device,
+                    } = event.kind
+                    {
+                        if let Err(err) =
+                            rules.rename_device(&event.room, &event.device, &new_room, &new_device)
+                        {
+                            println!("Failed to save rules: {err}");
+                        }
+                    }
+                }
+
+                for rule in rules.evaluate(&events.states(), Instant::now()) {
+                    let actions = rule
+                        .then
+                        .iter()
+                        .map(|action| perform(action, &house, &registry, &events, dry_run))
+                        .collect();
+
+                    let entry = AuditEntry {
+                        timestamp: events::now(),
+                        rule: rule.name,
+                        dry_run,
+                        actions,
+                    };
+
+                    println!("Rule fired: {}", serde_json::json!(entry));
+
+                    audit.record(entry);
+                }
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for AutomationEngine {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+/// Turns power switch on or off, only checks the switch is bound in dry-run mode
+pub(crate) fn perform(
+    action: &Action,
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    dry_run: bool,
+) -> ActionOutcome {
+    let binding = house
+        .lock()
+        .unwrap()
+        .binding(&action.room, &action.device)
+        .cloned();
+
+    let error = match binding {
+        None => Some(
+            Error::DeviceNotBoundError {
+                device_name: action.device.clone(),
+                room_name: action.room.clone(),
+            }
+            .to_string(),
+        ),
+        Some(_) if dry_run => None,
+        Some(binding) => {
+            let result = registry.turn(&binding, action.turn == Turn::On);
+
+            if !matches!(result, Err(Error::UnsupportedCommandError)) {
+                events.observe(&action.room, &action.device, &result);
+            }
+
+            result.err().map(|err| err.to_string())
+        }
+    };
+
+    ActionOutcome {
+        room: action.room.clone(),
+        device: action.device.clone(),
+        turn: action.turn,
+        error,
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+use serde::Serialize;
+
+use super::rule::Turn;
+use crate::errors::{self, Error::StorageError};
+
+/// Describes outcome of action of fired rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActionOutcome {
+    pub room: String,
+    pub device: String,
+    pub turn: Turn,
+    /// Error of performing the action, `None` if it succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Describes record about fired rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEntry {
+    /// Time of firing in milliseconds since Unix epoch
+    pub timestamp: u64,
+    pub rule: String,
+    /// `true` if actions were not performed
+    pub dry_run: bool,
+    pub actions: Vec<ActionOutcome>,
+}
+
+/// Describes log of fired rules. The latest entries are kept in memory,
+/// all entries are optionally appended to file as JSON lines.
+pub struct AuditLog {
+    entries: Mutex<VecDeque<AuditEntry>>,
+    capacity: usize,
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    /// Creates log keeping no more than `capacity` latest entries in memory
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            file: None,
+        }
+    }
+
+    /// Creates log which also appends entries to file at `path`
+    pub fn with_file(path: impl AsRef<Path>, capacity: usize) -> errors::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(StorageError)?;
+
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+            ..Self::new(capacity)
+        })
+    }
+
+    /// Adds `entry` to the log
+    pub fn record(&self, entry: AuditEntry) {
+        if let Some(file) = &self.file {
+            let result = serde_json::to_string(&entry)
+                .map_err(Into::into)
+                .and_then(|line| writeln!(file.lock().unwrap(), "{line}"));
+
+            if let Err(err) = result {
+                println!("Failed to write audit log: {err}");
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(entry);
+        while entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+
+    /// Returns the latest entries from the oldest to the newest
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_latest_entries() {
+        let log = AuditLog::new(2);
+
+        for rule in ["a", "b", "c"] {
+            log.record(AuditEntry {
+                timestamp: 0,
+                rule: rule.to_owned(),
+                dry_run: true,
+                actions: Vec::new(),
+            });
+        }
+
+        let rules: Vec<String> = log.entries().into_iter().map(|e| e.rule).collect();
+        assert_eq!(rules, vec!["b", "c"]);
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::AutomationConfig;
+use crate::{
+    errors,
+    events::{DeviceKey, ObservedState},
+};
+
+/// Describes threshold of metric value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Threshold {
+    Below(f64),
+    Above(f64),
+}
+
+/// Describes condition on metric of device, e.g. `temperature` of thermometer
+/// or `enabled` and `power` of power switch.
+///
+/// Once met, condition stays met until the value crosses the threshold
+/// by `hysteresis` in the opposite direction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub room: String,
+    pub device: String,
+    #[serde(default = "default_metric")]
+    pub metric: String,
+    #[serde(flatten)]
+    pub threshold: Threshold,
+    #[serde(default)]
+    pub hysteresis: f64,
+}
+
+fn default_metric() -> String {
+    "temperature".to_owned()
+}
+
+impl Condition {
+    /// Returns whether condition is met by `value` given it was met before.
+    /// Unknown value, e.g. of stale device, doesn't change the condition.
+    fn evaluate(&self, value: Option<f64>, was_met: bool) -> bool {
+        let Some(value) = value else {
+            return was_met;
+        };
+
+        match (self.threshold, was_met) {
+            (Threshold::Below(threshold), false) => value < threshold,
+            (Threshold::Below(threshold), true) => value < threshold + self.hysteresis,
+            (Threshold::Above(threshold), false) => value > threshold,
+            (Threshold::Above(threshold), true) => value > threshold - self.hysteresis,
+        }
+    }
+
+    /// Returns current value of the metric, `None` if device is stale
+    fn value(&self, states: &BTreeMap<DeviceKey, ObservedState>) -> Option<f64> {
+        let observed = states.get(&(self.room.clone(), self.device.clone()))?;
+
+        if observed.stale {
+            return None;
+        }
+
+        observed.state.as_ref()?.metric(&self.metric)
+    }
+}
+
+/// Describes target state of power switch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Turn {
+    On,
+    Off,
+}
+
+/// Describes action performed when rule fires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Action {
+    pub room: String,
+    pub device: String,
+    pub turn: Turn,
+}
+
+/// Describes rule which performs actions when all its conditions become met.
+/// Rule fires once per activation and no more often than once per `cooldown` seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub when: Vec<Condition>,
+    pub then: Vec<Action>,
+    #[serde(default)]
+    pub cooldown: u64,
+}
+
+/// Describes rule together with state of its evaluation
+#[derive(Debug)]
+struct RuleState {
+    rule: Rule,
+    met: Vec<bool>,
+    fired: bool,
+    last_fired: Option<Instant>,
+}
+
+/// Describes set of rules evaluated against observed states of devices
+#[derive(Debug)]
+pub struct RuleSet {
+    rules: Vec<RuleState>,
+    /// File the rules are saved to when they change
+    path: Option<PathBuf>,
+}
+
+impl RuleSet {
+    /// Creates set of `rules`, none of them is active
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| RuleState {
+                    met: vec![false; rule.when.len()],
+                    rule,
+                    fired: false,
+                    last_fired: None,
+                })
+                .collect(),
+            path: None,
+        }
+    }
+
+    /// Reads set of rules from configuration file at `path`,
+    /// the file is rewritten when rules change
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        let path = path.as_ref();
+        let config = AutomationConfig::from_file(path)?;
+
+        Ok(Self {
+            path: Some(path.to_owned()),
+            ..Self::new(config.rules)
+        })
+    }
+
+    /// Returns rules of the set
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().map(|state| &state.rule)
+    }
+
+    /// Makes conditions and actions on device refer to its new room and name,
+    /// saves the rules if they are read from file
+    pub fn rename_device(
+        &mut self,
+        room: &str,
+        device: &str,
+        new_room: &str,
+        new_device: &str,
+    ) -> errors::Result<()> {
+        let mut renamed = false;
+        let rules = self.rules.iter_mut().map(|state| &mut state.rule);
+
+        for rule in rules {
+            let conditions = rule
+                .when
+                .iter_mut()
+                .map(|condition| (&mut condition.room, &mut condition.device));
+            let actions = rule
+                .then
+                .iter_mut()
+                .map(|action| (&mut action.room, &mut action.device));
+
+            for (rule_room, rule_device) in conditions.chain(actions) {
+                if rule_room == room && rule_device == device {
+                    (*rule_room, *rule_device) = (new_room.to_owned(), new_device.to_owned());
+                    renamed = true;
+                }
+            }
+        }
+
+        match &self.path {
+            Some(path) if renamed => AutomationConfig {
+                rules: self.rules().cloned().collect(),
+            }
+            .save(path),
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates rules against `states` of devices at time `now`
+    /// and returns rules which fire
+    pub fn evaluate(
+        &mut self,
+        states: &BTreeMap<DeviceKey, ObservedState>,
+        now: Instant,
+    ) -> Vec<Rule> {
+        let mut fired = Vec::new();
+
+        for state in &mut self.rules {
+            for (condition, met) in state.rule.when.iter().zip(state.met.iter_mut()) {
+                *met = condition.evaluate(condition.value(states), *met);
+            }
+
+            if state.met.is_empty() || !state.met.iter().all(|met| *met) {
+                state.fired = false;
+                continue;
+            }
+
+            let cooldown = Duration::from_secs(state.rule.cooldown);
+            let cooling_down = state
+                .last_fired
+                .is_some_and(|last_fired| now.duration_since(last_fired) < cooldown);
+
+            if state.fired || cooling_down {
+                continue;
+            }
+
+            state.fired = true;
+            state.last_fired = Some(now);
+            fired.push(state.rule.clone());
+        }
+
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::DeviceState;
+
+    fn heating_rule(cooldown: u64) -> Rule {
+        serde_json::from_str(&format!(
+            r#"{{
+                "name": "Bathroom heating",
+                "when": [{{ "room": "Bathroom", "device": "therm1", "below": 19.0, "hysteresis": 0.5 }}],
+                "then": [{{ "room": "Bathroom", "device": "switch1", "turn": "on" }}],
+                "cooldown": {cooldown}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn temperature(value: f64, stale: bool) -> BTreeMap<DeviceKey, ObservedState> {
+        BTreeMap::from([(
+            ("Bathroom".to_owned(), "therm1".to_owned()),
+            ObservedState {
+                state: Some(DeviceState::Thermometer {
+                    temperature: value,
+                    measurements: BTreeMap::new(),
+                }),
+                updated: Some(0),
+                stale,
+            },
+        )])
+    }
+
+    fn fired(rules: &mut RuleSet, value: f64, stale: bool, now: Instant) -> usize {
+        rules.evaluate(&temperature(value, stale), now).len()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = heating_rule(60);
+
+        assert_eq!(rule.when[0].metric, "temperature");
+        assert_eq!(rule.when[0].threshold, Threshold::Below(19.0));
+        assert_eq!(rule.then[0].turn, Turn::On);
+    }
+
+    #[test]
+    fn test_rename_device() {
+        let mut rules = RuleSet::new(vec![heating_rule(0)]);
+
+        rules
+            .rename_device("Bathroom", "therm1", "Washroom", "therm1")
+            .unwrap();
+        rules
+            .rename_device("Bathroom", "switch1", "Bathroom", "heater")
+            .unwrap();
+
+        let rule = rules.rules().next().unwrap();
+        assert_eq!(rule.when[0].room, "Washroom");
+        assert_eq!(rule.then[0].room, "Bathroom");
+        assert_eq!(rule.then[0].device, "heater");
+        // Old name of thermometer doesn't activate the rule anymore
+        assert_eq!(fired(&mut rules, 18.0, false, Instant::now()), 0);
+    }
+
+    #[test]
+    fn test_renamed_rules_are_saved() {
+        let path =
+            std::env::temp_dir().join(format!("smart-house-rules-{}.json", std::process::id()));
+        AutomationConfig {
+            rules: vec![heating_rule(0)],
+        }
+        .save(&path)
+        .unwrap();
+
+        let mut rules = RuleSet::from_file(&path).unwrap();
+        rules
+            .rename_device("Bathroom", "switch1", "Bathroom", "heater")
+            .unwrap();
+
+        let config = AutomationConfig::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.rules[0].then[0].device, "heater");
+        assert_eq!(config.rules[0].when[0].device, "therm1");
+    }
+
+    #[test]
+    fn test_fire_once_per_activation_with_hysteresis() {
+        let mut rules = RuleSet::new(vec![heating_rule(0)]);
+        let now = Instant::now();
+
+        assert_eq!(fired(&mut rules, 20.0, false, now), 0);
+        assert_eq!(fired(&mut rules, 18.9, false, now), 1);
+        assert_eq!(fired(&mut rules, 18.5, false, now), 0);
+        // Still within hysteresis
+        assert_eq!(fired(&mut rules, 19.3, false, now), 0);
+        assert_eq!(fired(&mut rules, 18.9, false, now), 0);
+        // Deactivated and activated again
+        assert_eq!(fired(&mut rules, 19.6, false, now), 0);
+        assert_eq!(fired(&mut rules, 18.9, false, now), 1);
+    }
+
+    #[test]
+    fn test_cooldown_delays_firing() {
+        let mut rules = RuleSet::new(vec![heating_rule(60)]);
+        let now = Instant::now();
+
+        assert_eq!(fired(&mut rules, 18.0, false, now), 1);
+        assert_eq!(fired(&mut rules, 20.0, false, now), 0);
+        assert_eq!(fired(&mut rules, 18.0, false, now), 0);
+        // Rule still active after cooldown fires
+        assert_eq!(
+            fired(&mut rules, 18.0, false, now + Duration::from_secs(61)),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stale_reading_does_not_activate_rule() {
+        let mut rules = RuleSet::new(vec![heating_rule(0)]);
+
+        assert_eq!(fired(&mut rules, 18.0, true, Instant::now()), 0);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    error::Error,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use clap::Parser;
+use discovery::DEFAULT_GROUP;
+use smart_house::{
+    api::ApiServer,
+    automation::{AuditLog, AutomationEngine, RuleSet},
+    device_discovery::DeviceDiscovery,
+    groups::GroupConfig,
+    house_file::HouseFile,
+    load_shedding::LoadController,
+    monitor::StateMonitor,
+    recorder::{Recorder, RetentionPolicy, SampleStore},
+    registry::DeviceRegistry,
+    scenes::SceneConfig,
+    scheduler::{ScheduleStore, Scheduler},
+    smart_house::{PowerBudget, SmartHouse},
+    tariff::Tariff,
+};
+
+/// Describes number of the latest fired rules and ran jobs served by API
+const AUDIT_CAPACITY: usize = 1000;
+
+/// Daemon serving HTTP REST API of the smart house
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Address for HTTP server: <ip>:<port>
+    #[clap(short, long, value_parser, default_value = "127.0.0.1:8080")]
+    address: String,
+
+    /// Name of the smart house
+    #[clap(short, long, value_parser, default_value = "Smart house")]
+    name: String,
+
+    /// JSON file of the smart house created by `smart-house init`,
+    /// overrides the name. Discovered devices and changes made through API
+    /// are saved to it
+    #[clap(long, value_parser)]
+    house: Option<PathBuf>,
+
+    /// Time to wait for response of device in milliseconds
+    #[clap(long, value_parser, default_value_t = 2000)]
+    timeout: u64,
+
+    /// Interval of polling devices for live events in milliseconds
+    #[clap(long, value_parser, default_value_t = 5000)]
+    poll: u64,
+
+    /// Scan for announced devices during given number of seconds
+    /// and add them to the house before start
+    #[clap(long, value_parser)]
+    discover: Option<u64>,
+
+    /// Multicast group of announcements: <ip>:<port>
+    #[clap(long, value_parser, default_value_t = DEFAULT_GROUP)]
+    group: SocketAddr,
+
+    /// Room for discovered devices which don't announce their room
+    #[clap(long, value_parser, default_value = "Unassigned")]
+    room: String,
+
+    /// File to record history of device readings to
+    #[clap(long, value_parser)]
+    history: Option<PathBuf>,
+
+    /// Interval of recording device readings in milliseconds
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 60000)]
+    record: u64,
+
+    /// Age in hours after which recorded readings are downsampled
+    #[clap(long, value_parser, default_value_t = 168)]
+    raw_retention: u64,
+
+    /// Interval in minutes readings are averaged over when downsampled
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 60)]
+    downsample: u64,
+
+    /// Age in days after which recorded readings are removed
+    #[clap(long, value_parser, default_value_t = 365)]
+    max_age: u64,
+
+    /// JSON file with automation rules, it is updated when devices are renamed through API
+    #[clap(long, value_parser)]
+    rules: Option<PathBuf>,
+
+    /// Only log actions of fired rules without performing them
+    #[clap(long)]
+    dry_run: bool,
+
+    /// File to append log of fired rules and ran jobs to
+    #[clap(long, value_parser)]
+    audit_log: Option<PathBuf>,
+
+    /// JSON file with groups of devices
+    #[clap(long, value_parser)]
+    groups: Option<PathBuf>,
+
+    /// JSON file with power budget of the house
+    #[clap(long, value_parser)]
+    budget: Option<PathBuf>,
+
+    /// Interval of checking power budget in milliseconds
+    #[clap(long, value_parser, default_value_t = 2000)]
+    budget_interval: u64,
+
+    /// JSON file with tariff to calculate cost of recorded energy
+    #[clap(long, value_parser)]
+    tariff: Option<PathBuf>,
+
+    /// JSON file with scenes of the house
+    #[clap(long, value_parser)]
+    scenes: Option<PathBuf>,
+
+    /// JSON file with schedule of jobs, changes made through API are saved to it
+    #[clap(long, value_parser)]
+    schedule: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut house = match &args.house {
+        Some(path) => SmartHouse::from(HouseFile::load(path)?),
+        None => SmartHouse::new(&args.name),
+    };
+
+    if let Some(seconds) = args.discover {
+        println!("Discovering devices at {} for {seconds} s...", args.group);
+
+        let mut discovery = DeviceDiscovery::new(args.group)?;
+        discovery.scan(Duration::from_secs(seconds))?;
+
+        for (room_name, device_name) in discovery.add_to_house(&mut house, &args.room) {
+            println!("Added {device_name} to {room_name}");
+        }
+
+        if let Some(path) = &args.house {
+            HouseFile::from(&house).save(path)?;
+        }
+    }
+
+    if let Some(path) = &args.groups {
+        for (group_name, members) in GroupConfig::from_file(path)?.groups {
+            for member in members {
+                if !house.add_to_group(&group_name, &member.room, &member.device) {
+                    println!(
+                        "Skipped {} in {} for group {group_name}",
+                        member.device, member.room
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &args.scenes {
+        for scene in SceneConfig::from_file(path)?.scenes {
+            let scene_name = scene.name.clone();
+            if !house.add_scene(scene) {
+                println!("Skipped duplicate scene {scene_name}");
+            }
+        }
+    }
+
+    if let Some(path) = &args.budget {
+        let budget = PowerBudget::from_file(path)?;
+
+        println!("Power budget of {} W", budget.limit);
+
+        house.set_power_budget(Some(budget));
+    }
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(args.timeout)));
+    let mut server = ApiServer::new(&args.address, house.clone(), registry.clone())?;
+
+    if let Some(path) = &args.house {
+        server = server.with_house_file(path);
+    }
+
+    let _recorder = match &args.history {
+        Some(path) => {
+            let store = Arc::new(SampleStore::open(path)?);
+            let policy = RetentionPolicy {
+                raw_age: Duration::from_secs(args.raw_retention * 3600),
+                downsample_interval: Duration::from_secs(args.downsample * 60),
+                max_age: Duration::from_secs(args.max_age * 24 * 3600),
+            };
+
+            println!("Recording history to {}", path.display());
+
+            server = server.with_history(store.clone());
+            Some(Recorder::start(
+                house.clone(),
+                registry.clone(),
+                store,
+                Duration::from_millis(args.record),
+                policy,
+            ))
+        }
+        None => None,
+    };
+
+    if let Some(path) = &args.tariff {
+        let tariff = Tariff::from_file(path)?;
+
+        println!("Tariff in {}", tariff.currency);
+
+        server = server.with_tariff(tariff);
+    }
+
+    let audit = Arc::new(match &args.audit_log {
+        Some(path) => AuditLog::with_file(path, AUDIT_CAPACITY)?,
+        None => AuditLog::new(AUDIT_CAPACITY),
+    });
+
+    if args.rules.is_some() || args.schedule.is_some() {
+        server = server.with_automation(audit.clone());
+    }
+
+    let _automation = match &args.rules {
+        Some(path) => {
+            let rules = RuleSet::from_file(path)?;
+
+            println!(
+                "Automation with {} rules{}",
+                rules.rules().count(),
+                if args.dry_run { " in dry-run mode" } else { "" }
+            );
+
+            Some(AutomationEngine::start(
+                rules,
+                house.clone(),
+                registry.clone(),
+                server.events(),
+                audit.clone(),
+                args.dry_run,
+            ))
+        }
+        None => None,
+    };
+
+    let _scheduler = match &args.schedule {
+        Some(path) => {
+            let store = Arc::new(ScheduleStore::open(path)?);
+
+            println!("Scheduling {} jobs", store.schedule().jobs.len());
+
+            server = server.with_scheduler(store.clone());
+            Some(Scheduler::start(
+                store,
+                house.clone(),
+                registry.clone(),
+                server.events(),
+                audit,
+            ))
+        }
+        None => None,
+    };
+
+    // Budget may be set through API later
+    let load_controller = Arc::new(LoadController::start(
+        house.clone(),
+        registry.clone(),
+        server.events(),
+        Duration::from_millis(args.budget_interval),
+    ));
+    server = server.with_load_shedding(load_controller);
+
+    let _monitor = StateMonitor::start(
+        house,
+        registry,
+        server.events(),
+        Duration::from_millis(args.poll),
+    );
+
+    println!("Serving HTTP API at {}", args.address);
+
+    server.run();
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::{
+    error::Error,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use clap::Parser;
+use discovery::DEFAULT_GROUP;
+use smart_house::{
+    device_discovery::DeviceDiscovery,
+    events::HouseEvents,
+    monitor::StateMonitor,
+    mqtt::{BridgeSettings, MqttBridge, MqttClient},
+    registry::DeviceRegistry,
+    smart_house::{DeviceBinding, SmartHouse},
+};
+
+/// Bridge publishing states of devices to MQTT broker
+/// and turning power switches on and off by MQTT commands
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Address of MQTT broker: <host>:<port>
+    #[clap(short, long, value_parser, default_value = "127.0.0.1:1883")]
+    broker: String,
+
+    /// Client identifier of the bridge
+    #[clap(long, value_parser, default_value = "smart-house-bridge")]
+    client_id: String,
+
+    /// Keep alive interval of connection to broker in seconds
+    #[clap(long, value_parser, default_value_t = 30)]
+    keep_alive: u64,
+
+    /// Prefix of state and command topics
+    #[clap(long, value_parser, default_value = "smart-house")]
+    prefix: String,
+
+    /// Prefix of Home Assistant discovery topics
+    #[clap(long, value_parser, default_value = "homeassistant")]
+    discovery_prefix: String,
+
+    /// Power switch: <room>/<device>=<ip>:<port>
+    #[clap(long, value_parser = parse_device)]
+    switch: Vec<(String, String, SocketAddr)>,
+
+    /// Thermometer sender: <room>/<device>=<ip>:<port>
+    #[clap(long, value_parser = parse_device)]
+    thermometer: Vec<(String, String, SocketAddr)>,
+
+    /// Time to wait for response of device in milliseconds
+    #[clap(long, value_parser, default_value_t = 2000)]
+    timeout: u64,
+
+    /// Interval of polling devices in milliseconds
+    #[clap(long, value_parser, default_value_t = 5000)]
+    poll: u64,
+
+    /// Scan for announced devices during given number of seconds
+    /// and bridge them too
+    #[clap(long, value_parser)]
+    discover: Option<u64>,
+
+    /// Multicast group of announcements: <ip>:<port>
+    #[clap(long, value_parser, default_value_t = DEFAULT_GROUP)]
+    group: SocketAddr,
+
+    /// Room for discovered devices which don't announce their room
+    #[clap(long, value_parser, default_value = "Unassigned")]
+    room: String,
+}
+
+fn parse_device(s: &str) -> Result<(String, String, SocketAddr), String> {
+    let (path, address) = s
+        .split_once('=')
+        .ok_or("Expected <room>/<device>=<ip>:<port>")?;
+    let (room, device) = path
+        .split_once('/')
+        .ok_or("Expected <room>/<device> before '='")?;
+    let address = address.parse().map_err(|err| format!("{err}"))?;
+
+    Ok((room.to_owned(), device.to_owned(), address))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut house = SmartHouse::new("Smart house");
+
+    for (room, device, address) in &args.switch {
+        house.add_device(room, device);
+        house.bind_device(
+            room,
+            device,
+            DeviceBinding::PowerSwitch { address: *address },
+        );
+    }
+
+    for (room, device, sender) in &args.thermometer {
+        house.add_device(room, device);
+        house.bind_device(room, device, DeviceBinding::Thermometer { sender: *sender });
+    }
+
+    if let Some(seconds) = args.discover {
+        println!("Discovering devices at {} for {seconds} s...", args.group);
+
+        let mut discovery = DeviceDiscovery::new(args.group)?;
+        discovery.scan(Duration::from_secs(seconds))?;
+
+        for (room_name, device_name) in discovery.add_to_house(&mut house, &args.room) {
+            println!("Added {device_name} to {room_name}");
+        }
+    }
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(args.timeout)));
+    let events = Arc::new(HouseEvents::new());
+
+    let (client, messages) = MqttClient::connect(
+        &args.broker,
+        &args.client_id,
+        Duration::from_secs(args.keep_alive),
+    )?;
+
+    let settings = BridgeSettings {
+        prefix: args.prefix,
+        discovery_prefix: args.discovery_prefix,
+    };
+    let _bridge = MqttBridge::start(
+        client,
+        messages,
+        house.clone(),
+        registry.clone(),
+        events.clone(),
+        settings,
+    )?;
+
+    // Monitor is started after the bridge to publish initial states
+    let _monitor = StateMonitor::start(house, registry, events, Duration::from_millis(args.poll));
+
+    println!("Bridging devices to MQTT broker at {}", args.broker);
+
+    loop {
+        thread::park();
+    }
+}
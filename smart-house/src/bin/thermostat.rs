@@ -0,0 +1,105 @@
+use std::{error::Error, fs, net::SocketAddr, sync::Arc, thread, time::Duration};
+
+use clap::Parser;
+use smart_house::{
+    registry::DeviceRegistry,
+    thermostat::{RemoteSwitch, Thermostat, ThermostatMode, ThermostatSettings},
+};
+use thermometer::thermometer::Thermometer;
+
+/// Thermostat keeping temperature measured by thermometer
+/// by turning power switch on and off
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Address to receive temperature on: <ip>:<port>
+    #[clap(short, long, value_parser, default_value = "127.0.0.1:4444")]
+    receiver: String,
+
+    /// Address of thermometer sender: <ip>:<port>
+    #[clap(short, long, value_parser, default_value = "127.0.0.1:3333")]
+    sender: String,
+
+    /// Address of power switch: <ip>:<port>
+    #[clap(short = 'p', long, value_parser, default_value = "127.0.0.1:53453")]
+    switch: SocketAddr,
+
+    /// JSON file with settings of thermostat
+    #[clap(short, long, value_parser)]
+    config: Option<String>,
+
+    /// Mode overriding the one from settings: off, heat, cool or eco
+    #[clap(short, long, value_parser)]
+    mode: Option<ThermostatMode>,
+
+    /// Target temperature overriding the one from settings
+    #[clap(short, long, value_parser)]
+    target: Option<f64>,
+
+    /// Interval of control in milliseconds
+    #[clap(short, long, value_parser, default_value_t = 5000)]
+    interval: u64,
+
+    /// Time to wait for response of power switch in milliseconds
+    #[clap(long, value_parser, default_value_t = 2000)]
+    timeout: u64,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut settings = match &args.config {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => ThermostatSettings {
+            mode: ThermostatMode::Heat,
+            ..ThermostatSettings::default()
+        },
+    };
+
+    if let Some(mode) = args.mode {
+        settings.mode = mode;
+    }
+
+    if let Some(target) = args.target {
+        settings.target = target;
+    }
+
+    let thermometer = Thermometer::new(&args.receiver, &args.sender)?;
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(args.timeout)));
+    let switch = RemoteSwitch::new(args.switch, registry);
+
+    let interval = Duration::from_millis(args.interval);
+    let thermostat = Thermostat::start(thermometer, switch, settings, interval);
+
+    loop {
+        thread::sleep(interval);
+
+        let status = thermostat.status();
+        let temperature = status
+            .temperature
+            .map_or("-".to_owned(), |temperature| format!("{temperature:.1} °C"));
+        let setpoint = status
+            .setpoint
+            .map_or("-".to_owned(), |setpoint| format!("{setpoint:.1} °C"));
+        let switch = match status.enabled {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "unknown",
+        };
+
+        print!(
+            "Mode: {}, temperature: {temperature}, target: {setpoint}, switch: {switch}",
+            status.mode
+        );
+
+        if status.stale {
+            print!(" (stale reading, no action)");
+        }
+
+        if let Some(error) = status.error {
+            print!(" (error: {error})");
+        }
+
+        println!();
+    }
+}
@@ -0,0 +1,269 @@
+//! Module describes terminal dashboard with panel of current device states per room
+
+use std::{io, thread, time::Duration};
+
+use chrono::Local;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::{
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::SmartHouse,
+};
+
+/// Describes maximal number of room panels in a row
+const COLUMNS: usize = 3;
+
+/// Describes device shown in the room panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceLine {
+    pub device: String,
+    /// Current state, `None` if the device is not bound
+    pub state: Option<Result<DeviceState, String>>,
+}
+
+/// Describes panel of the room
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomPanel {
+    pub room: String,
+    pub devices: Vec<DeviceLine>,
+}
+
+/// Describes states of all devices of the house at some moment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dashboard {
+    pub house: String,
+    /// Time of the query formatted in local time zone
+    pub updated: String,
+    pub rooms: Vec<RoomPanel>,
+}
+
+impl Dashboard {
+    /// Queries all bound devices of the house concurrently
+    pub fn query(house: &SmartHouse, registry: &DeviceRegistry) -> Self {
+        let rooms = thread::scope(|scope| {
+            let rooms: Vec<_> = house
+                .get_rooms()
+                .values()
+                .map(|room| {
+                    let devices: Vec<_> = room
+                        .get_devices()
+                        .iter()
+                        .map(|device| {
+                            let binding = house.binding(room.get_name(), device);
+                            let handle = binding.map(|binding| {
+                                scope.spawn(move || {
+                                    registry.state(binding).map_err(|err| err.to_string())
+                                })
+                            });
+                            (device, handle)
+                        })
+                        .collect();
+                    (room.get_name(), devices)
+                })
+                .collect();
+
+            rooms
+                .into_iter()
+                .map(|(room, devices)| RoomPanel {
+                    room: room.to_owned(),
+                    devices: devices
+                        .into_iter()
+                        .map(|(device, handle)| DeviceLine {
+                            device: device.clone(),
+                            state: handle
+                                .map(|handle| handle.join().expect("Querying device panicked")),
+                        })
+                        .collect(),
+                })
+                .collect()
+        });
+
+        Self {
+            house: house.get_name().to_owned(),
+            updated: Local::now().format("%H:%M:%S").to_string(),
+            rooms,
+        }
+    }
+
+    /// Draws title line and grid of room panels
+    pub fn render(&self, frame: &mut Frame) {
+        let [title, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+
+        frame.render_widget(
+            Line::from(vec![
+                Span::from(self.house.as_str()).bold(),
+                Span::from(format!("  updated at {}  (q to quit)", self.updated)).dark_gray(),
+            ]),
+            title,
+        );
+
+        for (room, area) in self.rooms.iter().zip(Self::grid(body, self.rooms.len())) {
+            let lines: Vec<_> = room.devices.iter().map(Self::device_line).collect();
+            let panel = Paragraph::new(lines).block(Block::bordered().title(room.room.as_str()));
+
+            frame.render_widget(panel, area);
+        }
+    }
+
+    /// Splits `area` to `count` cells in rows of up to `COLUMNS` cells
+    fn grid(area: Rect, count: usize) -> Vec<Rect> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let columns = count.min(COLUMNS);
+        let rows = count.div_ceil(columns);
+
+        Layout::vertical(vec![Constraint::Ratio(1, rows as u32); rows])
+            .split(area)
+            .iter()
+            .flat_map(|row| {
+                Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns])
+                    .split(*row)
+                    .to_vec()
+            })
+            .take(count)
+            .collect()
+    }
+
+    fn device_line(line: &DeviceLine) -> Line<'_> {
+        let name = Span::from(format!("{:<12} ", line.device));
+
+        let state = match &line.state {
+            None => Span::styled("not bound", Style::new().fg(Color::DarkGray)),
+            Some(Err(err)) => Span::styled(err.clone(), Style::new().fg(Color::Red)),
+            Some(Ok(DeviceState::PowerSwitch {
+                enabled,
+                power,
+                energy,
+            })) => Span::styled(
+                format!(
+                    "{:<3} {power:>8.1} W {}",
+                    if *enabled { "on" } else { "off" },
+                    energy.map_or_else(String::new, |energy| format!("{energy:>10.1} Wh"))
+                ),
+                Style::new().fg(if *enabled {
+                    Color::Green
+                } else {
+                    Color::Yellow
+                }),
+            ),
+            Some(Ok(DeviceState::Thermometer { temperature, .. })) => {
+                Span::styled(format!("{temperature:.1} °C"), Style::new().fg(Color::Cyan))
+            }
+        };
+
+        Line::from(vec![name, state])
+    }
+}
+
+/// Shows dashboard of the `house` in the terminal refreshing it every `interval`
+/// until `q` or `Esc` is pressed, any other key refreshes it at once
+pub fn run(house: &SmartHouse, registry: &DeviceRegistry, interval: Duration) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+
+    let result = (|| loop {
+        let dashboard = Dashboard::query(house, registry);
+        terminal.draw(|frame| dashboard.render(frame))?;
+
+        if event::poll(interval)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+
+    ratatui::restore();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    fn dashboard() -> Dashboard {
+        Dashboard {
+            house: "Test house".to_owned(),
+            updated: "12:00:00".to_owned(),
+            rooms: vec![
+                RoomPanel {
+                    room: "Bathroom".to_owned(),
+                    devices: vec![
+                        DeviceLine {
+                            device: "switch1".to_owned(),
+                            state: Some(Ok(DeviceState::PowerSwitch {
+                                enabled: true,
+                                power: 1500.0,
+                                energy: Some(320.0),
+                            })),
+                        },
+                        DeviceLine {
+                            device: "therm1".to_owned(),
+                            state: Some(Ok(DeviceState::Thermometer {
+                                temperature: 21.25,
+                                measurements: Default::default(),
+                            })),
+                        },
+                    ],
+                },
+                RoomPanel {
+                    room: "Kitchen".to_owned(),
+                    devices: vec![DeviceLine {
+                        device: "kettle".to_owned(),
+                        state: None,
+                    }],
+                },
+            ],
+        }
+    }
+
+    fn render(dashboard: &Dashboard, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| dashboard.render(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_room_panels() {
+        let screen = render(&dashboard(), 120, 8);
+
+        assert!(screen[0].starts_with("Test house  updated at 12:00:00"));
+        assert!(screen[1].contains("Bathroom") && screen[1].contains("Kitchen"));
+        assert!(screen[2].contains("switch1") && screen[2].contains("on    1500.0 W"));
+        assert!(screen[2].contains("kettle       not bound"));
+        assert!(screen[3].contains("therm1       21.2 °C"));
+    }
+
+    #[test]
+    fn test_grid_wraps_rooms_to_rows() {
+        let area = Rect::new(0, 0, 90, 20);
+
+        let cells = Dashboard::grid(area, 4);
+
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[0], Rect::new(0, 0, 30, 10));
+        assert_eq!(cells[3], Rect::new(0, 10, 30, 10));
+        assert!(Dashboard::grid(area, 0).is_empty());
+    }
+}
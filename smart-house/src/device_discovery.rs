@@ -0,0 +1,77 @@
+//! Module describes discovery of devices announced on the LAN
+
+use std::{collections::BTreeMap, net::SocketAddr, time::Duration};
+
+use discovery::{
+    announcement::{Announcement, DeviceKind},
+    listener::Listener,
+};
+
+use crate::{
+    errors,
+    smart_house::{DeviceBinding, SmartHouse},
+};
+
+/// Describes collector of devices announced to the multicast group
+pub struct DeviceDiscovery {
+    listener: Listener,
+    found: BTreeMap<(DeviceKind, String), Announcement>,
+}
+
+impl DeviceDiscovery {
+    /// Creates new discovery listening to the multicast `group`
+    pub fn new(group: SocketAddr) -> errors::Result<Self> {
+        Ok(Self {
+            listener: Listener::bind(group)?,
+            found: BTreeMap::new(),
+        })
+    }
+
+    /// Listens for announcements during `duration`
+    /// and returns all devices found so far
+    pub fn scan(&mut self, duration: Duration) -> errors::Result<Vec<&Announcement>> {
+        for announcement in self.listener.scan(duration)? {
+            self.found
+                .insert((announcement.kind, announcement.id.clone()), announcement);
+        }
+
+        Ok(self.devices())
+    }
+
+    /// Returns all devices found so far
+    pub fn devices(&self) -> Vec<&Announcement> {
+        self.found.values().collect()
+    }
+
+    /// Adds found devices to rooms of the `house` and binds them
+    /// to announced endpoints. Device is placed in the room it announces,
+    /// otherwise in `default_room`. Rooms are created when missing,
+    /// devices already present in the room are skipped.
+    ///
+    /// Returns room name and device name of each added device.
+    pub fn add_to_house(
+        &self,
+        house: &mut SmartHouse,
+        default_room: &str,
+    ) -> Vec<(String, String)> {
+        let mut added = Vec::new();
+
+        for announcement in self.found.values() {
+            let room_name = announcement.room.as_deref().unwrap_or(default_room);
+
+            if !house.add_device(room_name, &announcement.id) {
+                continue;
+            }
+
+            _ = house.bind_device(
+                room_name,
+                &announcement.id,
+                DeviceBinding::from(announcement),
+            );
+
+            added.push((room_name.to_owned(), announcement.id.clone()));
+        }
+
+        added
+    }
+}
@@ -12,6 +12,65 @@ pub enum Error {
         device_name: String,
         room_name: String,
     },
+
+    /// Describes error in case of device not found in the smart house by identifier
+    #[error(r#"Not found device with id "{}""#, id)]
+    DeviceIdNotFoundError { id: String },
+
+    /// Describes error in case of room not found in the smart house
+    #[error(r#"Not found room "{}""#, room_name)]
+    RoomNotFoundError { room_name: String },
+
+    /// Describes error in case of room with the same name exists in the smart house
+    #[error(r#"Room "{}" already exists"#, room_name)]
+    RoomExistsError { room_name: String },
+
+    /// Describes error in case of device with the same name exists in the room
+    #[error(r#"Device "{}" already exists in room "{}""#, device_name, room_name)]
+    DeviceExistsError {
+        device_name: String,
+        room_name: String,
+    },
+
+    /// Describes error in case of group not found in the smart house
+    #[error(r#"Not found group "{}""#, group_name)]
+    GroupNotFoundError { group_name: String },
+
+    /// Describes error in case of scene not found in the smart house
+    #[error(r#"Not found scene "{}""#, scene_name)]
+    SceneNotFoundError { scene_name: String },
+
+    /// Describes error in case of device is not bound to the address
+    /// it is reached at
+    #[error(r#"Device "{}" in room "{}" is not bound"#, device_name, room_name)]
+    DeviceNotBoundError {
+        device_name: String,
+        room_name: String,
+    },
+
+    /// Describes error in case of device doesn't respond
+    #[error("Device is unavailable: {0}")]
+    DeviceUnavailableError(String),
+
+    /// Describes error in case of command is not supported by the device
+    #[error("Command is not supported by the device")]
+    UnsupportedCommandError,
+
+    /// Describes error in case of HTTP server can't be started
+    #[error("Failed to start HTTP server: {0}")]
+    ServerError(String),
+
+    /// Describes error of receiving announcements of devices
+    #[error("Failed to discover devices: {0}")]
+    DiscoveryError(#[from] std::io::Error),
+
+    /// Describes error of reading or writing recorded samples
+    #[error("Failed to access storage: {0}")]
+    StorageError(#[source] std::io::Error),
+
+    /// Describes error of reading configuration
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
 }
 
 /// Describes alias for the library Result type
@@ -0,0 +1,359 @@
+//! Module describes events about changes of device states
+
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{errors, registry::DeviceState};
+
+/// Describes change of device state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Power switch was turned on or off
+    SwitchToggled { enabled: bool },
+    /// Power consumption of power switch changed
+    PowerChanged { power: f64 },
+    /// New reading of thermometer was received
+    TemperatureReading {
+        /// Temperature in degrees Celsius
+        temperature: f64,
+        /// Values of all metrics provided by sender by metric name
+        measurements: BTreeMap<String, f64>,
+    },
+    /// Device stopped responding
+    DeviceStale { error: String },
+    /// Device was renamed or moved to another room,
+    /// the event is about the device under its previous name
+    DeviceRenamed {
+        new_room: String,
+        new_device: String,
+    },
+    /// Power switch was turned off because total power exceeded the budget
+    LoadShed {
+        /// Power of the switch before it was turned off
+        power: f64,
+        /// Total power of all switches before the switch was turned off
+        total_power: f64,
+        limit: f64,
+    },
+    /// Power switch turned off by the budget was turned on again
+    LoadRestored {
+        /// Power of the switch before it was turned off
+        power: f64,
+        /// Total power of all switches before the switch was turned on
+        total_power: f64,
+        /// `None` if switch was restored because the budget was removed
+        limit: Option<f64>,
+    },
+}
+
+/// Describes event about device of the smart house
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HouseEvent {
+    /// Time of the event in milliseconds since Unix epoch
+    pub timestamp: u64,
+    pub room: String,
+    pub device: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Describes filter of events by room and device,
+/// `None` matches any room or device
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub room: Option<String>,
+    pub device: Option<String>,
+}
+
+impl EventFilter {
+    /// Returns `true` if `event` passes the filter
+    pub fn matches(&self, event: &HouseEvent) -> bool {
+        self.room.as_ref().is_none_or(|room| *room == event.room)
+            && self
+                .device
+                .as_ref()
+                .is_none_or(|device| *device == event.device)
+    }
+}
+
+/// Describes key of device by room name and device name
+pub type DeviceKey = (String, String);
+
+/// Describes the last observed state of device
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObservedState {
+    /// The last received state, `None` if device has never responded
+    pub state: Option<DeviceState>,
+    /// Time of the last received state in milliseconds since Unix epoch
+    pub updated: Option<u64>,
+    /// `true` if the last query of device failed
+    pub stale: bool,
+}
+
+/// Describes source of events. It remembers the last observed state
+/// of each device and publishes events about changes to subscribers.
+#[derive(Debug, Default)]
+pub struct HouseEvents {
+    subscribers: Mutex<Vec<(EventFilter, mpsc::Sender<HouseEvent>)>>,
+    states: Mutex<BTreeMap<DeviceKey, ObservedState>>,
+}
+
+impl HouseEvents {
+    /// Creates new source of events without subscribers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns receiver of events passing the `filter`
+    pub fn subscribe(&self, filter: EventFilter) -> mpsc::Receiver<HouseEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push((filter, tx));
+        rx
+    }
+
+    /// Returns the last observed states of devices
+    pub fn states(&self) -> BTreeMap<DeviceKey, ObservedState> {
+        self.states.lock().unwrap().clone()
+    }
+
+    /// Compares result of querying the device with its previous state
+    /// and publishes events about changes
+    pub fn observe(&self, room: &str, device: &str, result: &errors::Result<DeviceState>) {
+        let key = (room.to_owned(), device.to_owned());
+        let previous = {
+            let mut states = self.states.lock().unwrap();
+            let previous = states.get(&key).cloned();
+            let current = match result {
+                Ok(state) => ObservedState {
+                    state: Some(state.clone()),
+                    updated: Some(now()),
+                    stale: false,
+                },
+                Err(_) => ObservedState {
+                    stale: true,
+                    ..previous.clone().unwrap_or_default()
+                },
+            };
+            states.insert(key, current);
+            previous
+        };
+
+        let kinds = match (result, previous) {
+            (Ok(DeviceState::PowerSwitch { enabled, power, .. }), previous) => {
+                let (was_enabled, was_power) = match previous {
+                    Some(ObservedState {
+                        state: Some(DeviceState::PowerSwitch { enabled, power, .. }),
+                        stale: false,
+                        ..
+                    }) => (Some(enabled), Some(power)),
+                    _ => (None, None),
+                };
+
+                let mut kinds = Vec::new();
+                if was_enabled != Some(*enabled) {
+                    kinds.push(EventKind::SwitchToggled { enabled: *enabled });
+                }
+                if was_power != Some(*power) {
+                    kinds.push(EventKind::PowerChanged { power: *power });
+                }
+                kinds
+            }
+            (
+                Ok(DeviceState::Thermometer {
+                    temperature,
+                    measurements,
+                }),
+                _,
+            ) => vec![EventKind::TemperatureReading {
+                temperature: *temperature,
+                measurements: measurements.clone(),
+            }],
+            // Stale device is reported once until it responds again
+            (Err(_), Some(ObservedState { stale: true, .. })) => Vec::new(),
+            (Err(err), _) => vec![EventKind::DeviceStale {
+                error: err.to_string(),
+            }],
+        };
+
+        for kind in kinds {
+            self.publish(HouseEvent {
+                timestamp: now(),
+                room: room.to_owned(),
+                device: device.to_owned(),
+                kind,
+            });
+        }
+    }
+
+    /// Forgets states of devices for which `keep` returns `false`,
+    /// e.g. of removed devices
+    pub fn retain(&self, keep: impl Fn(&str, &str) -> bool) {
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|(room, device), _| keep(room, device));
+    }
+
+    /// Moves the last observed state of device to its new name
+    /// and publishes event about renaming
+    pub fn rename(&self, room: &str, device: &str, new_room: &str, new_device: &str) {
+        {
+            let mut states = self.states.lock().unwrap();
+            if let Some(state) = states.remove(&(room.to_owned(), device.to_owned())) {
+                states.insert((new_room.to_owned(), new_device.to_owned()), state);
+            }
+        }
+
+        self.publish(HouseEvent {
+            timestamp: now(),
+            room: room.to_owned(),
+            device: device.to_owned(),
+            kind: EventKind::DeviceRenamed {
+                new_room: new_room.to_owned(),
+                new_device: new_device.to_owned(),
+            },
+        });
+    }
+
+    /// Publishes `event` to subscribers
+    pub fn publish(&self, event: HouseEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|(filter, tx)| !filter.matches(&event) || tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Returns current time in milliseconds since Unix epoch
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+
+    fn kinds(rx: &mpsc::Receiver<HouseEvent>) -> Vec<EventKind> {
+        rx.try_iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn test_publish_only_changes_of_switch() {
+        let events = HouseEvents::new();
+        let rx = events.subscribe(EventFilter::default());
+        let on = DeviceState::PowerSwitch {
+            enabled: true,
+            power: 100.0,
+            energy: Some(1.0),
+        };
+
+        events.observe("Hall", "switch1", &Ok(on.clone()));
+        events.observe("Hall", "switch1", &Ok(on));
+        events.observe(
+            "Hall",
+            "switch1",
+            &Ok(DeviceState::PowerSwitch {
+                enabled: true,
+                power: 120.0,
+                energy: Some(2.0),
+            }),
+        );
+
+        assert_eq!(
+            kinds(&rx),
+            vec![
+                EventKind::SwitchToggled { enabled: true },
+                EventKind::PowerChanged { power: 100.0 },
+                EventKind::PowerChanged { power: 120.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stale_device_is_reported_once() {
+        let events = HouseEvents::new();
+        let rx = events.subscribe(EventFilter::default());
+        let unavailable = || Err(Error::DeviceUnavailableError("timed out".to_owned()));
+
+        events.observe("Hall", "therm1", &unavailable());
+        events.observe("Hall", "therm1", &unavailable());
+
+        let observed = &events.states()[&("Hall".to_owned(), "therm1".to_owned())];
+        assert!(observed.stale);
+        assert_eq!(observed.updated, None);
+
+        assert_eq!(
+            kinds(&rx),
+            vec![EventKind::DeviceStale {
+                error: "Device is unavailable: timed out".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rename_moves_observed_state() {
+        let events = HouseEvents::new();
+        let on = DeviceState::PowerSwitch {
+            enabled: true,
+            power: 100.0,
+            energy: Some(1.0),
+        };
+        events.observe("Hall", "switch1", &Ok(on.clone()));
+        let rx = events.subscribe(EventFilter::default());
+
+        events.rename("Hall", "switch1", "Kitchen", "kettle");
+
+        let states = events.states();
+        assert!(!states.contains_key(&("Hall".to_owned(), "switch1".to_owned())));
+        assert_eq!(
+            states[&("Kitchen".to_owned(), "kettle".to_owned())].state,
+            Some(on)
+        );
+        assert_eq!(
+            kinds(&rx),
+            vec![EventKind::DeviceRenamed {
+                new_room: "Kitchen".to_owned(),
+                new_device: "kettle".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_room_and_device() {
+        let events = HouseEvents::new();
+        let hall = events.subscribe(EventFilter {
+            room: Some("Hall".to_owned()),
+            device: None,
+        });
+        let switch2 = events.subscribe(EventFilter {
+            room: None,
+            device: Some("switch2".to_owned()),
+        });
+        let state = DeviceState::PowerSwitch {
+            enabled: false,
+            power: 0.0,
+            energy: Some(0.0),
+        };
+
+        events.observe("Hall", "switch1", &Ok(state.clone()));
+        events.observe("Bathroom", "switch2", &Ok(state));
+
+        let hall: Vec<HouseEvent> = hall.try_iter().collect();
+        let switch2: Vec<HouseEvent> = switch2.try_iter().collect();
+
+        assert_eq!(hall.len(), 2);
+        assert!(hall.iter().all(|event| event.room == "Hall"));
+        assert_eq!(switch2.len(), 2);
+        assert!(switch2.iter().all(|event| event.device == "switch2"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Module describes reading and writing files of the smart house
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::errors::{
+    self,
+    Error::{ConfigError, StorageError},
+};
+
+/// Reads value from JSON file at `path`, failing with the path in the message
+pub fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> errors::Result<T> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .map_err(|err| ConfigError(format!("{}: {err}", path.display())))?;
+
+    serde_json::from_str(&content).map_err(|err| ConfigError(format!("{}: {err}", path.display())))
+}
+
+/// Writes `content` to the file at `path` replacing the previous content at once,
+/// so the file is never left partially written
+pub fn write_atomically(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> errors::Result<()> {
+    let path = path.as_ref();
+    let tmp_path = tmp_path(path);
+
+    fs::write(&tmp_path, content).map_err(StorageError)?;
+    fs::rename(&tmp_path, path).map_err(StorageError)
+}
+
+/// Returns path of temporary file for the file at `path`.
+/// Suffix is appended to the whole file name, so files which differ
+/// only in extension, e.g. `house.json` and `house.jsonl`, don't share it
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".tmp");
+    name.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tmp_path_keeps_extension() {
+        assert_eq!(
+            tmp_path(Path::new("data/house.json")),
+            Path::new("data/house.json.tmp")
+        );
+        assert_ne!(
+            tmp_path(Path::new("house.json")),
+            tmp_path(Path::new("house.jsonl"))
+        );
+    }
+
+    #[test]
+    fn test_read_json_names_file_in_error() {
+        let path = std::env::temp_dir().join(format!("smart-house-json-{}", std::process::id()));
+
+        write_atomically(&path, "[1, 2]").unwrap();
+        assert_eq!(read_json::<Vec<u8>>(&path).unwrap(), vec![1, 2]);
+
+        write_atomically(&path, "[1,").unwrap();
+        let err = read_json::<Vec<u8>>(&path).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+
+        fs::remove_file(&path).unwrap();
+        assert!(read_json::<Vec<u8>>(&path).is_err());
+    }
+
+    #[test]
+    fn test_write_atomically_replaces_content() {
+        let path = std::env::temp_dir().join(format!("smart-house-files-{}", std::process::id()));
+
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!tmp_path(&path).exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
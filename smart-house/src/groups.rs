@@ -0,0 +1,259 @@
+//! Module describes bulk commands and aggregated queries of device groups, e.g.
+//!
+//! ```json
+//! {
+//!     "groups": {
+//!         "heaters": [
+//!             { "room": "Bathroom", "device": "switch1" },
+//!             { "room": "Kitchen", "device": "switch1" }
+//!         ],
+//!         "ground floor": [{ "room": "Kitchen", "device": "switch1" }]
+//!     }
+//! }
+//! ```
+
+use std::{collections::BTreeMap, path::Path, sync::Mutex, thread};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{self, Error},
+    events::HouseEvents,
+    files,
+    registry::{DeviceRegistry, DeviceState},
+    scenes::{self, SceneReport},
+    smart_house::{DeviceBinding, GroupMembers, Scene, SmartHouse},
+};
+
+/// Describes device in the group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupMember {
+    pub room: String,
+    pub device: String,
+}
+
+/// Describes configuration file of groups
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupConfig {
+    pub groups: BTreeMap<String, Vec<GroupMember>>,
+}
+
+impl GroupConfig {
+    /// Reads configuration from JSON file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+}
+
+/// Describes error of device in aggregated query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceError {
+    pub room: String,
+    pub device: String,
+    pub error: String,
+}
+
+/// Describes aggregated state of devices in the group
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GroupSummary {
+    pub group: String,
+    /// Number of devices in the group
+    pub devices: usize,
+    /// Number of devices which responded
+    pub reachable: usize,
+    pub switches_on: usize,
+    pub switches_off: usize,
+    /// Total power consumption of switches in watts
+    pub total_power: f64,
+    /// Total energy consumed by switches reporting energy in watt-hours
+    pub total_energy: f64,
+    /// Temperatures of thermometers in degrees Celsius
+    pub average_temperature: Option<f64>,
+    pub min_temperature: Option<f64>,
+    pub max_temperature: Option<f64>,
+    pub errors: Vec<DeviceError>,
+}
+
+impl GroupSummary {
+    /// Aggregates `states` of devices of the group
+    pub fn new(group_name: &str, states: &[(GroupMember, errors::Result<DeviceState>)]) -> Self {
+        let mut summary = Self {
+            group: group_name.to_owned(),
+            devices: states.len(),
+            ..Self::default()
+        };
+        let mut temperatures = Vec::new();
+
+        for (member, state) in states {
+            match state {
+                Ok(DeviceState::PowerSwitch {
+                    enabled,
+                    power,
+                    energy,
+                }) => {
+                    if *enabled {
+                        summary.switches_on += 1;
+                    } else {
+                        summary.switches_off += 1;
+                    }
+                    summary.total_power += power;
+                    summary.total_energy += energy.unwrap_or_default();
+                }
+                Ok(DeviceState::Thermometer { temperature, .. }) => temperatures.push(*temperature),
+                Err(err) => summary.errors.push(DeviceError {
+                    room: member.room.clone(),
+                    device: member.device.clone(),
+                    error: err.to_string(),
+                }),
+            }
+        }
+
+        summary.reachable = summary.devices - summary.errors.len();
+
+        if !temperatures.is_empty() {
+            summary.average_temperature =
+                Some(temperatures.iter().sum::<f64>() / temperatures.len() as f64);
+            summary.min_temperature = temperatures.iter().copied().reduce(f64::min);
+            summary.max_temperature = temperatures.iter().copied().reduce(f64::max);
+        }
+
+        summary
+    }
+}
+
+/// Returns members of the group
+fn members(house: &SmartHouse, group_name: &str) -> errors::Result<GroupMembers> {
+    house
+        .group(group_name)
+        .cloned()
+        .ok_or_else(|| Error::GroupNotFoundError {
+            group_name: group_name.to_owned(),
+        })
+}
+
+/// Turns all power switches of the group on or off concurrently,
+/// thermometers of the group are skipped
+pub fn turn(
+    group_name: &str,
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    enabled: bool,
+) -> errors::Result<SceneReport> {
+    let scene = {
+        let house = house.lock().unwrap();
+        let mut scene = Scene::new(group_name);
+
+        for (room, device) in members(&house, group_name)? {
+            if !matches!(
+                house.binding(&room, &device),
+                Some(DeviceBinding::Thermometer { .. })
+            ) {
+                scene.set_state(&room, &device, enabled);
+            }
+        }
+
+        scene
+    };
+
+    Ok(scenes::apply(&scene, house, registry, events, false))
+}
+
+/// Queries all devices of the group concurrently and aggregates their states
+pub fn summary(
+    group_name: &str,
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+) -> errors::Result<GroupSummary> {
+    let devices: Vec<_> = {
+        let house = house.lock().unwrap();
+
+        members(&house, group_name)?
+            .into_iter()
+            .map(|(room, device)| {
+                let binding = house.binding(&room, &device).cloned();
+                (GroupMember { room, device }, binding)
+            })
+            .collect()
+    };
+
+    let states: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = devices
+            .into_iter()
+            .map(|(member, binding)| {
+                scope.spawn(move || {
+                    let state = match binding {
+                        Some(binding) => registry.state(&binding),
+                        None => Err(Error::DeviceNotBoundError {
+                            device_name: member.device.clone(),
+                            room_name: member.room.clone(),
+                        }),
+                    };
+                    (member, state)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Querying device panicked"))
+            .collect()
+    });
+
+    Ok(GroupSummary::new(group_name, &states))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(room: &str, device: &str) -> GroupMember {
+        GroupMember {
+            room: room.to_owned(),
+            device: device.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_states() {
+        let switch = |enabled, power| {
+            Ok(DeviceState::PowerSwitch {
+                enabled,
+                power,
+                energy: Some(10.0),
+            })
+        };
+        let thermometer = |temperature| {
+            Ok(DeviceState::Thermometer {
+                temperature,
+                measurements: BTreeMap::new(),
+            })
+        };
+
+        let summary = GroupSummary::new(
+            "ground floor",
+            &[
+                (member("Kitchen", "kettle"), switch(true, 2000.0)),
+                (member("Hall", "heater"), switch(true, 1500.0)),
+                (member("Hall", "lamp"), switch(false, 0.0)),
+                (member("Kitchen", "therm1"), thermometer(20.0)),
+                (member("Hall", "therm1"), thermometer(23.0)),
+                (
+                    member("Hall", "therm2"),
+                    Err(Error::DeviceUnavailableError("timed out".to_owned())),
+                ),
+            ],
+        );
+
+        assert_eq!(summary.devices, 6);
+        assert_eq!(summary.reachable, 5);
+        assert_eq!(summary.switches_on, 2);
+        assert_eq!(summary.switches_off, 1);
+        assert_eq!(summary.total_power, 3500.0);
+        assert_eq!(summary.total_energy, 30.0);
+        assert_eq!(summary.average_temperature, Some(21.5));
+        assert_eq!(summary.min_temperature, Some(20.0));
+        assert_eq!(summary.max_temperature, Some(23.0));
+        assert_eq!(summary.errors[0].device, "therm2");
+    }
+}
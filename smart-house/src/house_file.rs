@@ -0,0 +1,224 @@
+//! Module describes file the smart house is stored in, e.g.
+//!
+//! ```json
+//! {
+//!     "name": "Our house",
+//!     "rooms": [
+//!         {
+//!             "name": "Bathroom",
+//!             "devices": [
+//!                 {
+//!                     "id": "0b5e7f32-5c1a-4d8e-9f0a-2f4c6a8b1d3e",
+//!                     "name": "switch1",
+//!                     "binding": { "kind": "power_switch", "address": "127.0.0.1:53453" }
+//!                 },
+//!                 { "name": "therm1", "binding": { "kind": "thermometer", "sender": "127.0.0.1:3333" } }
+//!             ]
+//!         }
+//!     ],
+//!     "groups": { "heaters": [{ "room": "Bathroom", "device": "switch1" }] },
+//!     "scenes": [],
+//!     "budget": null
+//! }
+//! ```
+//!
+//! Devices without `id` get new identifiers when the file is loaded.
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{self, Error::StorageError},
+    files,
+    groups::GroupMember,
+    smart_house::{DeviceBinding, DeviceId, PowerBudget, Room, Scene, SmartHouse},
+};
+
+/// Describes device of the room in the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<DeviceId>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<DeviceBinding>,
+}
+
+/// Describes room of the house in the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomEntry {
+    pub name: String,
+    #[serde(default)]
+    pub devices: Vec<DeviceEntry>,
+}
+
+/// Describes content of the house file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HouseFile {
+    pub name: String,
+    #[serde(default)]
+    pub rooms: Vec<RoomEntry>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<GroupMember>>,
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    #[serde(default)]
+    pub budget: Option<PowerBudget>,
+}
+
+impl HouseFile {
+    /// Reads the file at `path`
+    pub fn load(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+
+    /// Writes the file to `path` replacing the previous content at once
+    pub fn save(&self, path: impl AsRef<Path>) -> errors::Result<()> {
+        let path = path.as_ref();
+        let content =
+            serde_json::to_string_pretty(self).map_err(|err| StorageError(io::Error::from(err)))?;
+
+        files::write_atomically(path, content)
+    }
+}
+
+impl From<&SmartHouse> for HouseFile {
+    fn from(house: &SmartHouse) -> Self {
+        let rooms = house
+            .get_rooms()
+            .values()
+            .map(|room| RoomEntry {
+                name: room.get_name().to_owned(),
+                devices: room
+                    .get_devices()
+                    .iter()
+                    .map(|device| DeviceEntry {
+                        id: house.device_id(room.get_name(), device),
+                        name: device.clone(),
+                        binding: house.binding(room.get_name(), device).cloned(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let groups = house
+            .get_groups()
+            .iter()
+            .map(|(group_name, members)| {
+                let members = members
+                    .iter()
+                    .map(|(room, device)| GroupMember {
+                        room: room.clone(),
+                        device: device.clone(),
+                    })
+                    .collect();
+
+                (group_name.clone(), members)
+            })
+            .collect();
+
+        Self {
+            name: house.get_name().to_owned(),
+            rooms,
+            groups,
+            scenes: house.get_scenes().values().cloned().collect(),
+            budget: house.power_budget().cloned(),
+        }
+    }
+}
+
+impl From<HouseFile> for SmartHouse {
+    /// Creates house from the file,
+    /// group members referring to absent devices are skipped,
+    /// devices with duplicate identifiers get new ones
+    fn from(file: HouseFile) -> Self {
+        let mut house = SmartHouse::new(&file.name);
+
+        for room in file.rooms {
+            _ = house.add_room(Room::new(&room.name));
+
+            for device in room.devices {
+                let added = device
+                    .id
+                    .is_some_and(|id| house.add_device_with_id(&room.name, &device.name, id));
+                if !added {
+                    _ = house.add_device(&room.name, &device.name);
+                }
+
+                if let Some(binding) = device.binding {
+                    _ = house.bind_device(&room.name, &device.name, binding);
+                }
+            }
+        }
+
+        for (group_name, members) in file.groups {
+            for member in members {
+                _ = house.add_to_group(&group_name, &member.room, &member.device);
+            }
+        }
+
+        for scene in file.scenes {
+            _ = house.add_scene(scene);
+        }
+
+        house.set_power_budget(file.budget);
+
+        house
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn house() -> SmartHouse {
+        let mut house = SmartHouse::new("Test house");
+        _ = house.add_room(Room::new("Hall"));
+        _ = house.add_device("Bathroom", "switch1");
+        _ = house.bind_device(
+            "Bathroom",
+            "switch1",
+            DeviceBinding::PowerSwitch {
+                address: "127.0.0.1:53453".parse().unwrap(),
+            },
+        );
+        _ = house.add_device("Bathroom", "therm1");
+        _ = house.add_to_group("heaters", "Bathroom", "switch1");
+
+        let mut scene = Scene::new("Night");
+        scene.set_state("Bathroom", "switch1", false);
+        _ = house.add_scene(scene);
+
+        let mut budget = PowerBudget::new(3000.0);
+        budget.set_priority("Bathroom", "switch1", 5);
+        house.set_power_budget(Some(budget));
+
+        house
+    }
+
+    #[test]
+    fn test_house_is_saved_and_loaded() {
+        let path = env::temp_dir().join(format!("smart-house-file-{}.json", std::process::id()));
+        let house = house();
+
+        HouseFile::from(&house).save(&path).unwrap();
+        let loaded = SmartHouse::from(HouseFile::load(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.get_name(), "Test house");
+        assert!(loaded.devices("Hall").unwrap().is_empty());
+        assert_eq!(loaded.devices("Bathroom").unwrap().len(), 2);
+        assert_eq!(
+            loaded.binding("Bathroom", "switch1"),
+            house.binding("Bathroom", "switch1")
+        );
+        assert_eq!(loaded.binding("Bathroom", "therm1"), None);
+        assert_eq!(loaded.get_device_ids(), house.get_device_ids());
+        assert_eq!(loaded.groups_of("Bathroom", "switch1"), vec!["heaters"]);
+        assert_eq!(loaded.scene("Night"), house.scene("Night"));
+        assert_eq!(loaded.power_budget(), house.power_budget());
+    }
+}
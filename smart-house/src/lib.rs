@@ -1,2 +1,20 @@
+pub mod api;
+pub mod automation;
+pub mod dashboard;
+pub mod device_discovery;
 pub mod errors;
+pub mod events;
+pub mod files;
+pub mod groups;
+pub mod house_file;
+pub mod load_shedding;
+pub mod metrics;
+pub mod monitor;
+pub mod mqtt;
+pub mod recorder;
+pub mod registry;
+pub mod scenes;
+pub mod scheduler;
 pub mod smart_house;
+pub mod tariff;
+pub mod thermostat;
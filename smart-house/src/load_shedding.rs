@@ -0,0 +1,498 @@
+//! Module describes keeping total power of power switches within
+//! [`PowerBudget`] of the house by turning off switches of low priority
+//! and turning them on again when headroom returns
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    events::{self, DeviceKey, EventKind, HouseEvent, HouseEvents},
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::{DeviceBinding, PowerBudget, SmartHouse},
+};
+
+/// Describes live state of power switch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwitchLoad {
+    pub enabled: bool,
+    /// Current power consumption in watts
+    pub power: f64,
+}
+
+/// Describes power switch turned off to keep the budget
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShedSwitch {
+    pub room: String,
+    pub device: String,
+    /// Power of the switch before it was turned off
+    pub power: f64,
+}
+
+/// Describes decision of the shedder
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// Switch has to be turned off
+    Shed(ShedSwitch),
+    /// Switch has to be turned on again
+    Restore(ShedSwitch),
+}
+
+/// Describes logic of load shedding. It remembers switches it turned off.
+#[derive(Debug, Default)]
+pub struct LoadShedder {
+    shed: Vec<ShedSwitch>,
+}
+
+impl LoadShedder {
+    /// Creates shedder which hasn't turned off any switch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns switches turned off to keep the budget
+    pub fn shed(&self) -> &[ShedSwitch] {
+        &self.shed
+    }
+
+    /// Makes switch turned off by the shedder refer to its new room and name
+    pub fn rename_device(&mut self, room: &str, device: &str, new_room: &str, new_device: &str) {
+        for switch in &mut self.shed {
+            if switch.room == room && switch.device == device {
+                (switch.room, switch.device) = (new_room.to_owned(), new_device.to_owned());
+            }
+        }
+    }
+
+    /// Returns switches to turn off or on given live `loads` of all switches.
+    /// Decisions are assumed to succeed, failed ones are reverted
+    /// by [`LoadShedder::revert`].
+    pub fn decide(
+        &mut self,
+        budget: &PowerBudget,
+        loads: &BTreeMap<DeviceKey, SwitchLoad>,
+    ) -> Vec<Decision> {
+        let priority = |switch: &ShedSwitch| budget.priority(&switch.room, &switch.device);
+
+        // Switches turned on by somebody else are forgotten,
+        // unknown switches may come back
+        self.shed.retain(|switch| {
+            loads
+                .get(&(switch.room.clone(), switch.device.clone()))
+                .is_none_or(|load| !load.enabled)
+        });
+
+        let mut total: f64 = loads
+            .values()
+            .filter(|load| load.enabled)
+            .map(|load| load.power)
+            .sum();
+        let mut decisions = Vec::new();
+
+        if total > budget.limit {
+            let mut candidates: Vec<_> = budget
+                .priorities
+                .iter()
+                .filter_map(|switch| {
+                    let key = (switch.room.clone(), switch.device.clone());
+                    let load = loads.get(&key).filter(|load| load.enabled)?;
+                    Some((switch.priority, key, load.power))
+                })
+                .collect();
+            candidates.sort_by_key(|(priority, ..)| *priority);
+
+            for (_, (room, device), power) in candidates {
+                if total <= budget.limit {
+                    break;
+                }
+
+                let switch = ShedSwitch {
+                    room,
+                    device,
+                    power,
+                };
+                self.shed.push(switch.clone());
+                decisions.push(Decision::Shed(switch));
+                total -= power;
+            }
+        } else {
+            // Switches of higher priority are restored first,
+            // switches which don't fit into headroom wait
+            self.shed
+                .sort_by_key(|switch| std::cmp::Reverse(priority(switch)));
+
+            for switch in std::mem::take(&mut self.shed) {
+                if total + switch.power <= budget.limit - budget.margin {
+                    total += switch.power;
+                    decisions.push(Decision::Restore(switch));
+                } else {
+                    self.shed.push(switch);
+                }
+            }
+        }
+
+        decisions
+    }
+
+    /// Reverts decision which failed to be performed
+    pub fn revert(&mut self, decision: &Decision) {
+        match decision {
+            Decision::Shed(switch) => self.shed.retain(|shed| shed != switch),
+            Decision::Restore(switch) => self.shed.push(switch.clone()),
+        }
+    }
+
+    /// Forgets all switches, e.g. when budget is removed
+    pub fn take_all(&mut self) -> Vec<Decision> {
+        self.shed.drain(..).map(Decision::Restore).collect()
+    }
+}
+
+/// Describes thread which periodically sums power of all power switches
+/// of the house and keeps it within the budget of the house.
+/// Each decision is published to [`HouseEvents`].
+/// Control stops when controller is dropped.
+pub struct LoadController {
+    shedder: Arc<Mutex<LoadShedder>>,
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LoadController {
+    /// Starts checking the budget of the `house` each `interval`
+    pub fn start(
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        events: Arc<HouseEvents>,
+        interval: Duration,
+    ) -> Self {
+        let shedder = Arc::new(Mutex::new(LoadShedder::new()));
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn({
+            let shedder = shedder.clone();
+
+            move || loop {
+                control(&house, &registry, &events, &shedder);
+
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            shedder,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns switches turned off to keep the budget
+    pub fn shed(&self) -> Vec<ShedSwitch> {
+        self.shedder.lock().unwrap().shed().to_vec()
+    }
+
+    /// Makes switch turned off to keep the budget refer to its new room and name
+    pub fn rename_device(&self, room: &str, device: &str, new_room: &str, new_device: &str) {
+        self.shedder
+            .lock()
+            .unwrap()
+            .rename_device(room, device, new_room, new_device);
+    }
+}
+
+impl Drop for LoadController {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+/// Queries power switches of the house and turns them off or on
+/// according to decisions of the shedder
+fn control(
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    shedder: &Mutex<LoadShedder>,
+) {
+    let (budget, switches) = {
+        let house = house.lock().unwrap();
+        let switches: Vec<_> = house
+            .get_bindings()
+            .iter()
+            .filter(|(_, binding)| matches!(binding, DeviceBinding::PowerSwitch { .. }))
+            .map(|(key, binding)| (key.clone(), binding.clone()))
+            .collect();
+
+        (house.power_budget().cloned(), switches)
+    };
+
+    // Switches are queried only if there is something to do
+    if budget.is_none() && shedder.lock().unwrap().shed().is_empty() {
+        return;
+    }
+
+    // Switches are queried without holding the lock of the house
+    let loads = query_loads(&switches, registry, events);
+    let bindings: BTreeMap<_, _> = switches.into_iter().collect();
+
+    let mut shedder = shedder.lock().unwrap();
+    let (decisions, limit) = match &budget {
+        Some(budget) => (shedder.decide(budget, &loads), Some(budget.limit)),
+        // Switches are restored when the budget is removed
+        None => (shedder.take_all(), None),
+    };
+
+    let mut total: f64 = loads
+        .values()
+        .filter(|load| load.enabled)
+        .map(|load| load.power)
+        .sum();
+
+    for decision in decisions {
+        let (switch, enabled) = match &decision {
+            Decision::Shed(switch) => (switch, false),
+            Decision::Restore(switch) => (switch, true),
+        };
+
+        let Some(binding) = bindings.get(&(switch.room.clone(), switch.device.clone())) else {
+            continue;
+        };
+
+        let result = registry.turn(binding, enabled);
+        events.observe(&switch.room, &switch.device, &result);
+
+        if let Err(err) = result {
+            println!(
+                "Failed to turn {} {} in {}: {err}",
+                if enabled { "on" } else { "off" },
+                switch.device,
+                switch.room
+            );
+            shedder.revert(&decision);
+            continue;
+        }
+
+        let kind = if enabled {
+            EventKind::LoadRestored {
+                power: switch.power,
+                total_power: total,
+                limit,
+            }
+        } else {
+            EventKind::LoadShed {
+                power: switch.power,
+                total_power: total,
+                limit: limit.unwrap_or_default(),
+            }
+        };
+        total += if enabled { switch.power } else { -switch.power };
+
+        events.publish(HouseEvent {
+            timestamp: events::now(),
+            room: switch.room.clone(),
+            device: switch.device.clone(),
+            kind,
+        });
+    }
+}
+
+/// Queries power switches concurrently, unreachable switches are skipped
+fn query_loads(
+    switches: &[(DeviceKey, DeviceBinding)],
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+) -> BTreeMap<DeviceKey, SwitchLoad> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = switches
+            .iter()
+            .map(|((room, device), binding)| {
+                scope.spawn(move || {
+                    let result = registry.state(binding);
+                    events.observe(room, device, &result);
+
+                    match result {
+                        Ok(DeviceState::PowerSwitch { enabled, power, .. }) => Some((
+                            (room.clone(), device.clone()),
+                            SwitchLoad { enabled, power },
+                        )),
+                        _ => None,
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().expect("Querying switch panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(device: &str) -> DeviceKey {
+        ("Hall".to_owned(), device.to_owned())
+    }
+
+    fn loads(switches: &[(&str, bool, f64)]) -> BTreeMap<DeviceKey, SwitchLoad> {
+        switches
+            .iter()
+            .map(|(device, enabled, power)| {
+                (
+                    key(device),
+                    SwitchLoad {
+                        enabled: *enabled,
+                        power: *power,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn devices(decisions: &[Decision]) -> Vec<(&str, bool)> {
+        decisions
+            .iter()
+            .map(|decision| match decision {
+                Decision::Shed(switch) => (switch.device.as_str(), false),
+                Decision::Restore(switch) => (switch.device.as_str(), true),
+            })
+            .collect()
+    }
+
+    fn budget() -> PowerBudget {
+        let mut budget = PowerBudget::new(3000.0);
+        budget.margin = 200.0;
+        budget.set_priority("Hall", "heater", 1);
+        budget.set_priority("Hall", "dryer", 2);
+        budget.set_priority("Hall", "oven", 3);
+        budget
+    }
+
+    #[test]
+    fn test_shed_by_priority() {
+        let mut shedder = LoadShedder::new();
+        let budget = budget();
+
+        // Fridge may not be turned off
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("fridge", true, 500.0),
+                ("heater", true, 1000.0),
+                ("dryer", true, 1500.0),
+                ("oven", true, 1000.0),
+            ]),
+        );
+        assert_eq!(devices(&decisions), vec![("heater", false)]);
+
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("fridge", true, 2500.0),
+                ("heater", false, 0.0),
+                ("dryer", true, 1500.0),
+                ("oven", true, 1000.0),
+            ]),
+        );
+        assert_eq!(devices(&decisions), vec![("dryer", false), ("oven", false)]);
+        assert_eq!(shedder.shed().len(), 3);
+    }
+
+    #[test]
+    fn test_restore_when_headroom_returns() {
+        let mut shedder = LoadShedder::new();
+        let budget = budget();
+
+        shedder.decide(
+            &budget,
+            &loads(&[("heater", true, 1000.0), ("oven", true, 2500.0)]),
+        );
+        shedder.decide(
+            &budget,
+            &loads(&[("heater", false, 0.0), ("oven", true, 3500.0)]),
+        );
+        assert_eq!(shedder.shed().len(), 2);
+
+        // Heater doesn't fit into margin yet
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("heater", false, 0.0),
+                ("oven", false, 0.0),
+                ("fridge", true, 1900.0),
+            ]),
+        );
+        assert_eq!(devices(&decisions), vec![]);
+
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("heater", false, 0.0),
+                ("oven", false, 0.0),
+                ("fridge", true, 1700.0),
+            ]),
+        );
+        assert_eq!(devices(&decisions), vec![("heater", true)]);
+
+        // Dryer of higher priority is restored first, heater doesn't fit after it
+        let mut shedder = LoadShedder::new();
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("heater", true, 2000.0),
+                ("dryer", true, 1500.0),
+                ("oven", true, 2000.0),
+            ]),
+        );
+        assert_eq!(
+            devices(&decisions),
+            vec![("heater", false), ("dryer", false)]
+        );
+
+        let decisions = shedder.decide(
+            &budget,
+            &loads(&[
+                ("heater", false, 0.0),
+                ("dryer", false, 0.0),
+                ("oven", false, 0.0),
+            ]),
+        );
+        assert_eq!(devices(&decisions), vec![("dryer", true)]);
+        assert_eq!(shedder.shed()[0].device, "heater");
+    }
+
+    #[test]
+    fn test_forget_switch_turned_on_by_user() {
+        let mut shedder = LoadShedder::new();
+        let budget = budget();
+
+        shedder.decide(&budget, &loads(&[("heater", true, 3500.0)]));
+        assert_eq!(shedder.shed().len(), 1);
+
+        shedder.decide(&budget, &loads(&[("heater", true, 500.0)]));
+        assert!(shedder.shed().is_empty());
+
+        let decision = shedder
+            .decide(&budget, &loads(&[("heater", true, 3500.0)]))
+            .remove(0);
+        shedder.revert(&decision);
+        assert!(shedder.shed().is_empty());
+    }
+}
@@ -1,74 +1,590 @@
-use power_switch::power_switch::PowerSwitch;
-use smart_house::errors;
-use smart_house::smart_house::{DeviceInfoProvider, SmartHouse};
-use thermometer::thermometer::Thermometer;
+use std::{
+    error::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
 
-struct OwningDeviceInfoProvider {
-    switch1: PowerSwitch,
-    switch2: PowerSwitch,
-    thermometer1: Thermometer,
-    thermometer2: Thermometer,
+use chrono::Local;
+use clap::{Parser, Subcommand, ValueEnum};
+use smart_house::{
+    automation::RuleSet,
+    dashboard,
+    errors::Error::{
+        DeviceIdNotFoundError, DeviceNotBoundError, DeviceNotFoundError, GroupNotFoundError,
+        RoomNotFoundError,
+    },
+    events::HouseEvents,
+    groups,
+    house_file::HouseFile,
+    registry::DeviceRegistry,
+    scenes::SceneReport,
+    scheduler::ScheduleStore,
+    smart_house::{DeviceBinding, DeviceId, DeviceInfoProvider, Room, SmartHouse},
+};
+
+/// Manages the smart house stored in a file and shows states of its devices
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// JSON file of the smart house
+    #[clap(short, long, value_parser, default_value = "house.json")]
+    file: PathBuf,
+
+    /// Time to wait for response of device in milliseconds
+    #[clap(long, value_parser, default_value_t = 2000)]
+    timeout: u64,
+
+    /// JSON file with automation rules following renamed and moved devices
+    #[clap(long, value_parser)]
+    rules: Option<PathBuf>,
+
+    /// JSON file with schedule of jobs following renamed and moved devices
+    #[clap(long, value_parser)]
+    schedule: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Command,
 }
 
-impl DeviceInfoProvider for OwningDeviceInfoProvider {
-    fn report(&self, room_name: &str, device_name: &str) -> Option<String> {
-        match (room_name, device_name) {
-            ("Dinning room", "therm1") => Some(format!("{}", self.thermometer1)),
-            ("Dinning room", "switch1") => Some(format!("{}", self.switch1)),
-            ("Bathroom", "therm2") => Some(format!("{}", self.thermometer2)),
-            ("Bathroom", "switch1") => Some(format!("{}", self.switch2)),
-            (_, _) => None,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Creates file of new empty house
+    Init {
+        /// Name of the smart house
+        #[clap(short, long, value_parser, default_value = "Smart house")]
+        name: String,
+
+        /// Overwrite existing file
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Manages rooms
+    #[clap(subcommand)]
+    Room(RoomCommand),
+
+    /// Manages devices of rooms
+    #[clap(subcommand)]
+    Device(DeviceCommand),
+
+    /// Manages groups of devices spanning rooms
+    #[clap(subcommand)]
+    Group(GroupCommand),
+
+    /// Shows current states of all devices
+    Report,
+
+    /// Turns power switch on or off
+    Switch {
+        room: String,
+        device: String,
+        #[clap(value_enum)]
+        state: Turn,
+    },
+
+    /// Prints readings of device until interrupted
+    Tail {
+        room: String,
+        device: String,
+
+        /// Interval of readings in milliseconds
+        #[clap(short, long, value_parser, default_value_t = 1000)]
+        interval: u64,
+    },
+
+    /// Shows live panels of rooms in the terminal
+    Dashboard {
+        /// Interval of refreshing in milliseconds
+        #[clap(short, long, value_parser, default_value_t = 2000)]
+        interval: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RoomCommand {
+    /// Lists rooms with their devices
+    List,
+    /// Adds empty room
+    Add { room: String },
+    /// Removes room with its devices
+    Remove { room: String },
+    /// Renames room keeping references to its devices
+    Rename { room: String, new_name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum DeviceCommand {
+    /// Adds device to the room, the room is created if it doesn't exist
+    Add {
+        room: String,
+        device: String,
+        #[clap(flatten)]
+        binding: BindingArgs,
+    },
+    /// Binds device to the address it is reached at
+    Bind {
+        room: String,
+        device: String,
+        #[clap(flatten)]
+        binding: BindingArgs,
+    },
+    /// Finds room and name of device by its identifier
+    Find { id: DeviceId },
+    /// Removes device from the room
+    Remove { room: String, device: String },
+    /// Renames device keeping references to it
+    Rename {
+        room: String,
+        device: String,
+        new_name: String,
+    },
+    /// Moves device to another room keeping references to it
+    Move {
+        room: String,
+        device: String,
+        new_room: String,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct BindingArgs {
+    /// Address of power switch: <ip>:<port>
+    #[clap(long, value_parser, conflicts_with = "thermometer")]
+    switch: Option<SocketAddr>,
+
+    /// Address of thermometer sender: <ip>:<port>
+    #[clap(long, value_parser)]
+    thermometer: Option<SocketAddr>,
+}
+
+impl BindingArgs {
+    fn binding(&self) -> Option<DeviceBinding> {
+        match (self.switch, self.thermometer) {
+            (Some(address), _) => Some(DeviceBinding::PowerSwitch { address }),
+            (_, Some(sender)) => Some(DeviceBinding::Thermometer { sender }),
+            (None, None) => None,
         }
     }
 }
 
-struct BorrowingDeviceInfoProvider<'a> {
-    switch1: &'a PowerSwitch,
-    switch2: &'a PowerSwitch,
-    thermometer1: &'a Thermometer,
-    thermometer2: &'a Thermometer,
+#[derive(Subcommand, Debug)]
+enum GroupCommand {
+    /// Lists groups with their devices
+    List,
+    /// Adds device to the group, the group is created if it doesn't exist
+    Add {
+        group: String,
+        room: String,
+        device: String,
+    },
+    /// Removes device from the group
+    Remove {
+        group: String,
+        room: String,
+        device: String,
+    },
+    /// Removes the whole group
+    Delete { group: String },
+    /// Turns all power switches of the group on or off
+    Switch {
+        group: String,
+        #[clap(value_enum)]
+        state: Turn,
+    },
+    /// Shows aggregated state of devices of the group
+    Summary { group: String },
 }
 
-impl<'a> DeviceInfoProvider for BorrowingDeviceInfoProvider<'a> {
-    fn report(&self, room_name: &str, device_name: &str) -> Option<String> {
-        match (room_name, device_name) {
-            ("Dinning room", "therm1") => Some(format!("{}", self.thermometer1)),
-            ("Dinning room", "switch1") => Some(format!("{}", self.switch1)),
-            ("Bathroom", "therm2") => Some(format!("{}", self.thermometer2)),
-            ("Bathroom", "switch1") => Some(format!("{}", self.switch2)),
-            (_, _) => None,
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Turn {
+    On,
+    Off,
+}
+
+impl Turn {
+    fn enabled(self) -> bool {
+        matches!(self, Turn::On)
+    }
+}
+
+/// Describes files besides the house file referring to devices by room and name
+struct References<'a> {
+    rules: Option<&'a Path>,
+    schedule: Option<&'a Path>,
+}
+
+impl References<'_> {
+    /// Makes rules and jobs refer to the device by its new room and name
+    fn rename_device(
+        &self,
+        room: &str,
+        device: &str,
+        new_room: &str,
+        new_device: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = self.rules {
+            RuleSet::from_file(path)?.rename_device(room, device, new_room, new_device)?;
         }
+
+        if let Some(path) = self.schedule {
+            ScheduleStore::open(path)?.rename_device(room, device, new_room, new_device)?;
+        }
+
+        Ok(())
     }
 }
 
-fn main() -> errors::Result<()> {
-    let dinning_power_switch = PowerSwitch::new("Dinning room");
-    let bathroom_power_switch = PowerSwitch::new("Bathroom");
-    let dinning_thermometer = Thermometer::new("127.0.0.1:6876", "127.0.0.1:6877").unwrap();
-    let bathroom_thermometer = Thermometer::new("127.0.0.1:7888", "127.0.0.1:7889").unwrap();
+/// Describes provider reporting current states of bound devices
+struct LiveInfoProvider<'a> {
+    house: &'a SmartHouse,
+    registry: &'a DeviceRegistry,
+}
 
-    let smart_house = SmartHouse::generate();
+impl DeviceInfoProvider for LiveInfoProvider<'_> {
+    fn report(&self, room_name: &str, device_name: &str) -> Option<String> {
+        let state = match self.house.binding(room_name, device_name) {
+            Some(binding) => match self.registry.state(binding) {
+                Ok(state) => state.to_string(),
+                Err(err) => err.to_string(),
+            },
+            None => "not bound".to_owned(),
+        };
 
-    let info_provider1 = OwningDeviceInfoProvider {
-        switch1: dinning_power_switch.clone(),
-        switch2: bathroom_power_switch.clone(),
-        thermometer1: dinning_thermometer.clone(),
-        thermometer2: bathroom_thermometer.clone(),
-    };
+        Some(format!("{room_name} / {device_name}: {state}"))
+    }
+}
 
-    let report1 = smart_house.create_report(&info_provider1)?;
+fn main() {
+    if let Err(err) = run(Args::parse()) {
+        eprintln!("Error: {err}");
+        process::exit(1);
+    }
+}
 
-    let info_provider2 = BorrowingDeviceInfoProvider {
-        switch1: &dinning_power_switch,
-        switch2: &bathroom_power_switch,
-        thermometer1: &dinning_thermometer,
-        thermometer2: &bathroom_thermometer,
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let registry = DeviceRegistry::new(Duration::from_millis(args.timeout));
+    let references = References {
+        rules: args.rules.as_deref(),
+        schedule: args.schedule.as_deref(),
     };
 
-    let report2 = smart_house.create_report(&info_provider2)?;
+    match args.command {
+        Command::Init { name, force } => {
+            if args.file.exists() && !force {
+                return Err(format!("{} already exists", args.file.display()).into());
+            }
+
+            HouseFile::from(&SmartHouse::new(&name)).save(&args.file)?;
+            println!("Created {name} in {}", args.file.display());
+        }
+        Command::Room(command) => room(&args.file, &references, command)?,
+        Command::Device(command) => device(&args.file, &references, command)?,
+        Command::Group(command) => group(&args.file, &registry, command)?,
+        Command::Report => {
+            let house = load(&args.file)?;
+            let provider = LiveInfoProvider {
+                house: &house,
+                registry: &registry,
+            };
+
+            println!("{}", house.get_name());
+            print!("{}", house.create_report(&provider)?);
+        }
+        Command::Switch {
+            room,
+            device,
+            state,
+        } => {
+            let house = load(&args.file)?;
+            let state = registry.turn(bound(&house, &room, &device)?, state.enabled())?;
+
+            println!("{room} / {device}: {state}");
+        }
+        Command::Tail {
+            room,
+            device,
+            interval,
+        } => {
+            let house = load(&args.file)?;
+            let binding = bound(&house, &room, &device)?;
+
+            loop {
+                let time = Local::now().format("%Y-%m-%d %H:%M:%S");
+                match registry.state(binding) {
+                    Ok(state) => println!("{time} {state}"),
+                    Err(err) => println!("{time} {err}"),
+                }
+
+                thread::sleep(Duration::from_millis(interval));
+            }
+        }
+        Command::Dashboard { interval } => {
+            let house = load(&args.file)?;
+            dashboard::run(&house, &registry, Duration::from_millis(interval))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<SmartHouse, Box<dyn Error>> {
+    Ok(HouseFile::load(path)?.into())
+}
+
+fn save(house: &SmartHouse, path: &Path) -> Result<(), Box<dyn Error>> {
+    Ok(HouseFile::from(house).save(path)?)
+}
+
+/// Returns binding of the device or error if it isn't found or bound
+fn bound<'a>(
+    house: &'a SmartHouse,
+    room_name: &str,
+    device_name: &str,
+) -> Result<&'a DeviceBinding, Box<dyn Error>> {
+    if !house
+        .devices(room_name)
+        .is_some_and(|devices| devices.contains(device_name))
+    {
+        return Err(DeviceNotFoundError {
+            device_name: device_name.to_owned(),
+            room_name: room_name.to_owned(),
+        }
+        .into());
+    }
+
+    house.binding(room_name, device_name).ok_or_else(|| {
+        DeviceNotBoundError {
+            device_name: device_name.to_owned(),
+            room_name: room_name.to_owned(),
+        }
+        .into()
+    })
+}
+
+fn room(path: &Path, references: &References, command: RoomCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        RoomCommand::List => {
+            let house = load(path)?;
+
+            for room in house.get_rooms().values() {
+                println!("{}", room.get_name());
+
+                for device in room.get_devices() {
+                    let id = house.device_id(room.get_name(), device).unwrap_or_default();
+                    println!("  {device:<16} {id}");
+                }
+            }
 
-    println!("Report #1: \n{}", report1);
-    println!("Report #2: \n{}", report2);
+            return Ok(());
+        }
+        RoomCommand::Add { room } => {
+            let mut house = load(path)?;
+            if !house.add_room(Room::new(&room)) {
+                return Err(format!(r#"Room "{room}" already exists"#).into());
+            }
+            save(&house, path)?;
+        }
+        RoomCommand::Remove { room } => {
+            let mut house = load(path)?;
+            if !house.remove_room(&room) {
+                return Err(RoomNotFoundError { room_name: room }.into());
+            }
+            save(&house, path)?;
+        }
+        RoomCommand::Rename { room, new_name } => {
+            let mut house = load(path)?;
+            let devices = house.devices(&room).cloned().unwrap_or_default();
+            house.rename_room(&room, &new_name)?;
+            save(&house, path)?;
+
+            for device in devices {
+                references.rename_device(&room, &device, &new_name, &device)?;
+            }
+        }
+    }
+
+    println!("Saved {}", path.display());
 
     Ok(())
 }
+
+fn device(
+    path: &Path,
+    references: &References,
+    command: DeviceCommand,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        DeviceCommand::Add {
+            room,
+            device,
+            binding,
+        } => {
+            let mut house = load(path)?;
+            if !house.add_device(&room, &device) {
+                return Err(format!(r#"Device "{device}" already exists in room "{room}""#).into());
+            }
+            if let Some(binding) = binding.binding() {
+                _ = house.bind_device(&room, &device, binding);
+            }
+            save(&house, path)?;
+        }
+        DeviceCommand::Bind {
+            room,
+            device,
+            binding,
+        } => {
+            let binding = binding
+                .binding()
+                .ok_or("Address of switch or thermometer is required")?;
+
+            let mut house = load(path)?;
+            if !house.bind_device(&room, &device, binding) {
+                return Err(DeviceNotFoundError {
+                    device_name: device,
+                    room_name: room,
+                }
+                .into());
+            }
+            save(&house, path)?;
+        }
+        DeviceCommand::Find { id } => {
+            let house = load(path)?;
+            let (room, device) = house
+                .find_device(id)
+                .ok_or_else(|| DeviceIdNotFoundError { id: id.to_string() })?;
+
+            println!("{room} / {device}");
+
+            return Ok(());
+        }
+        DeviceCommand::Remove { room, device } => {
+            let mut house = load(path)?;
+            if !house.remove_device(&room, &device) {
+                return Err(DeviceNotFoundError {
+                    device_name: device,
+                    room_name: room,
+                }
+                .into());
+            }
+            save(&house, path)?;
+        }
+        DeviceCommand::Rename {
+            room,
+            device,
+            new_name,
+        } => {
+            let mut house = load(path)?;
+            house.rename_device(&room, &device, &new_name)?;
+            save(&house, path)?;
+            references.rename_device(&room, &device, &room, &new_name)?;
+        }
+        DeviceCommand::Move {
+            room,
+            device,
+            new_room,
+        } => {
+            let mut house = load(path)?;
+            house.move_device(&room, &device, &new_room)?;
+            save(&house, path)?;
+            references.rename_device(&room, &device, &new_room, &device)?;
+        }
+    }
+
+    println!("Saved {}", path.display());
+
+    Ok(())
+}
+
+fn group(
+    path: &Path,
+    registry: &DeviceRegistry,
+    command: GroupCommand,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        GroupCommand::List => {
+            let house = load(path)?;
+
+            for (group_name, members) in house.get_groups() {
+                let members: Vec<_> = members
+                    .iter()
+                    .map(|(room, device)| format!("{room} / {device}"))
+                    .collect();
+                println!("{group_name}: {}", members.join(", "));
+            }
+        }
+        GroupCommand::Add {
+            group,
+            room,
+            device,
+        } => {
+            let mut house = load(path)?;
+            if !house.add_to_group(&group, &room, &device) {
+                return Err(DeviceNotFoundError {
+                    device_name: device,
+                    room_name: room,
+                }
+                .into());
+            }
+            save(&house, path)?;
+            println!("Saved {}", path.display());
+        }
+        GroupCommand::Remove {
+            group,
+            room,
+            device,
+        } => {
+            let mut house = load(path)?;
+            if !house.remove_from_group(&group, &room, &device) {
+                return Err(format!(
+                    r#"Device "{device}" in room "{room}" is not in group "{group}""#
+                )
+                .into());
+            }
+            save(&house, path)?;
+            println!("Saved {}", path.display());
+        }
+        GroupCommand::Delete { group } => {
+            let mut house = load(path)?;
+            if !house.remove_group(&group) {
+                return Err(GroupNotFoundError { group_name: group }.into());
+            }
+            save(&house, path)?;
+            println!("Saved {}", path.display());
+        }
+        GroupCommand::Switch { group, state } => {
+            let house = Mutex::new(load(path)?);
+            let report = groups::turn(
+                &group,
+                &house,
+                registry,
+                &HouseEvents::new(),
+                state.enabled(),
+            )?;
+
+            print_outcomes(&report);
+        }
+        GroupCommand::Summary { group } => {
+            let house = Mutex::new(load(path)?);
+            let summary = groups::summary(&group, &house, registry)?;
+
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_outcomes(report: &SceneReport) {
+    for outcome in &report.devices {
+        let state = if outcome.enabled { "on" } else { "off" };
+
+        match &outcome.error {
+            Some(err) => println!("{} / {}: {err}", outcome.room, outcome.device),
+            None => println!("{} / {}: {state}", outcome.room, outcome.device),
+        }
+    }
+}
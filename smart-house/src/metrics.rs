@@ -0,0 +1,167 @@
+//! Module describes metrics of devices of the smart house
+//! in Prometheus text exposition format
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use power_switch::metrics::escape_label;
+
+use crate::{
+    events::{DeviceKey, ObservedState},
+    registry::DeviceState,
+};
+
+pub use power_switch::metrics::CONTENT_TYPE;
+
+/// Describes family of samples with the same metric name
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    samples: Vec<(String, f64)>,
+}
+
+impl Family {
+    fn new(name: &'static str, kind: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            help,
+            samples: Vec::new(),
+        }
+    }
+}
+
+/// Renders the last observed `states` of devices of the house labelled by
+/// house, room and device. `now` is current time in milliseconds since Unix epoch.
+pub fn render(house_name: &str, states: &BTreeMap<DeviceKey, ObservedState>, now: u64) -> String {
+    let mut up = Family::new(
+        "smart_house_device_up",
+        "gauge",
+        "1 if the last query of the device succeeded",
+    );
+    let mut age = Family::new(
+        "smart_house_device_age_seconds",
+        "gauge",
+        "Seconds since the last state was received from the device",
+    );
+    let mut temperature = Family::new(
+        "smart_house_temperature_celsius",
+        "gauge",
+        "The last temperature measured by thermometer",
+    );
+    let mut enabled = Family::new(
+        "smart_house_switch_enabled",
+        "gauge",
+        "1 if power switch is on",
+    );
+    let mut power = Family::new(
+        "smart_house_switch_power_watts",
+        "gauge",
+        "Power consumption of power switch",
+    );
+    let mut energy = Family::new(
+        "smart_house_switch_energy_watt_hours_total",
+        "counter",
+        "Energy consumed by power switch",
+    );
+
+    for ((room, device), observed) in states {
+        let labels = format!(
+            r#"{{house="{}",room="{}",device="{}"}}"#,
+            escape_label(house_name),
+            escape_label(room),
+            escape_label(device)
+        );
+
+        up.samples
+            .push((labels.clone(), if observed.stale { 0.0 } else { 1.0 }));
+
+        if let Some(updated) = observed.updated {
+            let seconds = now.saturating_sub(updated) as f64 / 1000.0;
+            age.samples.push((labels.clone(), seconds));
+        }
+
+        match &observed.state {
+            Some(DeviceState::Thermometer {
+                temperature: value, ..
+            }) => temperature.samples.push((labels, *value)),
+            Some(DeviceState::PowerSwitch {
+                enabled: is_enabled,
+                power: value,
+                energy: consumed,
+            }) => {
+                enabled
+                    .samples
+                    .push((labels.clone(), f64::from(u8::from(*is_enabled))));
+                power.samples.push((labels.clone(), *value));
+                if let Some(consumed) = consumed {
+                    energy.samples.push((labels, *consumed));
+                }
+            }
+            None => {}
+        }
+    }
+
+    let mut output = String::new();
+
+    for family in [up, age, temperature, enabled, power, energy] {
+        if family.samples.is_empty() {
+            continue;
+        }
+
+        _ = writeln!(output, "# HELP {} {}", family.name, family.help);
+        _ = writeln!(output, "# TYPE {} {}", family.name, family.kind);
+        for (labels, value) in family.samples {
+            _ = writeln!(output, "{}{labels} {value}", family.name);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let states = BTreeMap::from([
+            (
+                ("Hall".to_owned(), "therm1".to_owned()),
+                ObservedState {
+                    state: Some(DeviceState::Thermometer {
+                        temperature: 21.5,
+                        measurements: BTreeMap::new(),
+                    }),
+                    updated: Some(10_000),
+                    stale: true,
+                },
+            ),
+            (
+                ("Hall".to_owned(), "switch1".to_owned()),
+                ObservedState {
+                    state: Some(DeviceState::PowerSwitch {
+                        enabled: true,
+                        power: 1500.0,
+                        energy: Some(12.5),
+                    }),
+                    updated: Some(12_000),
+                    stale: false,
+                },
+            ),
+        ]);
+
+        let metrics = render("Our house", &states, 12_500);
+        let therm1 = r#"{house="Our house",room="Hall",device="therm1"}"#;
+        let switch1 = r#"{house="Our house",room="Hall",device="switch1"}"#;
+
+        assert!(metrics.contains("# TYPE smart_house_temperature_celsius gauge\n"));
+        assert!(metrics.contains(&format!("smart_house_temperature_celsius{therm1} 21.5\n")));
+        assert!(metrics.contains(&format!("smart_house_device_up{therm1} 0\n")));
+        assert!(metrics.contains(&format!("smart_house_device_age_seconds{therm1} 2.5\n")));
+        assert!(metrics.contains(&format!("smart_house_switch_enabled{switch1} 1\n")));
+        assert!(metrics.contains(&format!(
+            "smart_house_switch_energy_watt_hours_total{switch1} 12.5\n"
+        )));
+    }
+}
@@ -0,0 +1,65 @@
+//! Module describes background polling of device states
+
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{events::HouseEvents, registry::DeviceRegistry, smart_house::SmartHouse};
+
+/// Describes thread which periodically queries all bound devices
+/// of the house and passes results to [`HouseEvents`].
+/// Polling stops when monitor is dropped.
+pub struct StateMonitor {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StateMonitor {
+    /// Starts polling devices of the `house` each `interval`
+    pub fn start(
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        events: Arc<HouseEvents>,
+        interval: Duration,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn(move || loop {
+            let bindings = house.lock().unwrap().get_bindings().clone();
+
+            events.retain(|room, device| {
+                bindings.contains_key(&(room.to_owned(), device.to_owned()))
+            });
+
+            // Devices are queried without holding the lock of the house
+            for ((room, device), binding) in &bindings {
+                events.observe(room, device, &registry.state(binding));
+            }
+
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => return,
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for StateMonitor {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
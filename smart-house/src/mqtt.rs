@@ -0,0 +1,42 @@
+//! Module describes minimal MQTT 3.1.1 client with QoS 0
+//! and bridge of the smart house devices to MQTT broker
+
+pub use self::bridge::{BridgeSettings, MqttBridge};
+pub use self::client::{Message, MqttClient};
+pub use self::packet::Packet;
+
+mod bridge;
+mod client;
+mod packet;
+
+/// Returns `true` if `topic` matches `filter` with wildcards `+` and `#`
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter = filter.split('/');
+    let mut topic = topic.split('/');
+
+    loop {
+        match (filter.next(), topic.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => continue,
+            (Some(f), Some(t)) if f == t => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("a/b", "a/b"));
+        assert!(topic_matches("a/+/c", "a/b/c"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(topic_matches("#", "a"));
+        assert!(!topic_matches("a/+", "a/b/c"));
+        assert!(!topic_matches("a/b/c", "a/b"));
+        assert!(!topic_matches("a/b", "a/c"));
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    sync::{
+        mpsc::{self, RecvTimeoutError, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use super::client::{Message, MqttClient};
+use crate::{
+    errors::Error::UnsupportedCommandError,
+    events::{EventFilter, EventKind, HouseEvent, HouseEvents},
+    registry::DeviceRegistry,
+    smart_house::{DeviceBinding, SmartHouse},
+};
+
+/// Describes how often bridge checks whether it has to stop
+const TICK: Duration = Duration::from_millis(200);
+
+/// Describes delay before the first attempt to reconnect to broker,
+/// the delay is doubled after each failed attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Describes maximal delay between attempts to reconnect to broker
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Describes topics used by [`MqttBridge`]
+#[derive(Debug, Clone)]
+pub struct BridgeSettings {
+    /// Prefix of state and command topics:
+    /// `<prefix>/<room>/<device>/<state|power|temperature|availability|set>`
+    pub prefix: String,
+    /// Prefix of Home Assistant discovery topics
+    pub discovery_prefix: String,
+}
+
+impl Default for BridgeSettings {
+    fn default() -> Self {
+        Self {
+            prefix: "smart-house".to_owned(),
+            discovery_prefix: "homeassistant".to_owned(),
+        }
+    }
+}
+
+/// Describes key of device by room name and device name
+type DeviceKey = (String, String);
+
+/// Describes bridge between devices of the smart house and MQTT broker.
+///
+/// Bridge publishes retained states of devices from [`HouseEvents`],
+/// so devices have to be polled, e.g. by [`crate::monitor::StateMonitor`]
+/// started after the bridge. Messages `ON` and `OFF` to command topics
+/// turn power switches on and off. Retained messages of renamed devices
+/// are cleared under their previous names. Bridge reconnects to broker
+/// with growing delays when connection is lost.
+pub struct MqttBridge {
+    client: Arc<MqttClient>,
+    stop: Option<mpsc::Sender<()>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl MqttBridge {
+    /// Starts bridge for `client` connected to broker and its `messages`
+    pub fn start(
+        client: MqttClient,
+        messages: mpsc::Receiver<Message>,
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        events: Arc<HouseEvents>,
+        settings: BridgeSettings,
+    ) -> io::Result<Self> {
+        let client = Arc::new(client);
+        let (stop, stopped) = mpsc::channel();
+
+        client.subscribe(&format!("{}/+/+/set", settings.prefix))?;
+
+        let publisher = Publisher {
+            client: client.clone(),
+            house: house.clone(),
+            settings: settings.clone(),
+            announced: BTreeSet::new(),
+            online: BTreeSet::new(),
+            retained: BTreeMap::new(),
+        };
+        let received = events.subscribe(EventFilter::default());
+        let publisher = thread::spawn(move || publisher.run(received, stopped));
+
+        let commands = thread::spawn(move || {
+            for message in messages {
+                handle_command(&message, &house, &registry, &events, &settings);
+            }
+        });
+
+        Ok(Self {
+            client,
+            stop: Some(stop),
+            handles: vec![publisher, commands],
+        })
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        _ = self.client.disconnect();
+
+        for handle in self.handles.drain(..) {
+            _ = handle.join();
+        }
+    }
+}
+
+/// Describes publisher of device states
+struct Publisher {
+    client: Arc<MqttClient>,
+    house: Arc<Mutex<SmartHouse>>,
+    settings: BridgeSettings,
+    // Devices with published discovery payloads
+    announced: BTreeSet<DeviceKey>,
+    // Devices with published `online` availability
+    online: BTreeSet<DeviceKey>,
+    // Topics of retained messages published for each device
+    retained: BTreeMap<DeviceKey, BTreeSet<String>>,
+}
+
+impl Publisher {
+    fn run(mut self, received: mpsc::Receiver<HouseEvent>, stopped: mpsc::Receiver<()>) {
+        let ping_interval = self.client.keep_alive() / 2;
+        let mut last_ping = Instant::now();
+        // Event not published because of lost connection
+        let mut pending = None;
+
+        while let Err(TryRecvError::Empty) = stopped.try_recv() {
+            let result = match pending
+                .take()
+                .map_or_else(|| received.recv_timeout(TICK), Ok)
+            {
+                Ok(event) => self.publish(&event).inspect_err(|_| pending = Some(event)),
+                Err(RecvTimeoutError::Timeout) if last_ping.elapsed() >= ping_interval => {
+                    last_ping = Instant::now();
+                    self.client.ping()
+                }
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            if let Err(err) = result {
+                println!("Failed to publish to MQTT broker: {err}");
+
+                if !self.reconnect(&stopped) {
+                    return;
+                }
+                last_ping = Instant::now();
+            }
+        }
+    }
+
+    /// Reconnects to broker with growing delays until succeeded.
+    /// Returns `false` if bridge is stopped meanwhile.
+    fn reconnect(&mut self, stopped: &mpsc::Receiver<()>) -> bool {
+        let mut delay = RECONNECT_DELAY;
+
+        loop {
+            if !matches!(stopped.recv_timeout(delay), Err(RecvTimeoutError::Timeout)) {
+                return false;
+            }
+
+            match self.client.reconnect() {
+                Ok(()) => {
+                    println!("Reconnected to MQTT broker");
+
+                    // Broker may have lost retained messages while bridge was away
+                    self.announced.clear();
+                    self.online.clear();
+                    return true;
+                }
+                Err(err) => {
+                    println!("Failed to reconnect to MQTT broker: {err}");
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+
+    fn publish(&mut self, event: &HouseEvent) -> io::Result<()> {
+        let key = (event.room.clone(), event.device.clone());
+
+        // Device is announced again under its new name by its next event
+        if let EventKind::DeviceRenamed { .. } = event.kind {
+            return self.forget(&key);
+        }
+
+        if !self.announced.contains(&key) {
+            let binding = self
+                .house
+                .lock()
+                .unwrap()
+                .binding(&event.room, &event.device)
+                .cloned();
+
+            if let Some(binding) = binding {
+                for (topic, config) in discovery_configs(&self.settings, &key, &binding) {
+                    self.publish_retained(&key, topic, config.to_string().as_bytes())?;
+                }
+                self.announced.insert(key.clone());
+            }
+        }
+
+        let settings = self.settings.clone();
+        let topic = |name: &str| device_topic(&settings, &key, name);
+
+        if let EventKind::DeviceStale { .. } = event.kind {
+            self.online.remove(&key);
+            return self.publish_retained(&key, topic("availability"), b"offline");
+        }
+
+        if !self.online.contains(&key) {
+            self.publish_retained(&key, topic("availability"), b"online")?;
+            self.online.insert(key.clone());
+        }
+
+        match &event.kind {
+            EventKind::SwitchToggled { enabled } => {
+                let payload: &[u8] = if *enabled { b"ON" } else { b"OFF" };
+                self.publish_retained(&key, topic("state"), payload)
+            }
+            EventKind::PowerChanged { power } => {
+                self.publish_retained(&key, topic("power"), power.to_string().as_bytes())
+            }
+            EventKind::TemperatureReading {
+                temperature,
+                measurements,
+            } => {
+                let temperature = temperature.to_string();
+                self.publish_retained(&key, topic("temperature"), temperature.as_bytes())?;
+
+                for (name, value) in measurements {
+                    if name != "temperature" {
+                        self.publish_retained(&key, topic(name), value.to_string().as_bytes())?;
+                    }
+                }
+
+                Ok(())
+            }
+            EventKind::DeviceStale { .. }
+            | EventKind::DeviceRenamed { .. }
+            | EventKind::LoadShed { .. }
+            | EventKind::LoadRestored { .. } => Ok(()),
+        }
+    }
+
+    /// Publishes retained message of device and remembers its topic
+    fn publish_retained(
+        &mut self,
+        key: &DeviceKey,
+        topic: String,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        self.client.publish(&topic, payload, true)?;
+        self.retained.entry(key.clone()).or_default().insert(topic);
+        Ok(())
+    }
+
+    /// Clears retained messages of device under its previous name,
+    /// so Home Assistant removes its entities instead of keeping them unavailable
+    fn forget(&mut self, key: &DeviceKey) -> io::Result<()> {
+        for topic in self.retained.get(key).into_iter().flatten() {
+            self.client.publish(topic, b"", true)?;
+        }
+
+        self.retained.remove(key);
+        self.announced.remove(key);
+        self.online.remove(key);
+        Ok(())
+    }
+}
+
+/// Turns power switch on or off by message to its command topic
+fn handle_command(
+    message: &Message,
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    settings: &BridgeSettings,
+) {
+    let Some(path) = message
+        .topic
+        .strip_prefix(&settings.prefix)
+        .and_then(|path| path.strip_prefix('/'))
+    else {
+        return;
+    };
+
+    let [room, device, "set"] = path.split('/').collect::<Vec<_>>()[..] else {
+        return;
+    };
+
+    let enabled = match String::from_utf8_lossy(&message.payload).trim() {
+        payload if payload.eq_ignore_ascii_case("ON") => true,
+        payload if payload.eq_ignore_ascii_case("OFF") => false,
+        payload => {
+            println!("Unknown command {payload:?} in {}", message.topic);
+            return;
+        }
+    };
+
+    // Topics contain slugs of names
+    let found = house
+        .lock()
+        .unwrap()
+        .get_bindings()
+        .iter()
+        .find(|((r, d), _)| slug(r) == room && slug(d) == device)
+        .map(|(key, binding)| (key.clone(), binding.clone()));
+
+    let Some(((room, device), binding)) = found else {
+        println!("Not found bound device for {}", message.topic);
+        return;
+    };
+
+    let result = registry.turn(&binding, enabled);
+
+    match &result {
+        Err(UnsupportedCommandError) => println!("{device} in {room} can't be turned on or off"),
+        _ => events.observe(&room, &device, &result),
+    }
+}
+
+/// Returns topic of device
+fn device_topic(settings: &BridgeSettings, (room, device): &DeviceKey, name: &str) -> String {
+    format!("{}/{}/{}/{name}", settings.prefix, slug(room), slug(device))
+}
+
+/// Returns Home Assistant discovery payloads of device by their topics
+fn discovery_configs(
+    settings: &BridgeSettings,
+    key: &DeviceKey,
+    binding: &DeviceBinding,
+) -> Vec<(String, Value)> {
+    let (room, device) = key;
+    let node = format!("{}_{}", slug(room), slug(device));
+    let topic = |component: &str, object: &str| {
+        format!("{}/{component}/{object}/config", settings.discovery_prefix)
+    };
+    let base = json!({
+        "availability_topic": device_topic(settings, key, "availability"),
+        "device": {
+            "identifiers": [format!("smart_house_{node}")],
+            "name": device,
+            "suggested_area": room,
+        },
+    });
+    let config = |fields: Value| {
+        let mut config = base.clone();
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        config
+    };
+
+    match binding {
+        DeviceBinding::PowerSwitch { .. } => vec![
+            (
+                topic("switch", &node),
+                config(json!({
+                    "name": device,
+                    "unique_id": format!("smart_house_{node}"),
+                    "state_topic": device_topic(settings, key, "state"),
+                    "command_topic": device_topic(settings, key, "set"),
+                    "payload_on": "ON",
+                    "payload_off": "OFF",
+                })),
+            ),
+            (
+                topic("sensor", &format!("{node}_power")),
+                config(json!({
+                    "name": format!("{device} power"),
+                    "unique_id": format!("smart_house_{node}_power"),
+                    "state_topic": device_topic(settings, key, "power"),
+                    "device_class": "power",
+                    "unit_of_measurement": "W",
+                })),
+            ),
+        ],
+        DeviceBinding::Thermometer { .. } => vec![(
+            topic("sensor", &format!("{node}_temperature")),
+            config(json!({
+                "name": format!("{device} temperature"),
+                "unique_id": format!("smart_house_{node}_temperature"),
+                "state_topic": device_topic(settings, key, "temperature"),
+                "device_class": "temperature",
+                "unit_of_measurement": "°C",
+            })),
+        )],
+    }
+}
+
+/// Returns name usable as topic level and Home Assistant object id
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_discovery_config() {
+        let settings = BridgeSettings::default();
+        let key = ("Dinning room".to_owned(), "switch1".to_owned());
+        let binding = DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:53453".parse().unwrap(),
+        };
+
+        let configs = discovery_configs(&settings, &key, &binding);
+
+        assert_eq!(
+            configs[0].0,
+            "homeassistant/switch/dinning_room_switch1/config"
+        );
+        assert_eq!(
+            configs[0].1["command_topic"],
+            "smart-house/dinning_room/switch1/set"
+        );
+        assert_eq!(configs[0].1["device"]["suggested_area"], "Dinning room");
+        assert_eq!(
+            configs[1].0,
+            "homeassistant/sensor/dinning_room_switch1_power/config"
+        );
+        assert_eq!(configs[1].1["unit_of_measurement"], "W");
+    }
+}
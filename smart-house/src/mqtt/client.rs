@@ -0,0 +1,181 @@
+use std::{
+    io::{self, BufReader},
+    mem,
+    net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::packet::Packet;
+
+/// Describes message received from subscribed topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// Describes connection to MQTT broker.
+/// Incoming messages are received by separate thread
+/// and passed to channel returned by [`MqttClient::connect`],
+/// the channel is kept when client reconnects.
+pub struct MqttClient {
+    stream: Mutex<TcpStream>,
+    packet_id: AtomicU16,
+    keep_alive: Duration,
+    addresses: Vec<SocketAddr>,
+    client_id: String,
+    // Filters subscribed again on reconnect
+    subscriptions: Mutex<Vec<String>>,
+    // Sender of incoming messages, taken on disconnect
+    messages: Mutex<Option<mpsc::Sender<Message>>>,
+}
+
+impl MqttClient {
+    /// Connects to broker at `address` and returns client together with
+    /// receiver of messages from subscribed topics.
+    /// Client has to send packets at least once per `keep_alive`,
+    /// see [`MqttClient::ping`].
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        client_id: &str,
+        keep_alive: Duration,
+    ) -> io::Result<(Self, mpsc::Receiver<Message>)> {
+        let addresses: Vec<_> = address.to_socket_addrs()?.collect();
+        let (tx, rx) = mpsc::channel();
+        let stream = Self::open(&addresses, client_id, keep_alive, tx.clone())?;
+
+        let client = Self {
+            stream: Mutex::new(stream),
+            packet_id: AtomicU16::new(1),
+            keep_alive,
+            addresses,
+            client_id: client_id.to_owned(),
+            subscriptions: Mutex::new(Vec::new()),
+            messages: Mutex::new(Some(tx)),
+        };
+
+        Ok((client, rx))
+    }
+
+    /// Connects to broker again replacing the broken connection
+    /// and renews subscriptions
+    pub fn reconnect(&self) -> io::Result<()> {
+        let messages =
+            self.messages.lock().unwrap().clone().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotConnected, "Client is disconnected")
+            })?;
+        let stream = Self::open(&self.addresses, &self.client_id, self.keep_alive, messages)?;
+
+        let broken = mem::replace(&mut *self.stream.lock().unwrap(), stream);
+        _ = broken.shutdown(Shutdown::Both);
+
+        let filters = self.subscriptions.lock().unwrap().clone();
+        for filter in filters {
+            self.send_subscribe(&filter)?;
+        }
+
+        Ok(())
+    }
+
+    /// Connects to broker and starts thread passing incoming messages to `messages`
+    fn open(
+        addresses: &[SocketAddr],
+        client_id: &str,
+        keep_alive: Duration,
+        messages: mpsc::Sender<Message>,
+    ) -> io::Result<TcpStream> {
+        let mut stream = TcpStream::connect(addresses)?;
+
+        Packet::Connect {
+            client_id: client_id.to_owned(),
+            keep_alive: keep_alive.as_secs().try_into().unwrap_or(u16::MAX),
+        }
+        .write(&mut stream)?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        match Packet::read(&mut reader)? {
+            Packet::ConnAck { return_code: 0 } => {}
+            Packet::ConnAck { return_code } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("Broker refused connection with code {return_code}"),
+                ))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Expected CONNACK packet",
+                ))
+            }
+        }
+
+        thread::spawn(move || {
+            while let Ok(packet) = Packet::read(&mut reader) {
+                if let Packet::Publish { topic, payload, .. } = packet {
+                    if messages.send(Message { topic, payload }).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+
+    /// Returns keep alive interval negotiated with broker
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    /// Publishes `payload` to `topic`. Broker keeps the last
+    /// `retain`-ed message of topic for new subscribers.
+    pub fn publish(&self, topic: &str, payload: &[u8], retain: bool) -> io::Result<()> {
+        self.send(&Packet::Publish {
+            topic: topic.to_owned(),
+            payload: payload.to_vec(),
+            retain,
+        })
+    }
+
+    /// Subscribes to topics matching `filter`
+    pub fn subscribe(&self, filter: &str) -> io::Result<()> {
+        self.send_subscribe(filter)?;
+        self.subscriptions.lock().unwrap().push(filter.to_owned());
+
+        Ok(())
+    }
+
+    /// Tells broker that client is alive
+    pub fn ping(&self) -> io::Result<()> {
+        self.send(&Packet::PingReq)
+    }
+
+    /// Closes connection to broker, channel of incoming messages
+    /// is closed as well
+    pub fn disconnect(&self) -> io::Result<()> {
+        drop(self.messages.lock().unwrap().take());
+        let sent = self.send(&Packet::Disconnect);
+        self.stream.lock().unwrap().shutdown(Shutdown::Both)?;
+
+        sent
+    }
+
+    fn send_subscribe(&self, filter: &str) -> io::Result<()> {
+        let packet_id = self.packet_id.fetch_add(1, Ordering::Relaxed);
+
+        self.send(&Packet::Subscribe {
+            packet_id,
+            filters: vec![filter.to_owned()],
+        })
+    }
+
+    fn send(&self, packet: &Packet) -> io::Result<()> {
+        packet.write(&mut *self.stream.lock().unwrap())
+    }
+}
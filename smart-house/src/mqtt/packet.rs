@@ -0,0 +1,262 @@
+use std::io::{self, Read, Write};
+
+/// Describes control packet of MQTT 3.1.1.
+/// Only packets required for QoS 0 are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    Connect {
+        client_id: String,
+        keep_alive: u16,
+    },
+    ConnAck {
+        return_code: u8,
+    },
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+        retain: bool,
+    },
+    Subscribe {
+        packet_id: u16,
+        filters: Vec<String>,
+    },
+    SubAck {
+        packet_id: u16,
+        return_codes: Vec<u8>,
+    },
+    PingReq,
+    PingResp,
+    Disconnect,
+}
+
+impl Packet {
+    /// Writes packet to `writer`
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut body = Vec::new();
+
+        let header = match self {
+            Packet::Connect {
+                client_id,
+                keep_alive,
+            } => {
+                push_string(&mut body, "MQTT")?;
+                body.push(4); // protocol level of 3.1.1
+                body.push(0x02); // clean session
+                body.extend_from_slice(&keep_alive.to_be_bytes());
+                push_string(&mut body, client_id)?;
+                0x10
+            }
+            Packet::ConnAck { return_code } => {
+                body.extend_from_slice(&[0, *return_code]);
+                0x20
+            }
+            Packet::Publish {
+                topic,
+                payload,
+                retain,
+            } => {
+                push_string(&mut body, topic)?;
+                body.extend_from_slice(payload);
+                0x30 | u8::from(*retain)
+            }
+            Packet::Subscribe { packet_id, filters } => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                for filter in filters {
+                    push_string(&mut body, filter)?;
+                    body.push(0); // QoS 0
+                }
+                0x82
+            }
+            Packet::SubAck {
+                packet_id,
+                return_codes,
+            } => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                body.extend_from_slice(return_codes);
+                0x90
+            }
+            Packet::PingReq => 0xC0,
+            Packet::PingResp => 0xD0,
+            Packet::Disconnect => 0xE0,
+        };
+
+        let mut bytes = vec![header];
+        push_remaining_length(&mut bytes, body.len())?;
+        bytes.extend_from_slice(&body);
+
+        writer.write_all(&bytes)
+    }
+
+    /// Reads next packet from `reader`
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut header = [0u8];
+        reader.read_exact(&mut header)?;
+
+        let len = read_remaining_length(reader)?;
+        let mut body = vec![0u8; len];
+        reader.read_exact(&mut body)?;
+
+        let mut body = body.as_slice();
+
+        let packet = match header[0] >> 4 {
+            1 => {
+                let protocol = take_string(&mut body)?;
+                if protocol != "MQTT" {
+                    return Err(invalid("Unsupported protocol"));
+                }
+                let [_level, _flags, k1, k2] = take::<4>(&mut body)?;
+                Packet::Connect {
+                    keep_alive: u16::from_be_bytes([k1, k2]),
+                    client_id: take_string(&mut body)?,
+                }
+            }
+            2 => {
+                let [_, return_code] = take::<2>(&mut body)?;
+                Packet::ConnAck { return_code }
+            }
+            3 => {
+                let topic = take_string(&mut body)?;
+                // Packet identifier is present for QoS 1 and 2
+                if header[0] & 0x06 != 0 {
+                    take::<2>(&mut body)?;
+                }
+                Packet::Publish {
+                    topic,
+                    payload: body.to_vec(),
+                    retain: header[0] & 0x01 != 0,
+                }
+            }
+            8 => {
+                let packet_id = u16::from_be_bytes(take::<2>(&mut body)?);
+                let mut filters = Vec::new();
+                while !body.is_empty() {
+                    filters.push(take_string(&mut body)?);
+                    take::<1>(&mut body)?;
+                }
+                Packet::Subscribe { packet_id, filters }
+            }
+            9 => Packet::SubAck {
+                packet_id: u16::from_be_bytes(take::<2>(&mut body)?),
+                return_codes: body.to_vec(),
+            },
+            12 => Packet::PingReq,
+            13 => Packet::PingResp,
+            14 => Packet::Disconnect,
+            _ => return Err(invalid("Unsupported packet type")),
+        };
+
+        Ok(packet)
+    }
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn push_string(bytes: &mut Vec<u8>, s: &str) -> io::Result<()> {
+    let len = u16::try_from(s.len()).map_err(|_| invalid("String is too long"))?;
+    bytes.extend_from_slice(&len.to_be_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+fn push_remaining_length(bytes: &mut Vec<u8>, mut len: usize) -> io::Result<()> {
+    if len > 268_435_455 {
+        return Err(invalid("Packet is too long"));
+    }
+
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        bytes.push(byte);
+        if len == 0 {
+            return Ok(());
+        }
+    }
+}
+
+fn read_remaining_length(reader: &mut impl Read) -> io::Result<usize> {
+    let mut len = 0;
+
+    for i in 0..4 {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        len += ((byte[0] & 0x7F) as usize) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+
+    Err(invalid("Invalid remaining length"))
+}
+
+fn take<const N: usize>(body: &mut &[u8]) -> io::Result<[u8; N]> {
+    if body.len() < N {
+        return Err(invalid("Packet is truncated"));
+    }
+    let (head, tail) = body.split_at(N);
+    *body = tail;
+    Ok(head.try_into().expect("Length is checked"))
+}
+
+fn take_string(body: &mut &[u8]) -> io::Result<String> {
+    let len = u16::from_be_bytes(take::<2>(body)?) as usize;
+    if body.len() < len {
+        return Err(invalid("Packet is truncated"));
+    }
+    let (s, tail) = body.split_at(len);
+    *body = tail;
+    String::from_utf8(s.to_vec()).map_err(|_| invalid("Invalid UTF-8 string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(packet: Packet) {
+        let mut bytes = Vec::new();
+        packet.write(&mut bytes).unwrap();
+        assert_eq!(Packet::read(&mut bytes.as_slice()).unwrap(), packet);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(Packet::Connect {
+            client_id: "bridge".to_owned(),
+            keep_alive: 30,
+        });
+        round_trip(Packet::ConnAck { return_code: 0 });
+        round_trip(Packet::Publish {
+            topic: "smart-house/hall/heater/state".to_owned(),
+            payload: vec![b'x'; 300],
+            retain: true,
+        });
+        round_trip(Packet::Subscribe {
+            packet_id: 1,
+            filters: vec!["a/+".to_owned(), "b/#".to_owned()],
+        });
+        round_trip(Packet::SubAck {
+            packet_id: 1,
+            return_codes: vec![0, 0],
+        });
+        round_trip(Packet::PingReq);
+        round_trip(Packet::Disconnect);
+    }
+
+    #[test]
+    fn test_publish_encoding() {
+        let mut bytes = Vec::new();
+        Packet::Publish {
+            topic: "a/b".to_owned(),
+            payload: b"ON".to_vec(),
+            retain: true,
+        }
+        .write(&mut bytes)
+        .unwrap();
+
+        assert_eq!(bytes, b"\x31\x07\x00\x03a/bON");
+    }
+}
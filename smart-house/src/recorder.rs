@@ -0,0 +1,119 @@
+//! Module describes recording of device readings to local storage
+
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    events,
+    registry::DeviceRegistry,
+    registry::DeviceState,
+    smart_house::{DeviceId, SmartHouse},
+};
+
+pub use self::store::{follow_names, Query, RetentionPolicy, Sample, SampleStore, Series};
+
+mod store;
+
+/// Describes thread which periodically samples all bound devices
+/// of the house and appends their readings to [`SampleStore`].
+/// Recording stops when recorder is dropped.
+pub struct Recorder {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    /// Starts sampling devices of the `house` each `interval`.
+    /// The `store` is compacted according to `policy` on start
+    /// and then once per downsampling interval.
+    pub fn start(
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        store: Arc<SampleStore>,
+        interval: Duration,
+        policy: RetentionPolicy,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut compacted: Option<Instant> = None;
+
+            loop {
+                if compacted.is_none_or(|at| at.elapsed() >= policy.downsample_interval) {
+                    if let Err(err) = store.compact(&policy, events::now()) {
+                        println!("Failed to compact samples: {err}");
+                    }
+                    compacted = Some(Instant::now());
+                }
+
+                let (bindings, ids) = {
+                    let house = house.lock().unwrap();
+                    (house.get_bindings().clone(), house.get_device_ids().clone())
+                };
+                let timestamp = events::now();
+
+                // Devices are queried without holding the lock of the house,
+                // unavailable devices are skipped
+                let samples: Vec<Sample> = bindings
+                    .iter()
+                    .filter_map(|(key, binding)| {
+                        let state = registry.state(binding).ok()?;
+                        Some(samples(timestamp, key, ids.get(key).copied(), &state))
+                    })
+                    .flatten()
+                    .collect();
+
+                if let Err(err) = store.append(&samples) {
+                    println!("Failed to record samples: {err}");
+                }
+
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+/// Returns samples of all metrics of device `state`
+fn samples(
+    timestamp: u64,
+    (room, device): &(String, String),
+    id: Option<DeviceId>,
+    state: &DeviceState,
+) -> Vec<Sample> {
+    state
+        .metrics()
+        .into_iter()
+        .map(|(metric, value)| Sample {
+            timestamp,
+            room: room.clone(),
+            device: device.clone(),
+            id,
+            metric,
+            value,
+        })
+        .collect()
+}
@@ -0,0 +1,491 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{self, Error::StorageError},
+    files,
+    smart_house::{DeviceId, DeviceIdList},
+};
+
+/// Describes single value of device metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    /// Time of the sample in milliseconds since Unix epoch
+    pub timestamp: u64,
+    pub room: String,
+    pub device: String,
+    /// Identifier of the device, it is kept when the device is renamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<DeviceId>,
+    /// Name of metric, e.g. `temperature` or `power`
+    pub metric: String,
+    pub value: f64,
+}
+
+/// Describes how long samples are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Age after which samples are averaged over `downsample_interval`
+    pub raw_age: Duration,
+    pub downsample_interval: Duration,
+    /// Age after which samples are removed
+    pub max_age: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            raw_age: Duration::from_secs(7 * 24 * 3600),
+            downsample_interval: Duration::from_secs(3600),
+            max_age: Duration::from_secs(365 * 24 * 3600),
+        }
+    }
+}
+
+/// Describes query of samples, `None` matches any value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub room: Option<String>,
+    pub device: Option<String>,
+    /// Identifiers of devices, samples with identifier are matched by it
+    /// instead of room and device, so history of renamed devices is found
+    pub ids: Option<BTreeSet<DeviceId>>,
+    pub metric: Option<String>,
+    /// Start of the range in milliseconds since Unix epoch, inclusive
+    pub from: Option<u64>,
+    /// End of the range in milliseconds since Unix epoch, exclusive
+    pub to: Option<u64>,
+    /// Samples are averaged over buckets of `step` milliseconds if set
+    pub step: Option<u64>,
+}
+
+impl Query {
+    /// Matches samples of `devices` with room and device of the query by their
+    /// identifiers, so history recorded under previous names is found too
+    pub fn with_devices(mut self, devices: &DeviceIdList) -> Self {
+        if self.room.is_none() && self.device.is_none() {
+            return self;
+        }
+
+        let ids: BTreeSet<_> = devices
+            .iter()
+            .filter(|((room, device), _)| {
+                self.room.as_ref().is_none_or(|name| name == room)
+                    && self.device.as_ref().is_none_or(|name| name == device)
+            })
+            .map(|(_, id)| *id)
+            .collect();
+
+        if !ids.is_empty() {
+            self.ids = Some(ids);
+        }
+
+        self
+    }
+
+    fn matches(&self, sample: &Sample) -> bool {
+        let device = match (&self.ids, sample.id) {
+            (Some(ids), Some(id)) => ids.contains(&id),
+            _ => {
+                self.room.as_ref().is_none_or(|room| *room == sample.room)
+                    && self
+                        .device
+                        .as_ref()
+                        .is_none_or(|device| *device == sample.device)
+            }
+        };
+
+        device
+            && self
+                .metric
+                .as_ref()
+                .is_none_or(|metric| *metric == sample.metric)
+            && self.from.is_none_or(|from| sample.timestamp >= from)
+            && self.to.is_none_or(|to| sample.timestamp < to)
+    }
+}
+
+/// Describes values of one metric of device ordered by time.
+/// Room and device are names of the last recorded sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Series {
+    pub room: String,
+    pub device: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<DeviceId>,
+    pub metric: String,
+    /// Pairs of timestamp and value
+    pub points: Vec<(u64, f64)>,
+}
+
+/// Describes key of series by metric and device identifier,
+/// samples without identifier are grouped by room and device
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SeriesKey {
+    Id(DeviceId, String),
+    Names(String, String, String),
+}
+
+impl SeriesKey {
+    fn of(sample: &Sample) -> Self {
+        match sample.id {
+            Some(id) => Self::Id(id, sample.metric.clone()),
+            None => Self::Names(
+                sample.room.clone(),
+                sample.device.clone(),
+                sample.metric.clone(),
+            ),
+        }
+    }
+}
+
+/// Describes append-only file of samples, one JSON object per line
+pub struct SampleStore {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl SampleStore {
+    /// Opens store at `path`, the file is created if it doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> errors::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let file = Self::open_for_append(&path)?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends `samples` to the end of the store
+    pub fn append(&self, samples: &[Sample]) -> errors::Result<()> {
+        let mut lines = String::new();
+
+        for sample in samples {
+            lines += &serde_json::to_string(sample).map_err(|err| StorageError(err.into()))?;
+            lines.push('\n');
+        }
+
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(lines.as_bytes())
+            .map_err(StorageError)
+    }
+
+    /// Returns series of samples matching the `query`
+    pub fn query(&self, query: &Query) -> errors::Result<Vec<Series>> {
+        let samples = {
+            let _file = self.file.lock().unwrap();
+            self.read_all()?
+        };
+
+        let mut series: Vec<Series> = group(samples.into_iter().filter(|s| query.matches(s)))
+            .into_values()
+            .map(|mut series| {
+                if let Some(step) = query.step.filter(|step| *step > 0) {
+                    series.points = average(series.points, step);
+                }
+                series
+            })
+            .collect();
+
+        series
+            .sort_by(|a, b| (&a.room, &a.device, &a.metric).cmp(&(&b.room, &b.device, &b.metric)));
+
+        Ok(series)
+    }
+
+    /// Applies retention `policy` at time `now` in milliseconds since Unix epoch:
+    /// removes too old samples and averages samples older than raw age
+    /// over whole downsampling intervals
+    pub fn compact(&self, policy: &RetentionPolicy, now: u64) -> errors::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let samples = self.read_all()?;
+
+        let step = (policy.downsample_interval.as_millis() as u64).max(1);
+        let oldest = now.saturating_sub(policy.max_age.as_millis() as u64);
+        // Only whole intervals are averaged, so averages are never mixed with raw samples
+        let raw_since = now.saturating_sub(policy.raw_age.as_millis() as u64) / step * step;
+
+        let (old, mut compacted): (Vec<_>, Vec<_>) = samples
+            .into_iter()
+            .filter(|sample| sample.timestamp >= oldest)
+            .partition(|sample| sample.timestamp < raw_since);
+
+        for series in group(old.into_iter()).into_values() {
+            compacted.extend(
+                average(series.points, step)
+                    .into_iter()
+                    .map(|(timestamp, value)| Sample {
+                        timestamp,
+                        room: series.room.clone(),
+                        device: series.device.clone(),
+                        id: series.id,
+                        metric: series.metric.clone(),
+                        value,
+                    }),
+            );
+        }
+
+        compacted.sort_by_key(|sample| sample.timestamp);
+
+        let mut lines = String::new();
+        for sample in &compacted {
+            lines += &serde_json::to_string(sample).map_err(|err| StorageError(err.into()))?;
+            lines.push('\n');
+        }
+        files::write_atomically(&self.path, lines)?;
+
+        *file = Self::open_for_append(&self.path)?;
+
+        Ok(())
+    }
+
+    fn open_for_append(path: &Path) -> errors::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(StorageError)
+    }
+
+    /// Reads all samples skipping damaged lines, e.g. partially written
+    fn read_all(&self) -> errors::Result<Vec<Sample>> {
+        let file = File::open(&self.path).map_err(StorageError)?;
+        let mut samples = Vec::new();
+
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(StorageError)?;
+            if let Ok(sample) = serde_json::from_str(&line) {
+                samples.push(sample);
+            }
+        }
+
+        Ok(samples)
+    }
+}
+
+/// Names series of `devices` by current names of the devices,
+/// series of removed devices keep names of their last samples
+pub fn follow_names(series: &mut [Series], devices: &DeviceIdList) {
+    for series in series {
+        let names = series
+            .id
+            .and_then(|id| devices.iter().find(|(_, device_id)| **device_id == id));
+
+        if let Some(((room, device), _)) = names {
+            (series.room, series.device) = (room.clone(), device.clone());
+        }
+    }
+}
+
+/// Groups `samples` into series ordered by time, series are named
+/// by the last sample, so renamed device keeps its history in one series
+fn group(samples: impl Iterator<Item = Sample>) -> BTreeMap<SeriesKey, Series> {
+    let mut series: BTreeMap<SeriesKey, Series> = BTreeMap::new();
+
+    for sample in samples {
+        let entry = series
+            .entry(SeriesKey::of(&sample))
+            .or_insert_with(|| Series {
+                room: String::new(),
+                device: String::new(),
+                id: sample.id,
+                metric: sample.metric.clone(),
+                points: Vec::new(),
+            });
+
+        // Samples are appended in order of time
+        entry.room = sample.room;
+        entry.device = sample.device;
+        entry.points.push((sample.timestamp, sample.value));
+    }
+
+    for series in series.values_mut() {
+        series.points.sort_by_key(|(timestamp, _)| *timestamp);
+    }
+
+    series
+}
+
+/// Averages `points` ordered by time over buckets of `step` milliseconds,
+/// each bucket is marked by its start
+fn average(points: Vec<(u64, f64)>, step: u64) -> Vec<(u64, f64)> {
+    let mut buckets: BTreeMap<u64, (f64, u32)> = BTreeMap::new();
+
+    for (timestamp, value) in points {
+        let (sum, count) = buckets.entry(timestamp / step * step).or_default();
+        *sum += value;
+        *count += 1;
+    }
+
+    buckets
+        .into_iter()
+        .map(|(timestamp, (sum, count))| (timestamp, sum / f64::from(count)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600 * 1000;
+
+    fn sample(timestamp: u64, device: &str, value: f64) -> Sample {
+        Sample {
+            timestamp,
+            room: "Hall".to_owned(),
+            device: device.to_owned(),
+            id: None,
+            metric: "temperature".to_owned(),
+            value,
+        }
+    }
+
+    fn store(name: &str) -> SampleStore {
+        let path =
+            std::env::temp_dir().join(format!("smart-house-{name}-{}.jsonl", std::process::id()));
+        _ = std::fs::remove_file(&path);
+        SampleStore::open(path).unwrap()
+    }
+
+    #[test]
+    fn test_query_by_device_and_range() {
+        let store = store("query");
+        store
+            .append(&[
+                sample(1000, "therm1", 20.0),
+                sample(2000, "therm2", 25.0),
+                sample(3000, "therm1", 22.0),
+                sample(4000, "therm1", 24.0),
+            ])
+            .unwrap();
+
+        let series = store
+            .query(&Query {
+                device: Some("therm1".to_owned()),
+                from: Some(1000),
+                to: Some(4000),
+                ..Query::default()
+            })
+            .unwrap();
+
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].points, vec![(1000, 20.0), (3000, 22.0)]);
+
+        let series = store
+            .query(&Query {
+                device: Some("therm1".to_owned()),
+                step: Some(2000),
+                ..Query::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            series[0].points,
+            vec![(0, 20.0), (2000, 22.0), (4000, 24.0)]
+        );
+    }
+
+    #[test]
+    fn test_renamed_device_keeps_its_series() {
+        let store = store("renamed");
+        let id = DeviceId::new_v4();
+        let with_id = |timestamp, device, value| Sample {
+            id: Some(id),
+            ..sample(timestamp, device, value)
+        };
+        store
+            .append(&[
+                with_id(1000, "therm1", 20.0),
+                // Removed device of the same name isn't a part of the series
+                Sample {
+                    id: Some(DeviceId::new_v4()),
+                    ..sample(1500, "therm2", 30.0)
+                },
+                with_id(2000, "therm2", 22.0),
+            ])
+            .unwrap();
+
+        let series = store.query(&Query::default()).unwrap();
+        assert_eq!(series.len(), 2);
+        let renamed = series.iter().find(|s| s.id == Some(id)).unwrap();
+        assert_eq!(renamed.points, vec![(1000, 20.0), (2000, 22.0)]);
+        assert_eq!(renamed.device, "therm2");
+
+        let devices = DeviceIdList::from([(("Hall".to_owned(), "therm2".to_owned()), id)]);
+        let query = Query {
+            device: Some("therm2".to_owned()),
+            ..Query::default()
+        };
+        let series = store.query(&query.with_devices(&devices)).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].id, Some(id));
+        assert_eq!(series[0].points.len(), 2);
+
+        let renamed = DeviceIdList::from([(("Hall".to_owned(), "heater".to_owned()), id)]);
+        let mut series = store.query(&Query::default()).unwrap();
+        follow_names(&mut series, &renamed);
+        let names: Vec<_> = series
+            .iter()
+            .map(|s| (s.id == Some(id), s.device.as_str()))
+            .collect();
+        assert!(names.contains(&(true, "heater")));
+        assert!(names.contains(&(false, "therm2")));
+
+        // Identifiers survive compaction
+        store.compact(&RetentionPolicy::default(), 3000).unwrap();
+        assert_eq!(store.query(&Query::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_compact_downsamples_and_removes_old_samples() {
+        let store = store("compact");
+        let now = 100 * HOUR;
+        store
+            .append(&[
+                sample(10 * HOUR, "therm1", 10.0),
+                sample(95 * HOUR, "therm1", 20.0),
+                sample(95 * HOUR + 1000, "therm1", 22.0),
+                sample(96 * HOUR + 1000, "therm1", 30.0),
+                sample(99 * HOUR, "therm1", 40.0),
+                sample(99 * HOUR + 1000, "therm1", 42.0),
+            ])
+            .unwrap();
+
+        let policy = RetentionPolicy {
+            raw_age: Duration::from_secs(3 * 3600 + 1800),
+            downsample_interval: Duration::from_secs(3600),
+            max_age: Duration::from_secs(24 * 3600),
+        };
+
+        store.compact(&policy, now).unwrap();
+        // Compaction is idempotent
+        store.compact(&policy, now).unwrap();
+
+        let series = store.query(&Query::default()).unwrap();
+        assert_eq!(
+            series[0].points,
+            vec![
+                (95 * HOUR, 21.0),
+                (96 * HOUR + 1000, 30.0),
+                (99 * HOUR, 40.0),
+                (99 * HOUR + 1000, 42.0)
+            ]
+        );
+
+        // Store is still appendable after compaction
+        store.append(&[sample(now, "therm1", 50.0)]).unwrap();
+        let series = store.query(&Query::default()).unwrap();
+        assert_eq!(series[0].points.last(), Some(&(now, 50.0)));
+    }
+}
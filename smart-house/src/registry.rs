@@ -0,0 +1,236 @@
+//! Module describes registry of devices reached by their bindings
+
+use std::{collections::BTreeMap, fmt, net::SocketAddr, time::Duration};
+
+use power_switch::{client::Client, command::Command, response::Response};
+use serde::Serialize;
+use thermometer::thermometer::{Settings, Thermometer};
+
+use crate::{
+    errors::{self, Error::DeviceUnavailableError, Error::UnsupportedCommandError},
+    smart_house::DeviceBinding,
+};
+
+/// Describes default time to wait for response of device
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Describes current state of device
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceState {
+    PowerSwitch {
+        enabled: bool,
+        /// Current power consumption
+        power: f64,
+        /// Energy consumed by the switch in watt-hours,
+        /// `None` if the switch doesn't report energy
+        #[serde(skip_serializing_if = "Option::is_none")]
+        energy: Option<f64>,
+    },
+    Thermometer {
+        /// Temperature in degrees Celsius
+        temperature: f64,
+        /// Values of all metrics provided by sender by metric name
+        measurements: BTreeMap<String, f64>,
+    },
+}
+
+impl DeviceState {
+    /// Returns values of all metrics of the state by metric name:
+    /// `enabled` (1 or 0), `power` and `energy` (if reported) of power switch,
+    /// `temperature` and other measurements of thermometer
+    pub fn metrics(&self) -> Vec<(String, f64)> {
+        match self {
+            DeviceState::PowerSwitch {
+                enabled,
+                power,
+                energy,
+            } => {
+                let mut values = vec![
+                    ("enabled".to_owned(), f64::from(u8::from(*enabled))),
+                    ("power".to_owned(), *power),
+                ];
+                values.extend(energy.map(|energy| ("energy".to_owned(), energy)));
+                values
+            }
+            DeviceState::Thermometer {
+                temperature,
+                measurements,
+            } => {
+                let mut values = vec![("temperature".to_owned(), *temperature)];
+                values.extend(
+                    measurements
+                        .iter()
+                        .filter(|(metric, _)| *metric != "temperature")
+                        .map(|(metric, value)| (metric.clone(), *value)),
+                );
+                values
+            }
+        }
+    }
+
+    /// Returns value of metric with given `name`
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics()
+            .into_iter()
+            .find_map(|(metric, value)| (metric == name).then_some(value))
+    }
+}
+
+impl fmt::Display for DeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceState::PowerSwitch {
+                enabled,
+                power,
+                energy,
+            } => {
+                let state = if *enabled { "on" } else { "off" };
+                match energy {
+                    Some(energy) => write!(
+                        f,
+                        "Power Switch (state: {state}, power: {power} W, energy: {energy} Wh)"
+                    ),
+                    None => write!(f, "Power Switch (state: {state}, power: {power} W)"),
+                }
+            }
+            DeviceState::Thermometer { temperature, .. } => {
+                write!(f, "Thermometer (temperature: {temperature:.1} °C)")
+            }
+        }
+    }
+}
+
+/// Describes registry which talks to devices of the smart house.
+/// Each request opens new connection to the device,
+/// thermometers are queried by request of reading.
+pub struct DeviceRegistry {
+    timeout: Duration,
+}
+
+impl DeviceRegistry {
+    /// Creates new registry waiting for each device no longer than `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    /// Returns current state of the device with given `binding`
+    pub fn state(&self, binding: &DeviceBinding) -> errors::Result<DeviceState> {
+        match binding {
+            DeviceBinding::PowerSwitch { address } => {
+                let mut client = self.connect(*address)?;
+                Self::switch_state(&mut client)
+            }
+            DeviceBinding::Thermometer { sender } => {
+                let thermometer = self.thermometer(*sender)?;
+
+                thermometer
+                    .read_now()
+                    .map_err(|err| DeviceUnavailableError(err.to_string()))?;
+
+                Ok(DeviceState::Thermometer {
+                    temperature: thermometer.temperature().to_celsius(),
+                    measurements: thermometer
+                        .measurements()
+                        .into_iter()
+                        .map(|(metric, value)| (metric.name().to_owned(), value))
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    /// Turns power switch with given `binding` on or off
+    /// and returns its new state
+    pub fn turn(&self, binding: &DeviceBinding, enabled: bool) -> errors::Result<DeviceState> {
+        let DeviceBinding::PowerSwitch { address } = binding else {
+            return Err(UnsupportedCommandError);
+        };
+
+        let mut client = self.connect(*address)?;
+        let command = if enabled {
+            Command::TurnOn
+        } else {
+            Command::TurnOff
+        };
+
+        match Self::run(&mut client, command)? {
+            Response::Ok => Self::switch_state(&mut client),
+            response => Err(DeviceUnavailableError(format!(
+                "unexpected response: {response}"
+            ))),
+        }
+    }
+
+    fn connect(&self, address: SocketAddr) -> errors::Result<Client> {
+        Client::with_timeout(address, self.timeout)
+            .map_err(|err| DeviceUnavailableError(err.to_string()))
+    }
+
+    fn run(client: &mut Client, command: Command) -> errors::Result<Response> {
+        client
+            .run_command(command)
+            .map_err(|err| DeviceUnavailableError(err.to_string()))
+    }
+
+    fn switch_state(client: &mut Client) -> errors::Result<DeviceState> {
+        let enabled = match Self::run(client, Command::IsEnabled)? {
+            Response::Enabled => true,
+            Response::Disabled => false,
+            response => {
+                return Err(DeviceUnavailableError(format!(
+                    "unexpected response: {response}"
+                )))
+            }
+        };
+
+        let power = match Self::run(client, Command::GetPower)? {
+            Response::Power(power) => power,
+            response => {
+                return Err(DeviceUnavailableError(format!(
+                    "unexpected response: {response}"
+                )))
+            }
+        };
+
+        // Switches without energy meter don't know the command
+        let energy = match Self::run(client, Command::GetEnergy)? {
+            Response::Energy(energy) => Some(energy),
+            Response::Unknown => None,
+            response => {
+                return Err(DeviceUnavailableError(format!(
+                    "unexpected response: {response}"
+                )))
+            }
+        };
+
+        Ok(DeviceState::PowerSwitch {
+            enabled,
+            power,
+            energy,
+        })
+    }
+
+    /// Creates thermometer receiving readings of `sender` at any free port
+    fn thermometer(&self, sender: SocketAddr) -> errors::Result<Thermometer> {
+        let receiver = match sender {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+
+        let settings = Settings {
+            request_timeout: self.timeout,
+            retries: 1,
+            ..Settings::default()
+        };
+
+        Thermometer::from_settings(receiver, &sender.to_string(), settings)
+            .map_err(|err| DeviceUnavailableError(err.to_string()))
+    }
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
@@ -0,0 +1,210 @@
+//! Module describes applying scenes of the smart house, e.g.
+//!
+//! ```json
+//! {
+//!     "scenes": [
+//!         {
+//!             "name": "Away",
+//!             "states": [
+//!                 { "room": "Bathroom", "device": "switch1", "enabled": false },
+//!                 { "room": "Kitchen", "device": "kettle", "enabled": false }
+//!             ]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Devices of the scene are switched concurrently. When rollback is requested
+//! and some device fails, devices already switched are returned to their
+//! previous states, so the scene is applied either to all devices or to none
+//! as far as devices respond.
+
+use std::{path::Path, sync::Mutex, thread};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{self, Error},
+    events::HouseEvents,
+    files,
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::{DeviceBinding, Scene, SceneState, SmartHouse},
+};
+
+/// Describes configuration file of scenes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneConfig {
+    pub scenes: Vec<Scene>,
+}
+
+impl SceneConfig {
+    /// Reads configuration from JSON file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+}
+
+/// Describes result of switching device of the scene
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceOutcome {
+    pub room: String,
+    pub device: String,
+    /// Desired state of the device
+    pub enabled: bool,
+    /// State before the scene was applied, known only when rollback is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// `true` if device was returned to its previous state
+    pub rolled_back: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollback_error: Option<String>,
+}
+
+/// Describes result of applying the scene
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SceneReport {
+    pub scene: String,
+    /// `true` if all devices were switched
+    pub applied: bool,
+    /// `true` if the scene was rolled back because some devices failed
+    pub rolled_back: bool,
+    pub devices: Vec<DeviceOutcome>,
+}
+
+/// Applies `scene` switching its devices concurrently.
+/// When `rollback` is `true` and some device fails,
+/// switched devices are returned to their previous states.
+pub fn apply(
+    scene: &Scene,
+    house: &Mutex<SmartHouse>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    rollback: bool,
+) -> SceneReport {
+    let bindings: Vec<_> = {
+        let house = house.lock().unwrap();
+
+        scene
+            .states
+            .iter()
+            .map(|state| binding(&house, state))
+            .collect()
+    };
+
+    let mut devices: Vec<DeviceOutcome> = thread::scope(|scope| {
+        let handles: Vec<_> = scene
+            .states
+            .iter()
+            .zip(&bindings)
+            .map(|(state, binding)| {
+                scope.spawn(move || switch(state, binding, registry, events, rollback))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Switching device panicked"))
+            .collect()
+    });
+
+    let applied = devices.iter().all(|outcome| outcome.error.is_none());
+    let rolled_back = rollback && !applied;
+
+    if rolled_back {
+        thread::scope(|scope| {
+            for (outcome, binding) in devices.iter_mut().zip(&bindings) {
+                let (Ok(binding), None, Some(previous)) =
+                    (binding, &outcome.error, outcome.previous)
+                else {
+                    continue;
+                };
+
+                scope.spawn(move || {
+                    if previous != outcome.enabled {
+                        let result = registry.turn(binding, previous);
+                        events.observe(&outcome.room, &outcome.device, &result);
+                        outcome.rollback_error = result.err().map(|err| err.to_string());
+                    }
+
+                    outcome.rolled_back = outcome.rollback_error.is_none();
+                });
+            }
+        });
+    }
+
+    SceneReport {
+        scene: scene.name.clone(),
+        applied,
+        rolled_back,
+        devices,
+    }
+}
+
+/// Returns binding of power switch of the scene
+fn binding(house: &SmartHouse, state: &SceneState) -> errors::Result<DeviceBinding> {
+    if !house
+        .devices(&state.room)
+        .is_some_and(|devices| devices.contains(&state.device))
+    {
+        return Err(Error::DeviceNotFoundError {
+            device_name: state.device.clone(),
+            room_name: state.room.clone(),
+        });
+    }
+
+    house
+        .binding(&state.room, &state.device)
+        .cloned()
+        .ok_or_else(|| Error::DeviceNotBoundError {
+            device_name: state.device.clone(),
+            room_name: state.room.clone(),
+        })
+}
+
+/// Switches device to the desired state, its previous state is queried first
+/// when it may be rolled back
+fn switch(
+    state: &SceneState,
+    binding: &errors::Result<DeviceBinding>,
+    registry: &DeviceRegistry,
+    events: &HouseEvents,
+    rollback: bool,
+) -> DeviceOutcome {
+    let mut outcome = DeviceOutcome {
+        room: state.room.clone(),
+        device: state.device.clone(),
+        enabled: state.enabled,
+        previous: None,
+        error: None,
+        rolled_back: false,
+        rollback_error: None,
+    };
+
+    let result = binding
+        .as_ref()
+        .map_err(|err| err.to_string())
+        .and_then(|binding| {
+            if rollback {
+                outcome.previous = match registry.state(binding) {
+                    Ok(DeviceState::PowerSwitch { enabled, .. }) => Some(enabled),
+                    Ok(DeviceState::Thermometer { .. }) => {
+                        return Err(Error::UnsupportedCommandError.to_string())
+                    }
+                    Err(err) => return Err(err.to_string()),
+                };
+            }
+
+            let result = registry.turn(binding, state.enabled);
+
+            if !matches!(result, Err(Error::UnsupportedCommandError)) {
+                events.observe(&state.room, &state.device, &result);
+            }
+
+            result.map(|_| ()).map_err(|err| err.to_string())
+        });
+
+    outcome.error = result.err();
+    outcome
+}
@@ -0,0 +1,257 @@
+//! Module describes scheduler of device actions, e.g.
+//!
+//! ```json
+//! {
+//!     "location": { "latitude": 52.52, "longitude": 13.405, "timezone": "Europe/Berlin" },
+//!     "jobs": [
+//!         {
+//!             "name": "Kettle on",
+//!             "when": "0 7 * * MON-FRI",
+//!             "then": [{ "room": "Kitchen", "device": "kettle", "turn": "on" }],
+//!             "missed": "run_once"
+//!         },
+//!         {
+//!             "name": "Kettle off",
+//!             "when": "20 7 * * MON-FRI",
+//!             "then": [{ "room": "Kitchen", "device": "kettle", "turn": "off" }],
+//!             "missed": "run_once"
+//!         },
+//!         {
+//!             "name": "Lights on",
+//!             "when": "sunset+15m",
+//!             "then": [{ "room": "Hall", "device": "lights", "turn": "on" }]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Cron expressions are evaluated in the time zone of the location,
+//! sunrise and sunset are computed from its coordinates.
+//! Runs missed while the scheduler was not running are handled
+//! according to [`MissedRuns`] of the job.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    automation::{self, AuditEntry, AuditLog},
+    events::{self, HouseEvents},
+    registry::DeviceRegistry,
+    smart_house::SmartHouse,
+};
+
+pub use self::cron::CronExpr;
+pub use self::job::{Job, Location, MissedRuns, Trigger};
+pub use self::store::{Schedule, ScheduleStore};
+pub use self::sun::{sun_event, SunEvent};
+
+mod cron;
+mod job;
+mod store;
+mod sun;
+
+/// Describes how often jobs are checked
+const TICK: Duration = Duration::from_secs(1);
+
+/// Describes job with times of its last and next runs
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    #[serde(flatten)]
+    pub job: Job,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+/// Returns jobs of the `store` with times of their runs after `now`
+pub fn jobs(store: &ScheduleStore, now: DateTime<Utc>) -> Vec<JobInfo> {
+    let schedule = store.schedule();
+
+    schedule
+        .jobs
+        .into_iter()
+        .map(|job| JobInfo {
+            last_run: store.last_run(&job.name),
+            next_run: job.when.next_after(now, &schedule.location),
+            job,
+        })
+        .collect()
+}
+
+/// Describes times jobs of the schedule were checked at
+struct Checks {
+    started: DateTime<Utc>,
+    checked: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl Checks {
+    fn new(started: DateTime<Utc>) -> Self {
+        Self {
+            started,
+            checked: BTreeMap::new(),
+        }
+    }
+
+    /// Returns how many times each job of the `schedule` has to run at `now`.
+    /// Jobs are checked since their last check or `last_run`,
+    /// jobs seen the first time only since `now`, so triggers before
+    /// a job was added don't run it.
+    fn due_runs(
+        &mut self,
+        schedule: &Schedule,
+        last_run: impl Fn(&str) -> Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Vec<usize> {
+        // Job removed and added again is new
+        self.checked
+            .retain(|name, _| schedule.jobs.iter().any(|job| &job.name == name));
+
+        schedule
+            .jobs
+            .iter()
+            .map(|job| {
+                let last = self
+                    .checked
+                    .insert(job.name.clone(), now)
+                    .or_else(|| last_run(&job.name))
+                    .unwrap_or(now);
+
+                job.due_runs(last, self.started, now, &schedule.location)
+            })
+            .collect()
+    }
+}
+
+/// Describes thread which runs jobs of the schedule when they are due
+/// and records them to the audit log. Changes of the schedule
+/// are picked up on the next check. Scheduling stops when scheduler is dropped.
+pub struct Scheduler {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    /// Starts running jobs of the `store`
+    pub fn start(
+        store: Arc<ScheduleStore>,
+        house: Arc<Mutex<SmartHouse>>,
+        registry: Arc<DeviceRegistry>,
+        events: Arc<HouseEvents>,
+        audit: Arc<AuditLog>,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut checks = Checks::new(Utc::now());
+
+            loop {
+                let now = Utc::now();
+                let schedule = store.schedule();
+                let due_runs = checks.due_runs(&schedule, |name| store.last_run(name), now);
+
+                for (job, runs) in schedule.jobs.iter().zip(due_runs) {
+                    for _ in 0..runs {
+                        let actions = job
+                            .then
+                            .iter()
+                            .map(|action| {
+                                automation::perform(action, &house, &registry, &events, false)
+                            })
+                            .collect();
+
+                        let entry = AuditEntry {
+                            timestamp: events::now(),
+                            rule: job.name.clone(),
+                            dry_run: false,
+                            actions,
+                        };
+
+                        println!("Job ran: {}", serde_json::json!(entry));
+
+                        audit.record(entry);
+
+                        if let Err(err) = store.record_run(&job.name, now) {
+                            println!("Failed to save schedule: {err}");
+                        }
+                    }
+                }
+
+                match stopped.recv_timeout(TICK) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_job_added_later_waits_for_next_trigger() {
+        let job: Job = serde_json::from_str(
+            r#"{
+                "name": "Kettle on",
+                "when": "0 7 * * *",
+                "then": [{ "room": "Kitchen", "device": "kettle", "turn": "on" }],
+                "missed": "run_all"
+            }"#,
+        )
+        .unwrap();
+        let mut schedule = Schedule::default();
+        let mut checks = Checks::new(at(1, 12, 0));
+
+        assert!(checks
+            .due_runs(&schedule, |_| None, at(1, 12, 0))
+            .is_empty());
+
+        // Added after 07:00 of the next day
+        schedule.jobs.push(job.clone());
+        assert_eq!(checks.due_runs(&schedule, |_| None, at(2, 10, 0)), vec![0]);
+        assert_eq!(checks.due_runs(&schedule, |_| None, at(2, 10, 1)), vec![0]);
+        assert_eq!(checks.due_runs(&schedule, |_| None, at(3, 7, 0)), vec![1]);
+
+        // Removed and added again after its trigger
+        schedule.jobs.clear();
+        assert!(checks.due_runs(&schedule, |_| None, at(3, 8, 0)).is_empty());
+        schedule.jobs.push(job);
+        assert_eq!(checks.due_runs(&schedule, |_| None, at(4, 8, 0)), vec![0]);
+
+        // Stored last run is caught up
+        let mut checks = Checks::new(at(5, 12, 0));
+        let last_run = |_: &str| Some(at(4, 8, 0));
+        assert_eq!(checks.due_runs(&schedule, last_run, at(5, 12, 0)), vec![1]);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// Describes how many days are searched for the next run,
+/// enough for expressions matching 29th of February only
+const SEARCH_DAYS: i64 = 366 * 8;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// Describes cron expression of five fields:
+/// `<minute> <hour> <day of month> <month> <day of week>`.
+///
+/// Fields are lists of values, ranges `a-b` and steps `*/n` or `a-b/n`.
+/// Months and days of week can be given by names, e.g. `JAN` or `MON-FRI`.
+/// Sunday is both `0` and `7`. As in classic cron, when both day of month
+/// and day of week are restricted, the day matches either of them.
+///
+/// Macros `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpr {
+    /// Returns whether expression matches the minute of `time`
+    pub fn matches(&self, time: NaiveDateTime) -> bool {
+        self.matches_date(time.date())
+            && has(self.hours, time.hour())
+            && has(self.minutes, time.minute())
+    }
+
+    /// Returns the first matching minute after `time`,
+    /// `None` if expression never matches, e.g. `0 0 30 2 *`
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = time
+            .with_second(0)?
+            .with_nanosecond(0)?
+            .checked_add_signed(Duration::minutes(1))?;
+        let mut date = start.date();
+
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                let from = if date == start.date() {
+                    start.time()
+                } else {
+                    NaiveTime::MIN
+                };
+
+                if let Some(time) = self.first_time_from(from) {
+                    return Some(date.and_time(time));
+                }
+            }
+
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    fn first_time_from(&self, from: NaiveTime) -> Option<NaiveTime> {
+        (from.hour()..24)
+            .filter(|hour| has(self.hours, *hour))
+            .find_map(|hour| {
+                let first_minute = if hour == from.hour() {
+                    from.minute()
+                } else {
+                    0
+                };
+
+                (first_minute..60)
+                    .find(|minute| has(self.minutes, *minute))
+                    .and_then(|minute| NaiveTime::from_hms_opt(hour, minute, 0))
+            })
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses field into set of values in `min..=max`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let value = match names.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+            Some(index) => index as u32 + min,
+            None => s
+                .parse()
+                .map_err(|_| format!(r#"Invalid value "{s}" in "{field}""#))?,
+        };
+
+        if value < min || value > max {
+            return Err(format!(
+                r#"Value {value} in "{field}" is out of range {min}-{max}"#
+            ));
+        }
+
+        Ok(value)
+    };
+
+    let mut set = 0;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!(r#"Invalid step "{step}" in "{field}""#))?;
+                if step == 0 {
+                    return Err(format!(r#"Zero step in "{field}""#));
+                }
+                (range, step)
+            }
+            None => (item, 1),
+        };
+
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // Single value with step runs up to the end of range, e.g. `5/15`
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+
+        if first > last {
+            return Err(format!(r#"Invalid range "{range}" in "{field}""#));
+        }
+
+        for value in (first..=last).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+impl FromStr for CronExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expr => expr,
+        };
+
+        let fields: Vec<_> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                r#"Expected 5 fields in cron expression "{s}", found {}"#,
+                fields.len()
+            ));
+        };
+
+        let mut weekday_set = parse_field(weekdays, 0, 7, &WEEKDAYS)?;
+        // Sunday is both 0 and 7
+        if has(weekday_set, 7) {
+            weekday_set |= 1;
+        }
+
+        Ok(Self {
+            source: s.trim().to_owned(),
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days: parse_field(days, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTHS)?,
+            weekdays: weekday_set,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+impl fmt::Display for CronExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2024-01-05 is Friday
+        let expr: CronExpr = "0,20 7 * * MON-FRI".parse().unwrap();
+
+        assert_eq!(
+            expr.next_after(time("2024-01-05 06:59")),
+            Some(time("2024-01-05 07:00"))
+        );
+        assert_eq!(
+            expr.next_after(time("2024-01-05 07:00")),
+            Some(time("2024-01-05 07:20"))
+        );
+        assert_eq!(
+            expr.next_after(time("2024-01-05 07:20")),
+            Some(time("2024-01-08 07:00"))
+        );
+        assert!(expr.matches(time("2024-01-08 07:20")));
+        assert!(!expr.matches(time("2024-01-07 07:20")));
+    }
+
+    #[test]
+    fn test_steps_macros_and_day_matching() {
+        let expr: CronExpr = "*/15 9-17/4 * * *".parse().unwrap();
+        assert_eq!(
+            expr.next_after(time("2024-03-01 13:50")),
+            Some(time("2024-03-01 17:00"))
+        );
+
+        let expr: CronExpr = "@yearly".parse().unwrap();
+        assert_eq!(
+            expr.next_after(time("2024-03-01 13:50")),
+            Some(time("2025-01-01 00:00"))
+        );
+
+        // Either 13th or Friday
+        let expr: CronExpr = "0 0 13 * 5".parse().unwrap();
+        assert_eq!(
+            expr.next_after(time("2024-01-06 00:00")),
+            Some(time("2024-01-12 00:00"))
+        );
+        assert_eq!(
+            expr.next_after(time("2024-01-12 00:00")),
+            Some(time("2024-01-13 00:00"))
+        );
+
+        let expr: CronExpr = "0 12 29 feb 7".parse().unwrap();
+        assert!(expr.matches(time("2024-02-29 12:00")));
+        assert!(expr.matches(time("2024-02-04 12:00")));
+
+        let expr: CronExpr = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(expr.next_after(time("2024-01-01 00:00")), None);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "x * * * *",
+        ] {
+            assert!(expr.parse::<CronExpr>().is_err(), "{expr}");
+        }
+    }
+}
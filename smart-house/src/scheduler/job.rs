@@ -0,0 +1,352 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Duration, LocalResult, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cron::CronExpr,
+    sun::{sun_event, SunEvent},
+};
+use crate::automation::Action;
+
+/// Describes how many days are searched for the next sunrise or sunset,
+/// enough to get over polar night
+const SUN_SEARCH_DAYS: i64 = 370;
+
+/// Describes how many missed runs are counted after downtime
+const MAX_MISSED_RUNS: usize = 100;
+
+/// Describes place of the house, used to compute sunrise and sunset
+/// and to evaluate cron expressions in local time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub latitude: f64,
+    /// Longitude in degrees, east is positive
+    pub longitude: f64,
+    /// Time zone from IANA database, e.g. `Europe/Berlin`
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Self {
+            latitude: 0.0,
+            longitude: 0.0,
+            timezone: default_timezone(),
+        }
+    }
+}
+
+/// Describes when job runs: cron expression, e.g. `0 7 * * MON-FRI`,
+/// or sunrise and sunset with optional offset, e.g. `sunset+15m` or `sunrise-1h`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Trigger {
+    Cron(CronExpr),
+    Sun {
+        event: SunEvent,
+        /// Offset from the event in minutes
+        offset: i64,
+    },
+}
+
+impl Trigger {
+    /// Returns the first time the trigger fires after `time` at `location`,
+    /// `None` if it never fires
+    pub fn next_after(&self, time: DateTime<Utc>, location: &Location) -> Option<DateTime<Utc>> {
+        let timezone = location.timezone;
+
+        match self {
+            Self::Cron(expr) => {
+                let mut local = time.with_timezone(&timezone).naive_local();
+
+                loop {
+                    local = expr.next_after(local)?;
+
+                    // Local times skipped by daylight saving transition are skipped,
+                    // repeated ones run once
+                    let next = match timezone.from_local_datetime(&local) {
+                        LocalResult::Single(next) | LocalResult::Ambiguous(next, _) => next,
+                        LocalResult::None => continue,
+                    };
+
+                    if next > time {
+                        return Some(next.with_timezone(&Utc));
+                    }
+                }
+            }
+            Self::Sun { event, offset } => {
+                // Event of the previous local day may occur after `time` with offset
+                let first = time.with_timezone(&timezone).date_naive().pred_opt()?;
+
+                first
+                    .iter_days()
+                    .take(SUN_SEARCH_DAYS as usize)
+                    .filter_map(|date| {
+                        sun_event(*event, date, location.latitude, location.longitude)
+                    })
+                    .map(|event_time| event_time + Duration::minutes(*offset))
+                    .find(|next| *next > time)
+            }
+        }
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (event, rest) = if let Some(rest) = s.strip_prefix("sunrise") {
+            (SunEvent::Sunrise, rest)
+        } else if let Some(rest) = s.strip_prefix("sunset") {
+            (SunEvent::Sunset, rest)
+        } else {
+            return Ok(Self::Cron(s.parse()?));
+        };
+
+        let invalid = || format!(r#"Expected offset like "+15m" or "-1h", found "{rest}""#);
+
+        let offset = if rest.is_empty() {
+            0
+        } else {
+            let (sign, amount) = match rest.split_at(1) {
+                ("+", amount) => (1, amount),
+                ("-", amount) => (-1, amount),
+                _ => return Err(invalid()),
+            };
+            let (amount, minutes) = match amount.strip_suffix('h') {
+                Some(hours) => (hours, 60),
+                None => (amount.strip_suffix('m').unwrap_or(amount), 1),
+            };
+
+            sign * minutes * amount.parse::<i64>().map_err(|_| invalid())?
+        };
+
+        Ok(Self::Sun { event, offset })
+    }
+}
+
+impl TryFrom<String> for Trigger {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Trigger> for String {
+    fn from(trigger: Trigger) -> Self {
+        trigger.to_string()
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cron(expr) => write!(f, "{expr}"),
+            Self::Sun { event, offset } => {
+                let event = match event {
+                    SunEvent::Sunrise => "sunrise",
+                    SunEvent::Sunset => "sunset",
+                };
+
+                match offset {
+                    0 => write!(f, "{event}"),
+                    offset => write!(f, "{event}{offset:+}m"),
+                }
+            }
+        }
+    }
+}
+
+/// Describes what to do with runs missed while the scheduler was not running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRuns {
+    /// Missed runs are forgotten
+    #[default]
+    Skip,
+    /// Job runs once if any run was missed
+    RunOnce,
+    /// Job runs as many times as it was missed, up to 100 times
+    RunAll,
+}
+
+/// Describes scheduled job turning power switches on and off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub name: String,
+    pub when: Trigger,
+    pub then: Vec<Action>,
+    #[serde(default)]
+    pub missed: MissedRuns,
+}
+
+impl Job {
+    /// Returns how many times job has to run now given it was checked
+    /// the last time at `last` and the scheduler was started at `started`.
+    /// Runs between `last` and `started` are missed.
+    pub fn due_runs(
+        &self,
+        last: DateTime<Utc>,
+        started: DateTime<Utc>,
+        now: DateTime<Utc>,
+        location: &Location,
+    ) -> usize {
+        let mut missed = 0;
+        let mut regular = 0;
+        let mut time = last;
+
+        while let Some(next) = self.when.next_after(time, location) {
+            if next > now || missed >= MAX_MISSED_RUNS {
+                break;
+            }
+
+            if next < started {
+                missed += 1;
+            } else {
+                regular += 1;
+            }
+
+            time = next;
+        }
+
+        let missed = match self.missed {
+            MissedRuns::Skip => 0,
+            MissedRuns::RunOnce => missed.min(1),
+            MissedRuns::RunAll => missed,
+        };
+
+        // Runs on time are never repeated, e.g. after the clock jumped forward
+        regular.min(1) + missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn berlin() -> Location {
+        Location {
+            latitude: 52.52,
+            longitude: 13.405,
+            timezone: chrono_tz::Europe::Berlin,
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn job(when: &str, missed: MissedRuns) -> Job {
+        Job {
+            name: "Kettle".to_owned(),
+            when: when.parse().unwrap(),
+            then: vec![],
+            missed,
+        }
+    }
+
+    #[test]
+    fn test_parse_trigger() {
+        assert_eq!(
+            "sunset+15m".parse::<Trigger>().unwrap(),
+            Trigger::Sun {
+                event: SunEvent::Sunset,
+                offset: 15
+            }
+        );
+        assert_eq!(
+            "sunrise-1h".parse::<Trigger>().unwrap(),
+            Trigger::Sun {
+                event: SunEvent::Sunrise,
+                offset: -60
+            }
+        );
+        assert_eq!(
+            "sunrise-60m".parse::<Trigger>().unwrap().to_string(),
+            "sunrise-60m"
+        );
+        assert_eq!(
+            "0 7 * * 1-5".parse::<Trigger>().unwrap().to_string(),
+            "0 7 * * 1-5"
+        );
+        assert!("sunset15".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn test_cron_in_timezone() {
+        let trigger: Trigger = "0 7 * * *".parse().unwrap();
+
+        // 07:00 CEST is 05:00 UTC
+        assert_eq!(
+            trigger.next_after(utc("2024-06-21T00:00:00Z"), &berlin()),
+            Some(utc("2024-06-21T05:00:00Z"))
+        );
+
+        // 02:30 doesn't exist on 2024-03-31 in Berlin
+        let trigger: Trigger = "30 2 * * *".parse().unwrap();
+        assert_eq!(
+            trigger.next_after(utc("2024-03-30T12:00:00Z"), &berlin()),
+            Some(utc("2024-04-01T00:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_sunset_with_offset() {
+        let trigger: Trigger = "sunset+15m".parse().unwrap();
+        let next = trigger
+            .next_after(utc("2024-06-21T12:00:00Z"), &berlin())
+            .unwrap();
+        let sunset = sun_event(
+            SunEvent::Sunset,
+            NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(),
+            52.52,
+            13.405,
+        )
+        .unwrap();
+
+        assert_eq!(next, sunset + Duration::minutes(15));
+        assert!(trigger.next_after(next, &berlin()).unwrap() > utc("2024-06-22T19:00:00Z"));
+    }
+
+    #[test]
+    fn test_missed_runs() {
+        let location = Location::default();
+        let last = utc("2024-01-01T12:00:00Z");
+        let started = utc("2024-01-04T12:00:00Z");
+
+        // Three runs at 07:00 were missed, no run is due now
+        let now = started;
+        assert_eq!(
+            job("0 7 * * *", MissedRuns::Skip).due_runs(last, started, now, &location),
+            0
+        );
+        assert_eq!(
+            job("0 7 * * *", MissedRuns::RunOnce).due_runs(last, started, now, &location),
+            1
+        );
+        assert_eq!(
+            job("0 7 * * *", MissedRuns::RunAll).due_runs(last, started, now, &location),
+            3
+        );
+
+        // Run on time
+        let last = utc("2024-01-05T06:59:59Z");
+        let now = utc("2024-01-05T07:00:01Z");
+        assert_eq!(
+            job("0 7 * * *", MissedRuns::Skip).due_runs(last, started, now, &location),
+            1
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::job::{Job, Location};
+use crate::{
+    errors::{
+        self,
+        Error::{ConfigError, StorageError},
+    },
+    files,
+};
+
+/// Describes schedule of jobs at the location of the house
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub location: Location,
+    #[serde(default)]
+    pub jobs: Vec<Job>,
+}
+
+/// Describes content of the schedule file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScheduleFile {
+    #[serde(flatten)]
+    schedule: Schedule,
+    /// Time of the last run of each job, used to catch up after downtime
+    #[serde(default)]
+    last_runs: BTreeMap<String, DateTime<Utc>>,
+}
+
+/// Describes schedule persisted to JSON file with times of the last runs.
+/// The file is rewritten on each change.
+pub struct ScheduleStore {
+    path: PathBuf,
+    file: Mutex<ScheduleFile>,
+}
+
+impl ScheduleStore {
+    /// Opens store at `path`, schedule is empty if the file doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> errors::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        let file = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|err| ConfigError(format!("{}: {err}", path.display())))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => ScheduleFile::default(),
+            Err(err) => return Err(StorageError(err)),
+        };
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Returns the schedule
+    pub fn schedule(&self) -> Schedule {
+        self.file.lock().unwrap().schedule.clone()
+    }
+
+    /// Replaces the whole schedule, times of the last runs are kept
+    pub fn set_schedule(&self, schedule: Schedule) -> errors::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        file.schedule = schedule;
+        self.save(&file)
+    }
+
+    /// Adds the `job`, fails if job with the same name exists
+    pub fn add_job(&self, job: Job) -> errors::Result<()> {
+        let mut file = self.file.lock().unwrap();
+
+        if file
+            .schedule
+            .jobs
+            .iter()
+            .any(|other| other.name == job.name)
+        {
+            return Err(ConfigError(format!(r#"Job "{}" already exists"#, job.name)));
+        }
+
+        file.schedule.jobs.push(job);
+        self.save(&file)
+    }
+
+    /// Removes job by `name`, returns `false` if there is no such job
+    pub fn remove_job(&self, name: &str) -> errors::Result<bool> {
+        let mut file = self.file.lock().unwrap();
+        let count = file.schedule.jobs.len();
+
+        file.schedule.jobs.retain(|job| job.name != name);
+        if file.schedule.jobs.len() == count {
+            return Ok(false);
+        }
+
+        file.last_runs.remove(name);
+        self.save(&file).map(|_| true)
+    }
+
+    /// Makes actions of jobs on device refer to its new room and name
+    pub fn rename_device(
+        &self,
+        room: &str,
+        device: &str,
+        new_room: &str,
+        new_device: &str,
+    ) -> errors::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let mut renamed = false;
+
+        for action in file.schedule.jobs.iter_mut().flat_map(|job| &mut job.then) {
+            if action.room == room && action.device == device {
+                (action.room, action.device) = (new_room.to_owned(), new_device.to_owned());
+                renamed = true;
+            }
+        }
+
+        if renamed {
+            self.save(&file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns time of the last run of job
+    pub fn last_run(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.file.lock().unwrap().last_runs.get(name).copied()
+    }
+
+    /// Records that job ran at `time`
+    pub fn record_run(&self, name: &str, time: DateTime<Utc>) -> errors::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        file.last_runs.insert(name.to_owned(), time);
+        self.save(&file)
+    }
+
+    /// Writes the file at once, so it is never left partially written
+    fn save(&self, file: &ScheduleFile) -> errors::Result<()> {
+        let content = serde_json::to_string_pretty(file).map_err(|err| StorageError(err.into()))?;
+
+        files::write_atomically(&self.path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_persisted() {
+        let path =
+            env::temp_dir().join(format!("smart-house-schedule-{}.json", std::process::id()));
+        _ = fs::remove_file(&path);
+
+        let job: Job = serde_json::from_str(
+            r#"{
+                "name": "Kettle on",
+                "when": "0 7 * * MON-FRI",
+                "then": [{ "room": "Kitchen", "device": "kettle", "turn": "on" }]
+            }"#,
+        )
+        .unwrap();
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        let store = ScheduleStore::open(&path).unwrap();
+        store.add_job(job.clone()).unwrap();
+        assert!(store.add_job(job.clone()).is_err());
+        store.record_run("Kettle on", time).unwrap();
+
+        let store = ScheduleStore::open(&path).unwrap();
+        assert_eq!(store.schedule().jobs, vec![job]);
+        assert_eq!(store.last_run("Kettle on"), Some(time));
+
+        store
+            .rename_device("Kitchen", "kettle", "Kitchen", "teapot")
+            .unwrap();
+        let store = ScheduleStore::open(&path).unwrap();
+        assert_eq!(store.schedule().jobs[0].then[0].device, "teapot");
+
+        assert!(store.remove_job("Kettle on").unwrap());
+        assert!(!store.remove_job("Kettle on").unwrap());
+        assert_eq!(store.last_run("Kettle on"), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Julian day of 2000-01-01 12:00 UTC
+const J2000: f64 = 2_451_545.0;
+/// Julian day of 1970-01-01 00:00 UTC
+const UNIX_EPOCH: f64 = 2_440_587.5;
+/// Obliquity of the Earth's axis in degrees
+const OBLIQUITY: f64 = 23.4397;
+/// Altitude of the Sun's center at sunrise and sunset accounting
+/// for refraction and the Sun's radius in degrees
+const HORIZON: f64 = -0.833;
+
+/// Describes astronomical event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SunEvent {
+    Sunrise,
+    Sunset,
+}
+
+/// Returns time of `event` on `date` at the place given by `latitude`
+/// and `longitude` (east is positive) in degrees.
+/// Returns `None` during polar day or night.
+///
+/// Calculation follows the sunrise equation and is accurate to a minute or two.
+pub fn sun_event(
+    event: SunEvent,
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+) -> Option<DateTime<Utc>> {
+    let days = date
+        .signed_duration_since(NaiveDate::from_ymd_opt(2000, 1, 1)?)
+        .num_days() as f64;
+
+    let mean_solar_noon = days - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_solar_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+
+    let transit =
+        J2000 + mean_solar_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (HORIZON.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+    let julian = match event {
+        SunEvent::Sunrise => transit - hour_angle,
+        SunEvent::Sunset => transit + hour_angle,
+    };
+
+    let millis = ((julian - UNIX_EPOCH) * 86_400_000.0).round() as i64;
+    DateTime::from_timestamp_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn assert_close(actual: Option<DateTime<Utc>>, expected: DateTime<Utc>) {
+        let difference = (actual.unwrap() - expected).num_seconds().abs();
+        assert!(difference < 180, "{actual:?} != {expected}");
+    }
+
+    #[test]
+    fn test_sunrise_and_sunset() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+
+        // Berlin
+        assert_close(
+            sun_event(SunEvent::Sunrise, date, 52.52, 13.405),
+            Utc.with_ymd_and_hms(2024, 6, 21, 2, 43, 0).unwrap(),
+        );
+        assert_close(
+            sun_event(SunEvent::Sunset, date, 52.52, 13.405),
+            Utc.with_ymd_and_hms(2024, 6, 21, 19, 33, 0).unwrap(),
+        );
+
+        // Polar day in Tromsø
+        assert_eq!(sun_event(SunEvent::Sunset, date, 69.65, 18.96), None);
+    }
+}
@@ -1,19 +1,51 @@
 //! Module describes smart house
 
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+};
 
-use crate::errors::{self, Error::DeviceNotFoundError};
+use uuid::Uuid;
 
-use self::room::{DeviceList, Room};
+use crate::errors::{
+    self,
+    Error::{DeviceExistsError, DeviceNotFoundError, RoomExistsError, RoomNotFoundError},
+};
 
+pub use self::binding::DeviceBinding;
+pub use self::budget::{PowerBudget, SwitchPriority};
+pub use self::room::{DeviceList, Room};
+pub use self::scene::{Scene, SceneState};
+
+mod binding;
+mod budget;
 mod room;
+mod scene;
 
 /// Describes list of rooms in the smart house
 pub type RoomList = BTreeMap<String, Room>;
+/// Describes stable unique identifier of device,
+/// it doesn't change when device is renamed or moved to another room
+pub type DeviceId = Uuid;
+/// Describes identifiers of devices by room name and device name
+pub type DeviceIdList = BTreeMap<(String, String), DeviceId>;
+/// Describes bindings of devices by room name and device name
+pub type BindingList = BTreeMap<(String, String), DeviceBinding>;
+/// Describes scenes of the smart house by name
+pub type SceneList = BTreeMap<String, Scene>;
+/// Describes devices of the group by room name and device name
+pub type GroupMembers = BTreeSet<(String, String)>;
+/// Describes groups of devices spanning rooms by group name, e.g. "heaters"
+pub type GroupList = BTreeMap<String, GroupMembers>;
 /// Describes smart house
 pub struct SmartHouse {
     name: String,
     rooms: RoomList,
+    device_ids: DeviceIdList,
+    bindings: BindingList,
+    scenes: SceneList,
+    groups: GroupList,
+    power_budget: Option<PowerBudget>,
 }
 
 impl SmartHouse {
@@ -22,6 +54,11 @@ impl SmartHouse {
         Self {
             name: String::from(name),
             rooms: BTreeMap::new(),
+            device_ids: BTreeMap::new(),
+            bindings: BTreeMap::new(),
+            scenes: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            power_budget: None,
         }
     }
 
@@ -58,7 +95,7 @@ impl SmartHouse {
         &self.rooms
     }
 
-    /// Adds room to the smart house.
+    /// Adds room to the smart house, devices of the room get new identifiers.
     /// Returns `true` if room was added,
     /// returns `false` otherwise.
     pub fn add_room(&mut self, room: Room) -> bool {
@@ -66,6 +103,12 @@ impl SmartHouse {
             return false;
         }
 
+        for device_name in room.get_devices() {
+            _ = self.device_ids.insert(
+                (room.get_name().to_owned(), device_name.clone()),
+                Uuid::new_v4(),
+            );
+        }
         _ = self.rooms.insert(room.get_name().to_owned(), room);
 
         true
@@ -80,15 +123,405 @@ impl SmartHouse {
         }
 
         _ = self.rooms.remove(room_name);
+        self.device_ids.retain(|(room, _), _| room != room_name);
+        self.bindings.retain(|(room, _), _| room != room_name);
+        self.retain_group_members(|room, _| room != room_name);
+        self.retain_scene_states(|room, _| room != room_name);
+        self.retain_priorities(|room, _| room != room_name);
+
+        true
+    }
+
+    /// Adds room with given name if it doesn't exist
+    /// and adds device with new identifier to the room.
+    /// Returns `true` if device was added,
+    /// returns `false` if room already contains the device.
+    pub fn add_device(&mut self, room_name: &str, device_name: &str) -> bool {
+        self.add_device_with_id(room_name, device_name, Uuid::new_v4())
+    }
+
+    /// Adds room with given name if it doesn't exist
+    /// and adds device with known identifier to the room, e.g. restored from file.
+    /// Returns `true` if device was added,
+    /// returns `false` if room already contains the device or identifier is taken.
+    pub fn add_device_with_id(&mut self, room_name: &str, device_name: &str, id: DeviceId) -> bool {
+        if self.find_device(id).is_some() {
+            return false;
+        }
+
+        let added = self
+            .rooms
+            .entry(room_name.to_owned())
+            .or_insert_with(|| Room::new(room_name))
+            .add_device(device_name);
+
+        if added {
+            _ = self
+                .device_ids
+                .insert((room_name.to_owned(), device_name.to_owned()), id);
+        }
+
+        added
+    }
+
+    /// Returns identifier of device of the room
+    pub fn device_id(&self, room_name: &str, device_name: &str) -> Option<DeviceId> {
+        self.device_ids
+            .get(&(room_name.to_owned(), device_name.to_owned()))
+            .copied()
+    }
+
+    /// Returns room name and device name of device by its identifier
+    pub fn find_device(&self, id: DeviceId) -> Option<(&str, &str)> {
+        self.device_ids
+            .iter()
+            .find(|(_, device_id)| **device_id == id)
+            .map(|((room, device), _)| (room.as_str(), device.as_str()))
+    }
+
+    /// Returns identifiers of all devices
+    pub fn get_device_ids(&self) -> &DeviceIdList {
+        &self.device_ids
+    }
+
+    /// Removes device from the room of the smart house with its binding.
+    /// Returns `true` if device was removed,
+    /// returns `false` otherwise.
+    pub fn remove_device(&mut self, room_name: &str, device_name: &str) -> bool {
+        let removed = self
+            .rooms
+            .get_mut(room_name)
+            .is_some_and(|room| room.remove_device(device_name));
+
+        if removed {
+            let key = (room_name.to_owned(), device_name.to_owned());
+            _ = self.device_ids.remove(&key);
+            _ = self.bindings.remove(&key);
+            self.retain_group_members(|room, device| room != room_name || device != device_name);
+            self.retain_scene_states(|room, device| room != room_name || device != device_name);
+            self.retain_priorities(|room, device| room != room_name || device != device_name);
+        }
+
+        removed
+    }
+
+    /// Renames room keeping bindings of its devices
+    /// and references to them from groups, scenes and power budget.
+    /// Returns error if room isn't found or room with `new_name` exists.
+    pub fn rename_room(&mut self, room_name: &str, new_name: &str) -> errors::Result<()> {
+        if !self.rooms.contains_key(room_name) {
+            return Err(room_not_found(room_name));
+        }
+
+        if room_name == new_name {
+            return Ok(());
+        }
+
+        if self.rooms.contains_key(new_name) {
+            return Err(RoomExistsError {
+                room_name: new_name.to_owned(),
+            });
+        }
+
+        let mut room = self.rooms.remove(room_name).expect("Room exists");
+        room.set_name(new_name);
+        _ = self.rooms.insert(new_name.to_owned(), room);
+
+        self.rename_references(|room, device| {
+            (room == room_name).then(|| (new_name.to_owned(), device.to_owned()))
+        });
+
+        Ok(())
+    }
+
+    /// Renames device of the room keeping its binding
+    /// and references to it from groups, scenes and power budget.
+    /// Returns error if device isn't found or room contains device with `new_name`.
+    pub fn rename_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        new_name: &str,
+    ) -> errors::Result<()> {
+        self.relocate_device(room_name, device_name, room_name, new_name)
+    }
+
+    /// Moves device to another room keeping its binding
+    /// and references to it from groups, scenes and power budget.
+    /// Returns error if device or new room isn't found
+    /// or new room contains device with the same name.
+    pub fn move_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        new_room_name: &str,
+    ) -> errors::Result<()> {
+        self.relocate_device(room_name, device_name, new_room_name, device_name)
+    }
+
+    /// Moves device to `new_room_name` under `new_device_name`
+    /// keeping its binding and references to it.
+    /// Returns error if device or new room isn't found
+    /// or new room contains device with the new name.
+    pub fn relocate_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        new_room_name: &str,
+        new_device_name: &str,
+    ) -> errors::Result<()> {
+        if !self
+            .devices(room_name)
+            .ok_or_else(|| room_not_found(room_name))?
+            .contains(device_name)
+        {
+            return Err(DeviceNotFoundError {
+                device_name: device_name.to_owned(),
+                room_name: room_name.to_owned(),
+            });
+        }
+
+        let target = self
+            .devices(new_room_name)
+            .ok_or_else(|| room_not_found(new_room_name))?;
+
+        if (room_name, device_name) == (new_room_name, new_device_name) {
+            return Ok(());
+        }
+
+        if target.contains(new_device_name) {
+            return Err(DeviceExistsError {
+                device_name: new_device_name.to_owned(),
+                room_name: new_room_name.to_owned(),
+            });
+        }
+
+        _ = self
+            .rooms
+            .get_mut(room_name)
+            .expect("Room exists")
+            .remove_device(device_name);
+        _ = self
+            .rooms
+            .get_mut(new_room_name)
+            .expect("Room exists")
+            .add_device(new_device_name);
+
+        self.rename_references(|room, device| {
+            (room == room_name && device == device_name)
+                .then(|| (new_room_name.to_owned(), new_device_name.to_owned()))
+        });
+
+        Ok(())
+    }
+
+    /// Replaces room and device names of identifiers, bindings, group members,
+    /// scene states and switch priorities for which `rename` returns new names
+    fn rename_references(&mut self, rename: impl Fn(&str, &str) -> Option<(String, String)>) {
+        let rename_key = |key: (String, String)| rename(&key.0, &key.1).unwrap_or(key);
+
+        self.device_ids = mem::take(&mut self.device_ids)
+            .into_iter()
+            .map(|(key, id)| (rename_key(key), id))
+            .collect();
+
+        self.bindings = mem::take(&mut self.bindings)
+            .into_iter()
+            .map(|(key, binding)| (rename_key(key), binding))
+            .collect();
+
+        for members in self.groups.values_mut() {
+            *members = mem::take(members).into_iter().map(rename_key).collect();
+        }
+
+        for state in self.scenes.values_mut().flat_map(|scene| &mut scene.states) {
+            if let Some((room, device)) = rename(&state.room, &state.device) {
+                (state.room, state.device) = (room, device);
+            }
+        }
+
+        if let Some(budget) = &mut self.power_budget {
+            for switch in &mut budget.priorities {
+                if let Some((room, device)) = rename(&switch.room, &switch.device) {
+                    (switch.room, switch.device) = (room, device);
+                }
+            }
+        }
+    }
+
+    /// Binds device of the room to the address it is reached at.
+    /// Returns `true` if device was bound,
+    /// returns `false` if room doesn't contain the device.
+    pub fn bind_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        binding: DeviceBinding,
+    ) -> bool {
+        if !self
+            .devices(room_name)
+            .is_some_and(|devices| devices.contains(device_name))
+        {
+            return false;
+        }
+
+        _ = self
+            .bindings
+            .insert((room_name.to_owned(), device_name.to_owned()), binding);
 
         true
     }
 
+    /// Returns binding of the device of the room
+    pub fn binding(&self, room_name: &str, device_name: &str) -> Option<&DeviceBinding> {
+        self.bindings
+            .get(&(room_name.to_owned(), device_name.to_owned()))
+    }
+
+    /// Returns bindings of all bound devices
+    pub fn get_bindings(&self) -> &BindingList {
+        &self.bindings
+    }
+
     /// Returns names of devices for the room of smart house by room's name
     pub fn devices(&self, room_name: &str) -> Option<&DeviceList> {
-        self.rooms
-            .get(&room_name.to_owned())
-            .map(|r| r.get_devices())
+        self.rooms.get(room_name).map(|r| r.get_devices())
+    }
+
+    /// Adds scene to the smart house.
+    /// Returns `true` if scene was added,
+    /// returns `false` if scene with the same name exists.
+    pub fn add_scene(&mut self, scene: Scene) -> bool {
+        if self.scenes.contains_key(&scene.name) {
+            return false;
+        }
+
+        _ = self.scenes.insert(scene.name.clone(), scene);
+
+        true
+    }
+
+    /// Removes scene from the smart house.
+    /// Returns `true` if scene was removed,
+    /// returns `false` otherwise.
+    pub fn remove_scene(&mut self, scene_name: &str) -> bool {
+        self.scenes.remove(scene_name).is_some()
+    }
+
+    /// Returns scene by its name
+    pub fn scene(&self, scene_name: &str) -> Option<&Scene> {
+        self.scenes.get(scene_name)
+    }
+
+    /// Returns list of scenes for the smart house
+    pub fn get_scenes(&self) -> &SceneList {
+        &self.scenes
+    }
+
+    /// Keeps only states of scenes for which `keep` returns `true`,
+    /// scenes left empty stay until they are removed
+    fn retain_scene_states(&mut self, keep: impl Fn(&str, &str) -> bool) {
+        for scene in self.scenes.values_mut() {
+            scene
+                .states
+                .retain(|state| keep(&state.room, &state.device));
+        }
+    }
+
+    /// Adds device of the room to the group, the group is created if it doesn't exist.
+    /// Returns `true` if device was added,
+    /// returns `false` if room doesn't contain the device or group already contains it.
+    pub fn add_to_group(&mut self, group_name: &str, room_name: &str, device_name: &str) -> bool {
+        if !self
+            .devices(room_name)
+            .is_some_and(|devices| devices.contains(device_name))
+        {
+            return false;
+        }
+
+        self.groups
+            .entry(group_name.to_owned())
+            .or_default()
+            .insert((room_name.to_owned(), device_name.to_owned()))
+    }
+
+    /// Removes device of the room from the group, empty group is removed.
+    /// Returns `true` if device was removed,
+    /// returns `false` otherwise.
+    pub fn remove_from_group(
+        &mut self,
+        group_name: &str,
+        room_name: &str,
+        device_name: &str,
+    ) -> bool {
+        let Some(members) = self.groups.get_mut(group_name) else {
+            return false;
+        };
+
+        let removed = members.remove(&(room_name.to_owned(), device_name.to_owned()));
+
+        if members.is_empty() {
+            _ = self.groups.remove(group_name);
+        }
+
+        removed
+    }
+
+    /// Removes group, its devices stay in their rooms.
+    /// Returns `true` if group was removed,
+    /// returns `false` otherwise.
+    pub fn remove_group(&mut self, group_name: &str) -> bool {
+        self.groups.remove(group_name).is_some()
+    }
+
+    /// Returns devices of the group by group name
+    pub fn group(&self, group_name: &str) -> Option<&GroupMembers> {
+        self.groups.get(group_name)
+    }
+
+    /// Returns list of groups for the smart house
+    pub fn get_groups(&self) -> &GroupList {
+        &self.groups
+    }
+
+    /// Returns names of groups containing device of the room
+    pub fn groups_of(&self, room_name: &str, device_name: &str) -> Vec<&str> {
+        let key = (room_name.to_owned(), device_name.to_owned());
+
+        self.groups
+            .iter()
+            .filter(|(_, members)| members.contains(&key))
+            .map(|(group_name, _)| group_name.as_str())
+            .collect()
+    }
+
+    /// Keeps only group members for which `keep` returns `true`,
+    /// groups left empty are removed
+    fn retain_group_members(&mut self, keep: impl Fn(&str, &str) -> bool) {
+        for members in self.groups.values_mut() {
+            members.retain(|(room, device)| keep(room, device));
+        }
+
+        self.groups.retain(|_, members| !members.is_empty());
+    }
+
+    /// Sets limit of total power of power switches, `None` removes the limit
+    pub fn set_power_budget(&mut self, budget: Option<PowerBudget>) {
+        self.power_budget = budget;
+    }
+
+    /// Returns limit of total power of power switches
+    pub fn power_budget(&self) -> Option<&PowerBudget> {
+        self.power_budget.as_ref()
+    }
+
+    /// Keeps only priorities of switches for which `keep` returns `true`
+    fn retain_priorities(&mut self, keep: impl Fn(&str, &str) -> bool) {
+        if let Some(budget) = &mut self.power_budget {
+            budget
+                .priorities
+                .retain(|switch| keep(&switch.room, &switch.device));
+        }
     }
 
     /// Returns report about devices of the smart house
@@ -116,6 +549,12 @@ impl SmartHouse {
     }
 }
 
+fn room_not_found(room_name: &str) -> errors::Error {
+    RoomNotFoundError {
+        room_name: room_name.to_owned(),
+    }
+}
+
 /// Describes contract for provider of info about devices
 pub trait DeviceInfoProvider {
     /// Returns description of device state by room name and device name
@@ -137,6 +576,212 @@ mod tests {
         assert!(devices.contains("switch1"));
     }
 
+    #[test]
+    fn test_bind_device() {
+        let mut smart_house = SmartHouse::generate();
+        let binding = DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:53453".parse().unwrap(),
+        };
+
+        assert!(smart_house.bind_device("Bathroom", "switch1", binding.clone()));
+        assert!(!smart_house.bind_device("Bathroom", "switch2", binding.clone()));
+        assert_eq!(smart_house.binding("Bathroom", "switch1"), Some(&binding));
+
+        smart_house.remove_room("Bathroom");
+
+        assert!(smart_house.get_bindings().is_empty());
+    }
+
+    #[test]
+    fn test_groups_span_rooms() {
+        let mut smart_house = SmartHouse::generate();
+
+        assert!(smart_house.add_to_group("heaters", "Bathroom", "switch1"));
+        assert!(smart_house.add_to_group("heaters", "Dinning room", "switch1"));
+        assert!(smart_house.add_to_group("always-on", "Dinning room", "therm1"));
+        assert!(!smart_house.add_to_group("heaters", "Bathroom", "switch1"));
+        assert!(!smart_house.add_to_group("heaters", "Kitchen", "switch1"));
+
+        assert_eq!(smart_house.group("heaters").unwrap().len(), 2);
+        assert_eq!(
+            smart_house.groups_of("Dinning room", "switch1"),
+            vec!["heaters"]
+        );
+
+        smart_house.remove_device("Dinning room", "switch1");
+        assert_eq!(smart_house.group("heaters").unwrap().len(), 1);
+
+        smart_house.remove_room("Dinning room");
+        assert_eq!(smart_house.group("always-on"), None);
+
+        assert!(smart_house.remove_from_group("heaters", "Bathroom", "switch1"));
+        assert!(smart_house.get_groups().is_empty());
+    }
+
+    fn house_with_references() -> SmartHouse {
+        let mut smart_house = SmartHouse::generate();
+        let binding = DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:53453".parse().unwrap(),
+        };
+        smart_house.bind_device("Bathroom", "switch1", binding);
+        smart_house.add_to_group("heaters", "Bathroom", "switch1");
+
+        let mut scene = Scene::new("Night");
+        scene.set_state("Bathroom", "switch1", false);
+        smart_house.add_scene(scene);
+
+        let mut budget = PowerBudget::new(3000.0);
+        budget.set_priority("Bathroom", "switch1", 5);
+        smart_house.set_power_budget(Some(budget));
+
+        smart_house
+    }
+
+    fn assert_references(smart_house: &SmartHouse, room_name: &str, device_name: &str) {
+        assert!(smart_house.binding(room_name, device_name).is_some());
+        assert_eq!(
+            smart_house.groups_of(room_name, device_name),
+            vec!["heaters"]
+        );
+
+        let state = &smart_house.scene("Night").unwrap().states[0];
+        assert_eq!(
+            (state.room.as_str(), state.device.as_str()),
+            (room_name, device_name)
+        );
+
+        let budget = smart_house.power_budget().unwrap();
+        assert_eq!(budget.priority(room_name, device_name), Some(5));
+    }
+
+    #[test]
+    fn test_remove_device_removes_references() {
+        let mut smart_house = house_with_references();
+        smart_house.add_to_group("heaters", "Dinning room", "switch1");
+        let mut budget = smart_house.power_budget().unwrap().clone();
+        budget.set_priority("Dinning room", "switch1", 1);
+        smart_house.set_power_budget(Some(budget));
+        let mut scene = Scene::new("Away");
+        scene.set_state("Dinning room", "switch1", false);
+        smart_house.add_scene(scene);
+
+        smart_house.remove_device("Bathroom", "switch1");
+
+        assert!(smart_house.groups_of("Bathroom", "switch1").is_empty());
+        assert!(smart_house.scene("Night").unwrap().states.is_empty());
+        let budget = smart_house.power_budget().unwrap();
+        assert_eq!(budget.priority("Bathroom", "switch1"), None);
+        assert_eq!(budget.priority("Dinning room", "switch1"), Some(1));
+        assert_eq!(smart_house.scene("Away").unwrap().states.len(), 1);
+
+        // Device added under the same name isn't referenced by the old scene
+        smart_house.add_device("Bathroom", "switch1");
+        assert!(smart_house.scene("Night").unwrap().states.is_empty());
+
+        smart_house.remove_room("Dinning room");
+
+        assert!(smart_house.get_groups().is_empty());
+        assert!(smart_house.scene("Away").unwrap().states.is_empty());
+        let budget = smart_house.power_budget().unwrap();
+        assert_eq!(budget.priority("Dinning room", "switch1"), None);
+    }
+
+    #[test]
+    fn test_rename_room_keeps_references() {
+        let mut smart_house = house_with_references();
+
+        smart_house.rename_room("Bathroom", "Washroom").unwrap();
+
+        assert!(smart_house.devices("Bathroom").is_none());
+        assert_eq!(smart_house.get_rooms()["Washroom"].get_name(), "Washroom");
+        assert_references(&smart_house, "Washroom", "switch1");
+        assert!(smart_house.binding("Dinning room", "switch1").is_none());
+
+        assert!(matches!(
+            smart_house.rename_room("Washroom", "Dinning room"),
+            Err(RoomExistsError { .. })
+        ));
+        assert!(matches!(
+            smart_house.rename_room("Bathroom", "Kitchen"),
+            Err(RoomNotFoundError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rename_device_keeps_references() {
+        let mut smart_house = house_with_references();
+
+        smart_house
+            .rename_device("Bathroom", "switch1", "heater")
+            .unwrap();
+
+        assert!(!smart_house.devices("Bathroom").unwrap().contains("switch1"));
+        assert_references(&smart_house, "Bathroom", "heater");
+
+        assert!(matches!(
+            smart_house.rename_device("Bathroom", "heater", "therm2"),
+            Err(DeviceExistsError { .. })
+        ));
+        assert!(matches!(
+            smart_house.rename_device("Bathroom", "switch1", "fan"),
+            Err(DeviceNotFoundError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_move_device_keeps_references() {
+        let mut smart_house = house_with_references();
+        smart_house.remove_device("Dinning room", "switch1");
+
+        smart_house
+            .move_device("Bathroom", "switch1", "Dinning room")
+            .unwrap();
+
+        assert!(!smart_house.devices("Bathroom").unwrap().contains("switch1"));
+        assert!(smart_house
+            .devices("Dinning room")
+            .unwrap()
+            .contains("switch1"));
+        assert_references(&smart_house, "Dinning room", "switch1");
+
+        assert!(matches!(
+            smart_house.move_device("Dinning room", "switch1", "Kitchen"),
+            Err(RoomNotFoundError { .. })
+        ));
+        smart_house.add_device("Bathroom", "switch1");
+        assert!(matches!(
+            smart_house.move_device("Dinning room", "switch1", "Bathroom"),
+            Err(DeviceExistsError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_device_ids_are_unique_and_stable() {
+        let mut smart_house = house_with_references();
+        let id = smart_house.device_id("Bathroom", "switch1").unwrap();
+
+        assert_ne!(smart_house.device_id("Dinning room", "switch1"), Some(id));
+        assert_eq!(smart_house.get_device_ids().len(), 4);
+        assert_eq!(smart_house.find_device(id), Some(("Bathroom", "switch1")));
+
+        smart_house
+            .relocate_device("Bathroom", "switch1", "Dinning room", "heater")
+            .unwrap();
+        smart_house.rename_room("Dinning room", "Hall").unwrap();
+        assert_eq!(smart_house.find_device(id), Some(("Hall", "heater")));
+        assert_eq!(smart_house.device_id("Hall", "heater"), Some(id));
+
+        assert!(!smart_house.add_device_with_id("Kitchen", "heater", id));
+        assert!(smart_house.devices("Kitchen").is_none());
+
+        smart_house.remove_device("Hall", "heater");
+        assert_eq!(smart_house.find_device(id), None);
+        assert!(smart_house.add_device_with_id("Kitchen", "heater", id));
+
+        smart_house.remove_room("Kitchen");
+        assert_eq!(smart_house.find_device(id), None);
+    }
+
     #[test]
     fn test_get_devices_panics_if_room_name_not_found() {
         let smart_house = SmartHouse::generate();
@@ -0,0 +1,27 @@
+use std::net::SocketAddr;
+
+use discovery::announcement::{Announcement, DeviceKind};
+use serde::{Deserialize, Serialize};
+
+/// Describes how the smart house reaches a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceBinding {
+    /// Power switch controlled over TCP at `address`
+    PowerSwitch { address: SocketAddr },
+    /// Thermometer answering requests of readings at `sender`
+    Thermometer { sender: SocketAddr },
+}
+
+impl From<&Announcement> for DeviceBinding {
+    fn from(announcement: &Announcement) -> Self {
+        match announcement.kind {
+            DeviceKind::PowerSwitch => DeviceBinding::PowerSwitch {
+                address: announcement.endpoint,
+            },
+            DeviceKind::Thermometer => DeviceBinding::Thermometer {
+                sender: announcement.endpoint,
+            },
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors, files};
+
+/// Describes power switch which may be turned off when the budget is exceeded.
+/// Switches with lower priority are turned off first and restored last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchPriority {
+    pub room: String,
+    pub device: String,
+    pub priority: u32,
+}
+
+/// Describes limit of total power of all power switches of the house
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerBudget {
+    /// Limit of total power in watts
+    pub limit: f64,
+    /// Headroom in watts left after switch is restored,
+    /// so it isn't turned off again right away
+    #[serde(default)]
+    pub margin: f64,
+    /// Switches which may be turned off, others are never turned off
+    #[serde(default)]
+    pub priorities: Vec<SwitchPriority>,
+}
+
+impl PowerBudget {
+    /// Creates budget with given `limit` which doesn't turn off any switch
+    pub fn new(limit: f64) -> Self {
+        Self {
+            limit,
+            margin: 0.0,
+            priorities: Vec::new(),
+        }
+    }
+
+    /// Reads budget from JSON file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+
+    /// Sets priority of the switch, replacing the previous one
+    pub fn set_priority(&mut self, room_name: &str, device_name: &str, priority: u32) {
+        self.priorities
+            .retain(|switch| switch.room != room_name || switch.device != device_name);
+        self.priorities.push(SwitchPriority {
+            room: room_name.to_owned(),
+            device: device_name.to_owned(),
+            priority,
+        });
+    }
+
+    /// Returns priority of the switch, `None` if it is never turned off
+    pub fn priority(&self, room_name: &str, device_name: &str) -> Option<u32> {
+        self.priorities
+            .iter()
+            .find(|switch| switch.room == room_name && switch.device == device_name)
+            .map(|switch| switch.priority)
+    }
+}
@@ -1,10 +1,12 @@
 use std::collections::BTreeSet;
 
+use serde::Serialize;
+
 /// Describes list of devices in the room
 pub type DeviceList = BTreeSet<String>;
 
 /// Describes room of the smart house
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Room {
     name: String,
     devices: DeviceList,
@@ -24,6 +26,11 @@ impl Room {
         &self.name
     }
 
+    /// Sets name to the room
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_owned();
+    }
+
     /// Returns list of devices in the room
     pub fn get_devices(&self) -> &DeviceList {
         &self.devices
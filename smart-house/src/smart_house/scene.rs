@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Describes desired state of power switch in the scene
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneState {
+    pub room: String,
+    pub device: String,
+    pub enabled: bool,
+}
+
+/// Describes named set of desired states of power switches, e.g. "Away"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub states: Vec<SceneState>,
+}
+
+impl Scene {
+    /// Creates new empty scene with given name
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            states: Vec::new(),
+        }
+    }
+
+    /// Sets desired state of the device, replacing the previous one
+    pub fn set_state(&mut self, room_name: &str, device_name: &str, enabled: bool) {
+        self.states
+            .retain(|state| state.room != room_name || state.device != device_name);
+        self.states.push(SceneState {
+            room: room_name.to_owned(),
+            device: device_name.to_owned(),
+            enabled,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_state_replaces_previous() {
+        let mut scene = Scene::new("Away");
+
+        scene.set_state("Bathroom", "switch1", true);
+        scene.set_state("Kitchen", "switch1", false);
+        scene.set_state("Bathroom", "switch1", false);
+
+        assert_eq!(scene.states.len(), 2);
+        assert_eq!(scene.states[1].room, "Bathroom");
+        assert!(!scene.states[1].enabled);
+    }
+}
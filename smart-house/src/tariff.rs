@@ -0,0 +1,209 @@
+//! Module describes tariffs of electricity and cost of energy
+//! consumed by power switches, e.g. day/night tariff
+//!
+//! ```json
+//! {
+//!     "currency": "EUR",
+//!     "timezone": "Europe/Berlin",
+//!     "kind": "day_night",
+//!     "day_price": 0.32,
+//!     "night_price": 0.21,
+//!     "night_start": "22:00:00",
+//!     "night_end": "06:00:00"
+//! }
+//! ```
+//!
+//! or time-of-use bands, price outside of bands is `default_price`
+//!
+//! ```json
+//! {
+//!     "currency": "EUR",
+//!     "kind": "time_of_use",
+//!     "default_price": 0.25,
+//!     "bands": [
+//!         { "days": ["Mon", "Tue", "Wed", "Thu", "Fri"], "from": "17:00:00", "to": "20:00:00", "price": 0.45 },
+//!         { "from": "00:00:00", "to": "07:00:00", "price": 0.18 }
+//!     ]
+//! }
+//! ```
+//!
+//! Prices are given per kWh.
+
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors, files};
+
+pub use self::cost::{costs, CostReport, RoomCost, SwitchCost};
+
+mod cost;
+
+/// Describes band of time-of-use tariff. Band ends at the next day
+/// when `to` is not after `from`, e.g. from 22:00 to 06:00.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Band {
+    /// Days the band starts at, all days if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+    pub price: f64,
+}
+
+impl Band {
+    fn contains(&self, weekday: Weekday, time: NaiveTime) -> bool {
+        let starts_on = |weekday| self.days.is_empty() || self.days.contains(&weekday);
+
+        if self.from < self.to {
+            starts_on(weekday) && self.from <= time && time < self.to
+        } else {
+            (starts_on(weekday) && time >= self.from)
+                || (starts_on(weekday.pred()) && time < self.to)
+        }
+    }
+}
+
+/// Describes how price of energy depends on time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Pricing {
+    /// The same price at any time
+    Flat { price: f64 },
+    /// Lower price at night
+    DayNight {
+        day_price: f64,
+        night_price: f64,
+        night_start: NaiveTime,
+        night_end: NaiveTime,
+    },
+    /// Prices of bands, the first matching band wins
+    TimeOfUse {
+        bands: Vec<Band>,
+        default_price: f64,
+    },
+}
+
+/// Describes tariff of electricity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tariff {
+    #[serde(default)]
+    pub currency: String,
+    /// Time zone bands of the tariff are given in
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    #[serde(flatten)]
+    pub pricing: Pricing,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+impl Tariff {
+    /// Creates tariff with the same `price` at any time
+    pub fn flat(price: f64, currency: &str) -> Self {
+        Self {
+            currency: currency.to_owned(),
+            timezone: default_timezone(),
+            pricing: Pricing::Flat { price },
+        }
+    }
+
+    /// Reads tariff from JSON file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> errors::Result<Self> {
+        files::read_json(path)
+    }
+
+    /// Returns price of kWh at `time`
+    pub fn price_at(&self, time: DateTime<Utc>) -> f64 {
+        let local = time.with_timezone(&self.timezone);
+        let (weekday, time) = (local.weekday(), local.time());
+
+        match &self.pricing {
+            Pricing::Flat { price } => *price,
+            Pricing::DayNight {
+                day_price,
+                night_price,
+                night_start,
+                night_end,
+            } => {
+                let night = Band {
+                    days: Vec::new(),
+                    from: *night_start,
+                    to: *night_end,
+                    price: *night_price,
+                };
+
+                if night.contains(weekday, time) {
+                    *night_price
+                } else {
+                    *day_price
+                }
+            }
+            Pricing::TimeOfUse {
+                bands,
+                default_price,
+            } => bands
+                .iter()
+                .find(|band| band.contains(weekday, time))
+                .map_or(*default_price, |band| band.price),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_day_night_in_timezone() {
+        let tariff: Tariff = serde_json::from_str(
+            r#"{
+                "currency": "EUR",
+                "timezone": "Europe/Berlin",
+                "kind": "day_night",
+                "day_price": 0.3,
+                "night_price": 0.2,
+                "night_start": "22:00:00",
+                "night_end": "06:00:00"
+            }"#,
+        )
+        .unwrap();
+
+        // 23:30 and 05:30 in Berlin in summer
+        assert_eq!(tariff.price_at(utc("2024-06-21T21:30:00Z")), 0.2);
+        assert_eq!(tariff.price_at(utc("2024-06-21T03:30:00Z")), 0.2);
+        assert_eq!(tariff.price_at(utc("2024-06-21T04:00:00Z")), 0.3);
+        assert_eq!(tariff.price_at(utc("2024-06-21T12:00:00Z")), 0.3);
+    }
+
+    #[test]
+    fn test_time_of_use_bands() {
+        let tariff: Tariff = serde_json::from_str(
+            r#"{
+                "kind": "time_of_use",
+                "default_price": 0.25,
+                "bands": [
+                    { "days": ["Mon", "Tue", "Wed", "Thu", "Fri"], "from": "17:00:00", "to": "20:00:00", "price": 0.45 },
+                    { "days": ["Sat"], "from": "23:00:00", "to": "07:00:00", "price": 0.1 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        // 2024-06-21 is Friday
+        assert_eq!(tariff.price_at(utc("2024-06-21T18:00:00Z")), 0.45);
+        assert_eq!(tariff.price_at(utc("2024-06-22T18:00:00Z")), 0.25);
+        // Saturday night band lasts until Sunday morning
+        assert_eq!(tariff.price_at(utc("2024-06-22T23:30:00Z")), 0.1);
+        assert_eq!(tariff.price_at(utc("2024-06-23T06:00:00Z")), 0.1);
+        assert_eq!(tariff.price_at(utc("2024-06-24T06:00:00Z")), 0.25);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use chrono::DateTime;
+use serde::Serialize;
+
+use super::Tariff;
+use crate::{
+    errors,
+    recorder::{self, Query, SampleStore, Series},
+    smart_house::DeviceIdList,
+};
+
+const MILLIS_PER_HOUR: f64 = 3_600_000.0;
+
+/// Describes energy and cost of power switch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SwitchCost {
+    pub device: String,
+    /// Energy in kWh
+    pub energy: f64,
+    pub cost: f64,
+}
+
+/// Describes energy and cost of power switches of the room
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoomCost {
+    pub room: String,
+    /// Energy in kWh
+    pub energy: f64,
+    pub cost: f64,
+    pub switches: Vec<SwitchCost>,
+}
+
+/// Describes energy and cost of power switches of the house over period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostReport {
+    /// Start of the period in milliseconds since Unix epoch, inclusive
+    pub from: u64,
+    /// End of the period in milliseconds since Unix epoch, exclusive
+    pub to: u64,
+    pub currency: String,
+    /// Energy in kWh
+    pub energy: f64,
+    pub cost: f64,
+    pub rooms: Vec<RoomCost>,
+}
+
+impl CostReport {
+    /// Computes cost of recorded `series` of `energy` counters of switches,
+    /// `power` is integrated for switches without recorded energy.
+    /// Energy consumed between two samples is charged by price
+    /// in the middle of the interval. Series are grouped by room and device,
+    /// so they should be named by current names, see [`recorder::follow_names`].
+    pub fn new(series: &[Series], tariff: &Tariff, from: u64, to: u64) -> Self {
+        let with_energy: BTreeSet<_> = series
+            .iter()
+            .filter(|series| series.metric == "energy")
+            .map(|series| (series.room.as_str(), series.device.as_str()))
+            .collect();
+        let mut switches: BTreeMap<(&str, &str), (f64, f64)> = BTreeMap::new();
+
+        for series in series {
+            let key = (series.room.as_str(), series.device.as_str());
+
+            match series.metric.as_str() {
+                "energy" => {}
+                "power" if !with_energy.contains(&key) => {}
+                _ => continue,
+            }
+
+            let (energy, cost) = switches.entry(key).or_default();
+
+            for pair in series.points.windows(2) {
+                let ((start, first), (end, second)) = (pair[0], pair[1]);
+                let wh = match series.metric.as_str() {
+                    // Counter starts from zero when switch restarts
+                    "energy" if second < first => second,
+                    "energy" => second - first,
+                    _ => first * (end - start) as f64 / MILLIS_PER_HOUR,
+                };
+                let price = DateTime::from_timestamp_millis(((start + end) / 2) as i64)
+                    .map_or(0.0, |time| tariff.price_at(time));
+
+                *energy += wh / 1000.0;
+                *cost += wh / 1000.0 * price;
+            }
+        }
+
+        let mut report = Self {
+            from,
+            to,
+            currency: tariff.currency.clone(),
+            energy: 0.0,
+            cost: 0.0,
+            rooms: Vec::new(),
+        };
+
+        for ((room, device), (energy, cost)) in switches {
+            if report.rooms.last().is_none_or(|last| last.room != room) {
+                report.rooms.push(RoomCost {
+                    room: room.to_owned(),
+                    energy: 0.0,
+                    cost: 0.0,
+                    switches: Vec::new(),
+                });
+            }
+
+            let room_cost = report.rooms.last_mut().expect("Room was added");
+            room_cost.energy += energy;
+            room_cost.cost += cost;
+            room_cost.switches.push(SwitchCost {
+                device: device.to_owned(),
+                energy,
+                cost,
+            });
+
+            report.energy += energy;
+            report.cost += cost;
+        }
+
+        report
+    }
+
+    /// Returns cost of switches, rooms and the house in CSV with header
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("level,room,device,energy_kwh,cost,currency\n");
+        let mut row = |level: &str, room: &str, device: &str, energy: f64, cost: f64| {
+            _ = writeln!(
+                csv,
+                "{level},{},{},{energy:.3},{cost:.2},{}",
+                escape(room),
+                escape(device),
+                escape(&self.currency)
+            );
+        };
+
+        for room in &self.rooms {
+            for switch in &room.switches {
+                row(
+                    "switch",
+                    &room.room,
+                    &switch.device,
+                    switch.energy,
+                    switch.cost,
+                );
+            }
+            row("room", &room.room, "", room.energy, room.cost);
+        }
+        row("house", "", "", self.energy, self.cost);
+
+        csv
+    }
+}
+
+/// Quotes CSV field if needed
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Computes cost of power switches recorded in the `store` from `from` to `to`,
+/// switches of `devices` are named by their current names
+pub fn costs(
+    store: &SampleStore,
+    devices: &DeviceIdList,
+    tariff: &Tariff,
+    from: u64,
+    to: u64,
+) -> errors::Result<CostReport> {
+    let mut series = Vec::new();
+
+    for metric in ["energy", "power"] {
+        series.extend(store.query(&Query {
+            metric: Some(metric.to_owned()),
+            from: Some(from),
+            to: Some(to),
+            ..Query::default()
+        })?);
+    }
+
+    recorder::follow_names(&mut series, devices);
+
+    Ok(CostReport::new(&series, tariff, from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(room: &str, device: &str, metric: &str, points: &[(u64, f64)]) -> Series {
+        Series {
+            room: room.to_owned(),
+            device: device.to_owned(),
+            id: None,
+            metric: metric.to_owned(),
+            points: points.to_vec(),
+        }
+    }
+
+    const HOUR: u64 = 3_600_000;
+
+    #[test]
+    fn test_cost_per_switch_room_and_house() {
+        let tariff = Tariff::flat(0.5, "EUR");
+        let series = [
+            // Counter was reset after 2000 Wh
+            series(
+                "Hall",
+                "heater",
+                "energy",
+                &[(0, 1000.0), (HOUR, 2000.0), (2 * HOUR, 500.0)],
+            ),
+            // Power is ignored when energy is recorded
+            series(
+                "Hall",
+                "heater",
+                "power",
+                &[(0, 5000.0), (2 * HOUR, 5000.0)],
+            ),
+            series(
+                "Hall",
+                "lamp",
+                "power",
+                &[(0, 100.0), (HOUR, 0.0), (2 * HOUR, 0.0)],
+            ),
+            series("Kitchen", "kettle", "energy", &[(0, 0.0), (HOUR, 2000.0)]),
+        ];
+
+        let report = CostReport::new(&series, &tariff, 0, 2 * HOUR);
+
+        assert_eq!(report.rooms.len(), 2);
+        assert_eq!(report.rooms[0].switches[0].energy, 1.5);
+        assert_eq!(report.rooms[0].switches[0].cost, 0.75);
+        assert_eq!(report.rooms[0].switches[1].energy, 0.1);
+        assert!((report.rooms[0].energy - 1.6).abs() < 1e-9);
+        assert_eq!(report.rooms[1].cost, 1.0);
+        assert!((report.cost - 1.8).abs() < 1e-9);
+
+        assert_eq!(
+            report.to_csv(),
+            "level,room,device,energy_kwh,cost,currency\n\
+             switch,Hall,heater,1.500,0.75,EUR\n\
+             switch,Hall,lamp,0.100,0.05,EUR\n\
+             room,Hall,,1.600,0.80,EUR\n\
+             switch,Kitchen,kettle,2.000,1.00,EUR\n\
+             room,Kitchen,,2.000,1.00,EUR\n\
+             house,,,3.600,1.80,EUR\n"
+        );
+    }
+
+    #[test]
+    fn test_cost_by_time_of_use() {
+        let tariff: Tariff = serde_json::from_str(
+            r#"{
+                "kind": "day_night",
+                "day_price": 0.3,
+                "night_price": 0.1,
+                "night_start": "22:00:00",
+                "night_end": "06:00:00"
+            }"#,
+        )
+        .unwrap();
+        // From 1970-01-01 00:00 to 12:00 UTC, 1 kWh per hour
+        let points: Vec<_> = (0..=12)
+            .map(|hour| (hour * HOUR, hour as f64 * 1000.0))
+            .collect();
+
+        let report = CostReport::new(
+            &[series("Hall", "heater", "energy", &points)],
+            &tariff,
+            0,
+            12 * HOUR,
+        );
+
+        assert!((report.cost - (6.0 * 0.1 + 6.0 * 0.3)).abs() < 1e-9);
+        assert_eq!(escape("Dinning, big"), "\"Dinning, big\"");
+    }
+}
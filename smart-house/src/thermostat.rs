@@ -0,0 +1,489 @@
+//! Module describes thermostat which keeps temperature of the room
+//! by turning on and off heater or cooler plugged into power switch
+
+use std::{
+    fmt,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Datelike, Local, TimeZone};
+use power_switch::power_switch::{PowerSwitch, SwitchState};
+use serde::{Deserialize, Serialize};
+use thermometer::{metrics::Metric, thermometer::Thermometer};
+
+use crate::{
+    errors,
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::DeviceBinding,
+};
+
+pub use self::schedule::{Setpoint, WeeklySchedule};
+
+mod schedule;
+
+/// Describes reading of temperature sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Temperature in degrees Celsius
+    pub temperature: f64,
+    /// Time the reading was received
+    pub time: SystemTime,
+}
+
+/// Describes source of temperature for thermostat
+pub trait TemperatureSensor: Send {
+    /// Returns the latest reading, `None` if there is no reading
+    fn reading(&self) -> Option<Reading>;
+}
+
+impl TemperatureSensor for Thermometer {
+    fn reading(&self) -> Option<Reading> {
+        Some(Reading {
+            temperature: self.metric(Metric::Temperature)?,
+            time: self.last_update()?,
+        })
+    }
+}
+
+/// Describes power switch controlled by thermostat
+pub trait Actuator: Send {
+    /// Returns `true` if the switch is on
+    fn is_enabled(&mut self) -> errors::Result<bool>;
+
+    /// Turns the switch on or off
+    fn turn(&mut self, enabled: bool) -> errors::Result<()>;
+}
+
+/// Local power switch, e.g. served by [`power_switch::server::Server`]
+impl Actuator for Arc<Mutex<PowerSwitch>> {
+    fn is_enabled(&mut self) -> errors::Result<bool> {
+        Ok(self.lock().unwrap().is_enabled())
+    }
+
+    fn turn(&mut self, enabled: bool) -> errors::Result<()> {
+        let state = if enabled {
+            SwitchState::On
+        } else {
+            SwitchState::Off
+        };
+        self.lock().unwrap().turn(state);
+        Ok(())
+    }
+}
+
+/// Describes power switch reached over network
+pub struct RemoteSwitch {
+    binding: DeviceBinding,
+    registry: Arc<DeviceRegistry>,
+}
+
+impl RemoteSwitch {
+    /// Creates switch served at `address` and reached through `registry`
+    pub fn new(address: SocketAddr, registry: Arc<DeviceRegistry>) -> Self {
+        Self {
+            binding: DeviceBinding::PowerSwitch { address },
+            registry,
+        }
+    }
+}
+
+impl Actuator for RemoteSwitch {
+    fn is_enabled(&mut self) -> errors::Result<bool> {
+        match self.registry.state(&self.binding)? {
+            DeviceState::PowerSwitch { enabled, .. } => Ok(enabled),
+            DeviceState::Thermometer { .. } => Err(errors::Error::UnsupportedCommandError),
+        }
+    }
+
+    fn turn(&mut self, enabled: bool) -> errors::Result<()> {
+        self.registry.turn(&self.binding, enabled).map(|_| ())
+    }
+}
+
+/// Describes mode of thermostat
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermostatMode {
+    /// Switch is kept off
+    #[default]
+    Off,
+    /// Switch heats the room up to scheduled target
+    Heat,
+    /// Switch cools the room down to scheduled target
+    Cool,
+    /// Switch heats the room up to eco target ignoring schedule
+    Eco,
+}
+
+impl FromStr for ThermostatMode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "heat" => Ok(Self::Heat),
+            "cool" => Ok(Self::Cool),
+            "eco" => Ok(Self::Eco),
+            _ => Err("Expected one of modes: off, heat, cool, eco"),
+        }
+    }
+}
+
+impl fmt::Display for ThermostatMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Heat => "heat",
+            Self::Cool => "cool",
+            Self::Eco => "eco",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Describes settings of thermostat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermostatSettings {
+    pub mode: ThermostatMode,
+    /// Target temperature in degrees Celsius when schedule is empty
+    pub target: f64,
+    /// Width of band around target where switch keeps its state
+    pub deadband: f64,
+    /// Target temperature in eco mode
+    pub eco_target: f64,
+    pub schedule: WeeklySchedule,
+    /// Age of reading in seconds after which thermostat refuses to act
+    pub max_age: u64,
+    /// Keep the switch in its state on stale reading,
+    /// by default the switch is turned off
+    pub hold_on_stale: bool,
+}
+
+impl Default for ThermostatSettings {
+    fn default() -> Self {
+        Self {
+            mode: ThermostatMode::default(),
+            target: 21.0,
+            deadband: 1.0,
+            eco_target: 16.0,
+            schedule: WeeklySchedule::default(),
+            max_age: 60,
+            hold_on_stale: false,
+        }
+    }
+}
+
+impl ThermostatSettings {
+    /// Returns target temperature at `now`, `None` in off mode
+    pub fn setpoint<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<f64> {
+        match self.mode {
+            ThermostatMode::Off => None,
+            ThermostatMode::Eco => Some(self.eco_target),
+            ThermostatMode::Heat | ThermostatMode::Cool => Some(
+                self.schedule
+                    .target_at(now.weekday(), now.time())
+                    .unwrap_or(self.target),
+            ),
+        }
+    }
+}
+
+/// Describes state of thermostat after the last control step
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ThermostatStatus {
+    pub mode: ThermostatMode,
+    /// Current target temperature
+    pub setpoint: Option<f64>,
+    /// The latest temperature
+    pub temperature: Option<f64>,
+    /// State of the switch, `None` if it is unknown
+    pub enabled: Option<bool>,
+    /// `true` if thermostat refused to act on stale reading
+    /// and turned the switch off or kept its state
+    pub stale: bool,
+    /// Error of reaching the switch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Performs single control step: turns the switch on or off according to
+/// `reading` and target of `settings` at `now`.
+/// The switch is turned off when `reading` is missing or stale,
+/// unless `settings` hold its state.
+pub fn control<Tz: TimeZone>(
+    settings: &ThermostatSettings,
+    reading: Option<Reading>,
+    switch: &mut impl Actuator,
+    now: &DateTime<Tz>,
+) -> ThermostatStatus {
+    let mut status = ThermostatStatus {
+        mode: settings.mode,
+        setpoint: settings.setpoint(now),
+        temperature: reading.map(|reading| reading.temperature),
+        ..ThermostatStatus::default()
+    };
+
+    let enabled = match switch.is_enabled() {
+        Ok(enabled) => enabled,
+        Err(err) => {
+            status.error = Some(err.to_string());
+            return status;
+        }
+    };
+    status.enabled = Some(enabled);
+
+    let desired = match status.setpoint {
+        None => false,
+        Some(setpoint) => {
+            let now = SystemTime::from(now.clone());
+            let fresh = reading.filter(|reading| {
+                // Reading from the future is considered fresh
+                now.duration_since(reading.time).unwrap_or_default()
+                    <= Duration::from_secs(settings.max_age)
+            });
+
+            let Some(reading) = fresh else {
+                status.stale = true;
+
+                if settings.hold_on_stale {
+                    return status;
+                }
+                return turn(switch, false, enabled, status);
+            };
+
+            let low = setpoint - settings.deadband / 2.0;
+            let high = setpoint + settings.deadband / 2.0;
+
+            match settings.mode {
+                ThermostatMode::Cool if reading.temperature > high => true,
+                ThermostatMode::Cool if reading.temperature < low => false,
+                ThermostatMode::Heat | ThermostatMode::Eco if reading.temperature < low => true,
+                ThermostatMode::Heat | ThermostatMode::Eco if reading.temperature > high => false,
+                _ => enabled,
+            }
+        }
+    };
+
+    turn(switch, desired, enabled, status)
+}
+
+/// Turns the switch to `desired` state if it differs from `enabled`
+/// and updates `status` accordingly
+fn turn(
+    switch: &mut impl Actuator,
+    desired: bool,
+    enabled: bool,
+    mut status: ThermostatStatus,
+) -> ThermostatStatus {
+    if desired != enabled {
+        match switch.turn(desired) {
+            Ok(()) => status.enabled = Some(desired),
+            Err(err) => status.error = Some(err.to_string()),
+        }
+    }
+
+    status
+}
+
+/// Describes data shared with control thread
+struct Shared {
+    settings: Mutex<ThermostatSettings>,
+    status: Mutex<ThermostatStatus>,
+}
+
+/// Describes thermostat controlling the switch by readings of the sensor
+/// in its own thread. Control stops when thermostat is dropped.
+pub struct Thermostat {
+    shared: Arc<Shared>,
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Thermostat {
+    /// Starts controlling `switch` by readings of `sensor` each `interval`
+    pub fn start(
+        sensor: impl TemperatureSensor + 'static,
+        mut switch: impl Actuator + 'static,
+        settings: ThermostatSettings,
+        interval: Duration,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            settings: Mutex::new(settings),
+            status: Mutex::new(ThermostatStatus::default()),
+        });
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn({
+            let shared = shared.clone();
+            move || loop {
+                let settings = shared.settings.lock().unwrap().clone();
+                let status = control(&settings, sensor.reading(), &mut switch, &Local::now());
+                *shared.status.lock().unwrap() = status;
+
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            shared,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns current settings
+    pub fn settings(&self) -> ThermostatSettings {
+        self.shared.settings.lock().unwrap().clone()
+    }
+
+    /// Changes mode, applied at the next control step
+    pub fn set_mode(&self, mode: ThermostatMode) {
+        self.shared.settings.lock().unwrap().mode = mode;
+    }
+
+    /// Changes target used when schedule is empty
+    pub fn set_target(&self, target: f64) {
+        self.shared.settings.lock().unwrap().target = target;
+    }
+
+    /// Returns state after the last control step
+    pub fn status(&self) -> ThermostatStatus {
+        self.shared.status.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Thermostat {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn switch(enabled: bool) -> Arc<Mutex<PowerSwitch>> {
+        let state = if enabled {
+            SwitchState::On
+        } else {
+            SwitchState::Off
+        };
+        Arc::new(Mutex::new(PowerSwitch::from_settings(
+            "Heater", state, 1000.0,
+        )))
+    }
+
+    fn reading(temperature: f64, age: u64) -> Option<Reading> {
+        Some(Reading {
+            temperature,
+            time: SystemTime::now() - Duration::from_secs(age),
+        })
+    }
+
+    fn settings(mode: ThermostatMode) -> ThermostatSettings {
+        ThermostatSettings {
+            mode,
+            ..ThermostatSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_heat_with_deadband() {
+        let settings = settings(ThermostatMode::Heat);
+        let now = Local::now();
+        let mut heater = switch(false);
+
+        let status = control(&settings, reading(20.4, 0), &mut heater, &now);
+        assert_eq!(status.enabled, Some(true));
+        assert_eq!(status.setpoint, Some(21.0));
+
+        // Within deadband the heater keeps heating
+        control(&settings, reading(21.4, 0), &mut heater, &now);
+        assert!(heater.lock().unwrap().is_enabled());
+
+        control(&settings, reading(21.6, 0), &mut heater, &now);
+        assert!(!heater.lock().unwrap().is_enabled());
+
+        control(&settings, reading(20.6, 0), &mut heater, &now);
+        assert!(!heater.lock().unwrap().is_enabled());
+    }
+
+    #[test]
+    fn test_cool_eco_and_off_modes() {
+        let now = Local::now();
+
+        let mut cooler = switch(false);
+        control(
+            &settings(ThermostatMode::Cool),
+            reading(22.0, 0),
+            &mut cooler,
+            &now,
+        );
+        assert!(cooler.lock().unwrap().is_enabled());
+
+        let mut heater = switch(true);
+        let status = control(
+            &settings(ThermostatMode::Eco),
+            reading(18.0, 0),
+            &mut heater,
+            &now,
+        );
+        assert_eq!(status.setpoint, Some(16.0));
+        assert_eq!(status.enabled, Some(false));
+
+        let mut heater = switch(true);
+        let status = control(&settings(ThermostatMode::Off), None, &mut heater, &now);
+        assert_eq!(status.enabled, Some(false));
+        assert!(!status.stale);
+    }
+
+    #[test]
+    fn test_refuse_to_act_on_stale_reading() {
+        let mut settings = settings(ThermostatMode::Heat);
+        let mut heater = switch(true);
+
+        // Heater is turned off when thermometer is silent
+        let status = control(&settings, reading(10.0, 120), &mut heater, &Local::now());
+        assert!(status.stale);
+        assert_eq!(status.enabled, Some(false));
+        assert!(!heater.lock().unwrap().is_enabled());
+
+        let status = control(&settings, None, &mut heater, &Local::now());
+        assert!(status.stale);
+        assert!(!heater.lock().unwrap().is_enabled());
+
+        settings.hold_on_stale = true;
+        let mut heater = switch(true);
+
+        let status = control(&settings, reading(10.0, 120), &mut heater, &Local::now());
+        assert!(status.stale);
+        assert_eq!(status.enabled, Some(true));
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        let settings: ThermostatSettings = serde_json::from_str(
+            r#"{ "mode": "heat", "target": 20.0, "schedule": [{ "at": "06:30:00", "target": 22.0 }] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.mode, ThermostatMode::Heat);
+        assert_eq!(settings.deadband, 1.0);
+        assert_eq!(settings.schedule.setpoints().len(), 1);
+    }
+}
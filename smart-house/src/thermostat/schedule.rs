@@ -0,0 +1,129 @@
+use chrono::{NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// Describes target temperature starting at given time of given days
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setpoint {
+    /// Days of week the setpoint applies to, e.g. `["Mon", "Tue"]`,
+    /// every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Time of day the setpoint starts at, e.g. `"06:30:00"`
+    pub at: NaiveTime,
+    /// Target temperature in degrees Celsius
+    pub target: f64,
+}
+
+/// Describes weekly schedule of setpoints. Each setpoint lasts
+/// until the next one, the last setpoint of the week lasts
+/// until the first one of the next week.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WeeklySchedule {
+    setpoints: Vec<Setpoint>,
+}
+
+/// Describes position in the week: day from Monday and seconds from midnight
+type WeekTime = (u32, u32);
+
+impl WeeklySchedule {
+    /// Creates schedule of given `setpoints`
+    pub fn new(setpoints: Vec<Setpoint>) -> Self {
+        Self { setpoints }
+    }
+
+    /// Returns setpoints of the schedule
+    pub fn setpoints(&self) -> &[Setpoint] {
+        &self.setpoints
+    }
+
+    /// Returns target temperature at `time` of `weekday`,
+    /// `None` if the schedule is empty
+    pub fn target_at(&self, weekday: Weekday, time: NaiveTime) -> Option<f64> {
+        let now = week_time(weekday, time);
+
+        let occurrences = self.setpoints.iter().flat_map(|setpoint| {
+            let days = if setpoint.days.is_empty() {
+                ALL_DAYS.to_vec()
+            } else {
+                setpoint.days.clone()
+            };
+
+            days.into_iter()
+                .map(move |day| (week_time(day, setpoint.at), setpoint.target))
+        });
+
+        // The latest setpoint started this week, or the last one of the previous week
+        let mut current: Option<(WeekTime, f64)> = None;
+        let mut last: Option<(WeekTime, f64)> = None;
+
+        for (start, target) in occurrences {
+            if start <= now && current.is_none_or(|(latest, _)| start > latest) {
+                current = Some((start, target));
+            }
+            if last.is_none_or(|(latest, _)| start > latest) {
+                last = Some((start, target));
+            }
+        }
+
+        current.or(last).map(|(_, target)| target)
+    }
+}
+
+const ALL_DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+fn week_time(weekday: Weekday, time: NaiveTime) -> WeekTime {
+    (
+        weekday.num_days_from_monday(),
+        time.num_seconds_from_midnight(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule() -> WeeklySchedule {
+        serde_json::from_str(
+            r#"[
+                { "days": ["Mon", "Tue", "Wed", "Thu", "Fri"], "at": "06:30:00", "target": 21.0 },
+                { "days": ["Sat", "Sun"], "at": "08:00:00", "target": 22.0 },
+                { "at": "22:00:00", "target": 17.0 }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_target_at() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.target_at(Weekday::Wed, at(6, 30)), Some(21.0));
+        assert_eq!(schedule.target_at(Weekday::Wed, at(12, 0)), Some(21.0));
+        assert_eq!(schedule.target_at(Weekday::Wed, at(23, 0)), Some(17.0));
+        assert_eq!(schedule.target_at(Weekday::Sat, at(7, 0)), Some(17.0));
+        assert_eq!(schedule.target_at(Weekday::Sat, at(9, 0)), Some(22.0));
+        // The last setpoint of the previous week lasts until Monday morning
+        assert_eq!(schedule.target_at(Weekday::Mon, at(5, 0)), Some(17.0));
+    }
+
+    #[test]
+    fn test_empty_schedule() {
+        assert_eq!(
+            WeeklySchedule::default().target_at(Weekday::Mon, at(5, 0)),
+            None
+        );
+    }
+}
@@ -0,0 +1,974 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use common::{start_switch, start_thermometer_sender};
+use power_switch::{command::Command, power_switch::SwitchState, response::Response};
+use serde_json::{json, Value};
+use smart_house::{
+    api::ApiServer,
+    house_file::HouseFile,
+    monitor::StateMonitor,
+    recorder::{Sample, SampleStore},
+    registry::DeviceRegistry,
+    scheduler::ScheduleStore,
+    smart_house::{DeviceBinding, SmartHouse},
+    tariff::Tariff,
+};
+use thermometer::metrics::{Measurements, Metric};
+
+mod common;
+
+fn start_api(house: SmartHouse) -> ApiServer {
+    let registry = DeviceRegistry::new(Duration::from_millis(500));
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(house)),
+        Arc::new(registry),
+    )
+    .unwrap();
+
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    server
+}
+
+fn request(server: &ApiServer, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1;
+    let value = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body).unwrap()
+    };
+
+    (status, value)
+}
+
+fn subscribe(server: &ApiServer, query: &str) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /events?{query} HTTP/1.1\r\nHost: localhost\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    reader.read_line(&mut line).unwrap();
+    assert!(line.starts_with("HTTP/1.1 200"));
+
+    // Skip headers
+    while line != "\r\n" {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+
+    reader
+}
+
+fn next_event(reader: &mut BufReader<TcpStream>) -> Value {
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+
+        if let Some(data) = line.strip_prefix("data: ") {
+            return serde_json::from_str(data).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_rooms_crud() {
+    let server = start_api(SmartHouse::new("Test house"));
+
+    let (status, _) = request(
+        &server,
+        "POST",
+        "/rooms",
+        Some(json!({ "name": "Dinning room" })),
+    );
+    assert_eq!(status, 201);
+
+    let (status, _) = request(
+        &server,
+        "POST",
+        "/rooms",
+        Some(json!({ "name": "Dinning room" })),
+    );
+    assert_eq!(status, 409);
+
+    let (status, rooms) = request(&server, "GET", "/rooms", None);
+    assert_eq!(status, 200);
+    assert_eq!(rooms, json!([{ "name": "Dinning room", "devices": [] }]));
+
+    let (status, _) = request(&server, "DELETE", "/rooms/Dinning%20room", None);
+    assert_eq!(status, 204);
+
+    let (status, error) = request(&server, "GET", "/rooms/Dinning%20room", None);
+    assert_eq!(status, 404);
+    assert_eq!(error["error"], r#"Not found room "Dinning room""#);
+
+    let (status, _) = request(&server, "POST", "/rooms", Some(json!({ "title": "Hall" })));
+    assert_eq!(status, 400);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_devices_crud() {
+    let server = start_api(SmartHouse::generate());
+
+    let binding = json!({ "kind": "thermometer", "sender": "127.0.0.1:3333" });
+    let (status, _) = request(
+        &server,
+        "POST",
+        "/rooms/Bathroom/devices",
+        Some(json!({ "name": "therm3", "binding": binding })),
+    );
+    assert_eq!(status, 201);
+
+    let (status, device) = request(&server, "GET", "/rooms/Bathroom/devices/therm3", None);
+    assert_eq!(status, 200);
+    assert_eq!(device["name"], "therm3");
+    assert_eq!(device["room"], "Bathroom");
+    assert_eq!(device["binding"], binding);
+    assert!(device["id"].is_string());
+
+    let (status, devices) = request(&server, "GET", "/rooms/Bathroom/devices", None);
+    assert_eq!(status, 200);
+    assert_eq!(devices.as_array().unwrap().len(), 3);
+
+    let (status, _) = request(
+        &server,
+        "POST",
+        "/rooms/Kitchen/devices",
+        Some(json!({ "name": "x" })),
+    );
+    assert_eq!(status, 404);
+
+    let (status, _) = request(&server, "DELETE", "/rooms/Bathroom/devices/therm3", None);
+    assert_eq!(status, 204);
+
+    let (status, _) = request(&server, "DELETE", "/rooms/Bathroom/devices/therm3", None);
+    assert_eq!(status, 404);
+
+    let (status, _) = request(&server, "GET", "/rooms/Bathroom/devices/therm2/state", None);
+    assert_eq!(status, 409);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_rename_and_move_devices() {
+    let path = std::env::temp_dir().join(format!(
+        "smart-house-api-rename-{}.json",
+        std::process::id()
+    ));
+    _ = std::fs::remove_file(&path);
+
+    let store = Arc::new(ScheduleStore::open(&path).unwrap());
+    let job = serde_json::from_value(json!({
+        "name": "Heating off",
+        "when": "0 23 * * *",
+        "then": [{ "room": "Bathroom", "device": "switch1", "turn": "off" }]
+    }))
+    .unwrap();
+    store.add_job(job).unwrap();
+
+    let mut house = SmartHouse::generate();
+    house.add_to_group("heaters", "Bathroom", "switch1");
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(house)),
+        Arc::new(DeviceRegistry::default()),
+    )
+    .unwrap()
+    .with_scheduler(store.clone());
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let (status, room) = request(
+        &server,
+        "PATCH",
+        "/rooms/Bathroom",
+        Some(json!({ "name": "Washroom" })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(room["name"], "Washroom");
+    assert_eq!(request(&server, "GET", "/rooms/Bathroom", None).0, 404);
+
+    let (status, _) = request(
+        &server,
+        "PATCH",
+        "/rooms/Washroom",
+        Some(json!({ "name": "Dinning room" })),
+    );
+    assert_eq!(status, 409);
+
+    // Dinning room already has switch1
+    let (status, _) = request(
+        &server,
+        "PATCH",
+        "/rooms/Washroom/devices/switch1",
+        Some(json!({ "room": "Dinning room" })),
+    );
+    assert_eq!(status, 409);
+
+    let (status, device) = request(
+        &server,
+        "PATCH",
+        "/rooms/Washroom/devices/switch1",
+        Some(json!({ "room": "Dinning room", "name": "heater" })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(device["name"], "heater");
+
+    let (_, group) = request(&server, "GET", "/groups/heaters", None);
+    assert_eq!(
+        group,
+        json!([{ "room": "Dinning room", "device": "heater" }])
+    );
+
+    let (_, jobs) = request(&server, "GET", "/schedule", None);
+    assert_eq!(
+        jobs[0]["then"][0],
+        json!({ "room": "Dinning room", "device": "heater", "turn": "off" })
+    );
+
+    let (status, _) = request(
+        &server,
+        "PATCH",
+        "/rooms/Washroom/devices/switch1",
+        Some(json!({ "name": "fan" })),
+    );
+    assert_eq!(status, 404);
+
+    server.shutdown();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_rename_succeeds_when_schedule_is_not_saved() {
+    let dir = std::env::temp_dir().join(format!("smart-house-api-unsaved-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = Arc::new(ScheduleStore::open(dir.join("schedule.json")).unwrap());
+    let job = serde_json::from_value(json!({
+        "name": "Heating off",
+        "when": "0 23 * * *",
+        "then": [{ "room": "Bathroom", "device": "switch1", "turn": "off" }]
+    }))
+    .unwrap();
+    store.add_job(job).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let house = dir.with_extension("json");
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(SmartHouse::generate())),
+        Arc::new(DeviceRegistry::default()),
+    )
+    .unwrap()
+    .with_scheduler(store)
+    .with_house_file(&house);
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let (status, device) = request(
+        &server,
+        "PATCH",
+        "/rooms/Bathroom/devices/switch1",
+        Some(json!({ "name": "heater" })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(device["name"], "heater");
+
+    let file = HouseFile::load(&house).unwrap();
+    let bathroom = file.rooms.iter().find(|r| r.name == "Bathroom").unwrap();
+    assert!(bathroom.devices.iter().any(|d| d.name == "heater"));
+
+    server.shutdown();
+    std::fs::remove_file(&house).unwrap();
+}
+
+#[test]
+fn test_devices_by_id() {
+    let server = start_api(SmartHouse::generate());
+
+    let (status, devices) = request(&server, "GET", "/devices", None);
+    assert_eq!(status, 200);
+    assert_eq!(devices.as_array().unwrap().len(), 4);
+
+    // Devices with the same name in different rooms have different ids
+    let (_, bathroom) = request(&server, "GET", "/rooms/Bathroom/devices/switch1", None);
+    let (_, dinning) = request(
+        &server,
+        "GET",
+        "/rooms/Dinning%20room/devices/switch1",
+        None,
+    );
+    assert_ne!(bathroom["id"], dinning["id"]);
+
+    let path = format!("/devices/{}", bathroom["id"].as_str().unwrap());
+    let (status, device) = request(
+        &server,
+        "PATCH",
+        &path,
+        Some(json!({ "room": "Dinning room", "name": "heater" })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(device["id"], bathroom["id"]);
+
+    let (status, device) = request(&server, "GET", &path, None);
+    assert_eq!(status, 200);
+    assert_eq!(device["room"], "Dinning room");
+    assert_eq!(device["name"], "heater");
+
+    // Device isn't bound
+    assert_eq!(request(&server, "POST", &format!("{path}/on"), None).0, 409);
+
+    let (status, _) = request(
+        &server,
+        "DELETE",
+        "/rooms/Dinning%20room/devices/heater",
+        None,
+    );
+    assert_eq!(status, 204);
+    assert_eq!(request(&server, "GET", &path, None).0, 404);
+    assert_eq!(request(&server, "GET", "/devices/switch1", None).0, 400);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_changes_are_saved_to_house_file() {
+    let path =
+        std::env::temp_dir().join(format!("smart-house-api-house-{}.json", std::process::id()));
+    _ = std::fs::remove_file(&path);
+
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(SmartHouse::generate())),
+        Arc::new(DeviceRegistry::default()),
+    )
+    .unwrap()
+    .with_house_file(&path);
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    // Nothing changed yet
+    request(&server, "GET", "/devices", None);
+    assert!(!path.exists());
+
+    let (status, _) = request(
+        &server,
+        "POST",
+        "/rooms/Bathroom/devices",
+        Some(json!({ "name": "therm3" })),
+    );
+    assert_eq!(status, 201);
+    let (_, added) = request(&server, "GET", "/rooms/Bathroom/devices/therm3", None);
+
+    let (status, _) = request(
+        &server,
+        "PATCH",
+        "/rooms/Bathroom/devices/switch1",
+        Some(json!({ "room": "Dinning room", "name": "heater" })),
+    );
+    assert_eq!(status, 200);
+
+    let file = HouseFile::load(&path).unwrap();
+    let bathroom = file.rooms.iter().find(|r| r.name == "Bathroom").unwrap();
+    let therm3 = bathroom
+        .devices
+        .iter()
+        .find(|d| d.name == "therm3")
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(therm3.id).unwrap(),
+        added["id"],
+        "id of added device must be kept"
+    );
+    assert!(bathroom.devices.iter().all(|d| d.name != "switch1"));
+
+    // Restarted daemon serves the same identifiers
+    let restarted = start_api(SmartHouse::from(file));
+    assert_eq!(
+        request(&server, "GET", "/devices", None).1,
+        request(&restarted, "GET", "/devices", None).1
+    );
+
+    let (status, _) = request(&server, "DELETE", "/rooms/Bathroom/devices/therm3", None);
+    assert_eq!(status, 204);
+    let file = HouseFile::load(&path).unwrap();
+    let bathroom = file.rooms.iter().find(|r| r.name == "Bathroom").unwrap();
+    assert!(bathroom.devices.iter().all(|d| d.name != "therm3"));
+
+    server.shutdown();
+    restarted.shutdown();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_switch_on_and_off() {
+    let server = start_api(SmartHouse::new("Test house"));
+    let address = start_switch(SwitchState::Off, 1500.0);
+
+    request(&server, "POST", "/rooms", Some(json!({ "name": "Hall" })));
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(
+            json!({ "name": "heater", "binding": { "kind": "power_switch", "address": address } }),
+        ),
+    );
+
+    let (status, state) = request(&server, "POST", "/rooms/Hall/devices/heater/on", None);
+    assert_eq!(status, 200);
+    assert_eq!(state["kind"], "power_switch");
+    assert_eq!(state["enabled"], true);
+    assert_eq!(state["power"], 1500.0);
+
+    let (status, state) = request(&server, "GET", "/rooms/Hall/devices/heater/state", None);
+    assert_eq!(status, 200);
+    assert_eq!(state["enabled"], true);
+
+    let (status, state) = request(&server, "POST", "/rooms/Hall/devices/heater/off", None);
+    assert_eq!(status, 200);
+    assert_eq!(state["enabled"], false);
+    assert_eq!(state["power"], 0.0);
+    // Energy was consumed while the heater was on
+    assert!(state["energy"].as_f64().unwrap() > 0.0);
+
+    server.shutdown();
+}
+
+/// Starts stand-in of switch server without energy meter,
+/// it answers `Unknown` to request of energy
+fn start_legacy_switch() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut command = [0u8];
+            while stream.read_exact(&mut command).is_ok() {
+                let response = match Command::from(command[0]) {
+                    Command::IsEnabled => Response::Enabled,
+                    Command::GetPower => Response::Power(60.0),
+                    _ => Response::Unknown,
+                };
+                let bytes: [u8; 9] = response.into();
+                _ = stream.write_all(&bytes);
+            }
+        }
+    });
+
+    address
+}
+
+#[test]
+fn test_state_of_switch_without_energy_meter() {
+    let server = start_api(SmartHouse::new("Test house"));
+    let address = start_legacy_switch();
+
+    request(&server, "POST", "/rooms", Some(json!({ "name": "Hall" })));
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(json!({ "name": "lamp", "binding": { "kind": "power_switch", "address": address } })),
+    );
+
+    let (status, state) = request(&server, "GET", "/rooms/Hall/devices/lamp/state", None);
+    assert_eq!(status, 200);
+    assert_eq!(state["enabled"], true);
+    assert_eq!(state["power"], 60.0);
+    assert!(state.get("energy").is_none());
+
+    server.shutdown();
+}
+
+#[test]
+fn test_scenes_crud_and_apply() {
+    let server = start_api(SmartHouse::new("Test house"));
+    let address = start_switch(SwitchState::Off, 1500.0);
+
+    request(&server, "POST", "/rooms", Some(json!({ "name": "Hall" })));
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(
+            json!({ "name": "heater", "binding": { "kind": "power_switch", "address": address } }),
+        ),
+    );
+
+    let scene = json!({
+        "name": "Morning",
+        "states": [{ "room": "Hall", "device": "heater", "enabled": true }]
+    });
+    assert_eq!(
+        request(&server, "POST", "/scenes", Some(scene.clone())).0,
+        201
+    );
+    assert_eq!(
+        request(&server, "POST", "/scenes", Some(scene.clone())).0,
+        409
+    );
+    assert_eq!(
+        request(&server, "GET", "/scenes/Morning", None),
+        (200, scene)
+    );
+
+    let (status, report) = request(&server, "POST", "/scenes/Morning/apply?rollback=true", None);
+    assert_eq!(status, 200);
+    assert_eq!(report["applied"], true);
+    assert_eq!(report["devices"][0]["previous"], false);
+
+    let (_, state) = request(&server, "GET", "/rooms/Hall/devices/heater/state", None);
+    assert_eq!(state["enabled"], true);
+
+    assert_eq!(
+        request(&server, "POST", "/scenes/Morning/apply?undo=1", None).0,
+        400
+    );
+    assert_eq!(request(&server, "DELETE", "/scenes/Morning", None).0, 204);
+    assert_eq!(
+        request(&server, "POST", "/scenes/Morning/apply", None).0,
+        404
+    );
+
+    server.shutdown();
+}
+
+#[test]
+fn test_group_bulk_commands_and_summary() {
+    let server = start_api(SmartHouse::new("Test house"));
+
+    for room in ["Hall", "Kitchen"] {
+        let address = start_switch(SwitchState::Off, 1500.0);
+        request(&server, "POST", "/rooms", Some(json!({ "name": room })));
+        request(
+            &server,
+            "POST",
+            &format!("/rooms/{room}/devices"),
+            Some(
+                json!({ "name": "heater", "binding": { "kind": "power_switch", "address": address } }),
+            ),
+        );
+
+        let member = json!({ "room": room, "device": "heater" });
+        let (status, _) = request(
+            &server,
+            "POST",
+            "/groups/heaters/devices",
+            Some(member.clone()),
+        );
+        assert_eq!(status, 201);
+        let (status, _) = request(&server, "POST", "/groups/heaters/devices", Some(member));
+        assert_eq!(status, 409);
+    }
+
+    let unknown = json!({ "room": "Hall", "device": "lamp" });
+    assert_eq!(
+        request(&server, "POST", "/groups/heaters/devices", Some(unknown)).0,
+        404
+    );
+
+    let (status, report) = request(&server, "POST", "/groups/heaters/on", None);
+    assert_eq!(status, 200);
+    assert_eq!(report["devices"].as_array().unwrap().len(), 2);
+
+    let (status, summary) = request(&server, "GET", "/groups/heaters/summary", None);
+    assert_eq!(status, 200);
+    assert_eq!(summary["switches_on"], 2);
+    assert_eq!(summary["total_power"], 3000.0);
+
+    request(&server, "POST", "/groups/heaters/off", None);
+    let (_, summary) = request(&server, "GET", "/groups/heaters/summary", None);
+    assert_eq!(summary["switches_off"], 2);
+    assert_eq!(summary["total_power"], 0.0);
+
+    let (status, _) = request(
+        &server,
+        "DELETE",
+        "/groups/heaters/devices/Hall/heater",
+        None,
+    );
+    assert_eq!(status, 204);
+    let (_, groups) = request(&server, "GET", "/groups", None);
+    assert_eq!(
+        groups,
+        json!({ "heaters": [{ "room": "Kitchen", "device": "heater" }] })
+    );
+
+    assert_eq!(request(&server, "DELETE", "/groups/heaters", None).0, 204);
+    assert_eq!(
+        request(&server, "GET", "/groups/heaters/summary", None).0,
+        404
+    );
+
+    server.shutdown();
+}
+
+#[test]
+fn test_power_budget() {
+    let server = start_api(SmartHouse::new("Test house"));
+
+    assert_eq!(request(&server, "GET", "/budget", None).0, 404);
+
+    let budget = json!({
+        "limit": 3000.0,
+        "margin": 200.0,
+        "priorities": [{ "room": "Hall", "device": "heater", "priority": 1 }]
+    });
+    assert_eq!(
+        request(&server, "PUT", "/budget", Some(budget.clone())).0,
+        200
+    );
+
+    let (status, value) = request(&server, "GET", "/budget", None);
+    assert_eq!(status, 200);
+    assert_eq!(value["limit"], budget["limit"]);
+    assert_eq!(value["priorities"], budget["priorities"]);
+    assert_eq!(value["shed"], json!([]));
+
+    assert_eq!(request(&server, "DELETE", "/budget", None).0, 204);
+    assert_eq!(request(&server, "DELETE", "/budget", None).0, 404);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_report_in_json() {
+    let measurements = Measurements::from([(Metric::Temperature, 21.5)]);
+    let sender_address = start_thermometer_sender(Arc::new(Mutex::new(measurements)));
+
+    let server = start_api(SmartHouse::new("Test house"));
+
+    request(&server, "POST", "/rooms", Some(json!({ "name": "Hall" })));
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(
+            json!({ "name": "therm1", "binding": { "kind": "thermometer", "sender": sender_address } }),
+        ),
+    );
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(
+            json!({ "name": "switch1", "binding": { "kind": "power_switch", "address": "127.0.0.1:1" } }),
+        ),
+    );
+    request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices",
+        Some(json!({ "name": "lamp" })),
+    );
+
+    let (status, report) = request(&server, "GET", "/report", None);
+    assert_eq!(status, 200);
+    assert_eq!(report["name"], "Test house");
+
+    let devices = &report["rooms"][0]["devices"];
+    assert_eq!(devices[0]["name"], "lamp");
+    assert_eq!(devices[0]["state"], Value::Null);
+    assert!(devices[1]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Device is unavailable"));
+    assert_eq!(
+        devices[2]["state"],
+        json!({ "kind": "thermometer", "temperature": 21.5, "measurements": { "temperature": 21.5 } })
+    );
+
+    server.shutdown();
+}
+
+#[test]
+fn test_event_stream_with_filter() {
+    let server = start_api(SmartHouse::new("Test house"));
+    let address = start_switch(SwitchState::Off, 1500.0);
+    let other_address = start_switch(SwitchState::Off, 1500.0);
+
+    for (room, device, address) in [
+        ("Hall", "heater", &address),
+        ("Bathroom", "fan", &other_address),
+    ] {
+        request(&server, "POST", "/rooms", Some(json!({ "name": room })));
+        request(
+            &server,
+            "POST",
+            &format!("/rooms/{room}/devices"),
+            Some(
+                json!({ "name": device, "binding": { "kind": "power_switch", "address": address } }),
+            ),
+        );
+    }
+
+    let mut events = subscribe(&server, "room=Hall");
+
+    request(&server, "POST", "/rooms/Bathroom/devices/fan/on", None);
+    request(&server, "POST", "/rooms/Hall/devices/heater/on", None);
+
+    let event = next_event(&mut events);
+    assert_eq!(event["kind"], "switch_toggled");
+    assert_eq!(event["room"], "Hall");
+    assert_eq!(event["device"], "heater");
+    assert_eq!(event["enabled"], true);
+    assert!(event["timestamp"].as_u64().unwrap() > 0);
+
+    let event = next_event(&mut events);
+    assert_eq!(event["kind"], "power_changed");
+    assert_eq!(event["power"], 1500.0);
+
+    server.shutdown();
+}
+
+#[test]
+fn test_monitor_reports_stale_device() {
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Hall", "switch1");
+    house.bind_device(
+        "Hall",
+        "switch1",
+        DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:1".parse().unwrap(),
+        },
+    );
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(200)));
+    let server = ApiServer::new("127.0.0.1:0", house.clone(), registry.clone()).unwrap();
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let mut events = subscribe(&server, "device=switch1");
+    let _monitor =
+        StateMonitor::start(house, registry, server.events(), Duration::from_millis(100));
+
+    let event = next_event(&mut events);
+    assert_eq!(event["kind"], "device_stale");
+    assert_eq!(event["room"], "Hall");
+
+    server.shutdown();
+}
+
+fn get_metrics(server: &ApiServer) -> String {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    write!(
+        stream,
+        "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("text/plain; version=0.0.4"));
+
+    response.split_once("\r\n\r\n").unwrap().1.to_owned()
+}
+
+#[test]
+fn test_metrics_of_polled_devices() {
+    let mut house = SmartHouse::new("Test house");
+    for (device, address) in [
+        ("heater", start_switch(SwitchState::Off, 1500.0)),
+        ("lamp", "127.0.0.1:1".parse().unwrap()),
+    ] {
+        house.add_device("Hall", device);
+        house.bind_device("Hall", device, DeviceBinding::PowerSwitch { address });
+    }
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(200)));
+    let server = ApiServer::new("127.0.0.1:0", house.clone(), registry.clone()).unwrap();
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let mut events = subscribe(&server, "device=lamp");
+    let _monitor =
+        StateMonitor::start(house, registry, server.events(), Duration::from_millis(100));
+
+    // Both devices are polled before the lamp is reported stale
+    next_event(&mut events);
+
+    let metrics = get_metrics(&server);
+    let heater = r#"{house="Test house",room="Hall",device="heater"}"#;
+    let lamp = r#"{house="Test house",room="Hall",device="lamp"}"#;
+
+    assert!(metrics.contains(&format!("smart_house_device_up{heater} 1\n")));
+    assert!(metrics.contains(&format!("smart_house_switch_enabled{heater} 0\n")));
+    assert!(metrics.contains(&format!(
+        "smart_house_switch_energy_watt_hours_total{heater} 0\n"
+    )));
+    assert!(metrics.contains(&format!("smart_house_device_up{lamp} 0\n")));
+
+    server.shutdown();
+}
+
+#[test]
+fn test_schedule_crud() {
+    let path = std::env::temp_dir().join(format!(
+        "smart-house-api-schedule-{}.json",
+        std::process::id()
+    ));
+    _ = std::fs::remove_file(&path);
+
+    let store = Arc::new(ScheduleStore::open(&path).unwrap());
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(SmartHouse::new("Test house"))),
+        Arc::new(DeviceRegistry::default()),
+    )
+    .unwrap()
+    .with_scheduler(store);
+
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let job = json!({
+        "name": "Lights on",
+        "when": "sunset+15m",
+        "then": [{ "room": "Hall", "device": "lights", "turn": "on" }]
+    });
+    assert_eq!(
+        request(&server, "POST", "/schedule/jobs", Some(job.clone())).0,
+        201
+    );
+    assert_eq!(request(&server, "POST", "/schedule/jobs", Some(job)).0, 409);
+
+    let invalid = json!({ "name": "Invalid", "when": "every day", "then": [] });
+    assert_eq!(
+        request(&server, "POST", "/schedule/jobs", Some(invalid)).0,
+        400
+    );
+
+    let (status, jobs) = request(&server, "GET", "/schedule", None);
+    assert_eq!(status, 200);
+    assert_eq!(jobs[0]["name"], "Lights on");
+    assert_eq!(jobs[0]["missed"], "skip");
+    assert!(jobs[0]["next_run"].is_string());
+    assert!(jobs[0]["last_run"].is_null());
+
+    assert_eq!(
+        request(&server, "DELETE", "/schedule/jobs/Lights%20on", None).0,
+        204
+    );
+    assert_eq!(
+        request(&server, "DELETE", "/schedule/jobs/Lights%20on", None).0,
+        404
+    );
+    assert!(ScheduleStore::open(&path)
+        .unwrap()
+        .schedule()
+        .jobs
+        .is_empty());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_costs_in_json_and_csv() {
+    let path = std::env::temp_dir().join(format!(
+        "smart-house-api-costs-{}.jsonl",
+        std::process::id()
+    ));
+    _ = std::fs::remove_file(&path);
+
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Kitchen", "Kettle");
+    let id = house.device_id("Kitchen", "Kettle");
+
+    // Kettle was renamed from Teapot after the second sample
+    let store = Arc::new(SampleStore::open(&path).unwrap());
+    let samples: Vec<_> = [
+        (1000, 100.0, "Teapot"),
+        (2000, 1100.0, "Teapot"),
+        (3000, 3100.0, "Kettle"),
+    ]
+    .into_iter()
+    .map(|(timestamp, value, device)| Sample {
+        timestamp,
+        room: "Kitchen".to_owned(),
+        device: device.to_owned(),
+        id,
+        metric: "energy".to_owned(),
+        value,
+    })
+    .collect();
+    store.append(&samples).unwrap();
+
+    let server = ApiServer::new(
+        "127.0.0.1:0",
+        Arc::new(Mutex::new(house)),
+        Arc::new(DeviceRegistry::default()),
+    )
+    .unwrap()
+    .with_history(store)
+    .with_tariff(Tariff::flat(0.5, "EUR"));
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let (status, costs) = request(&server, "GET", "/costs?from=0&to=10000", None);
+    assert_eq!(status, 200);
+    assert_eq!(costs["currency"], "EUR");
+    assert_eq!(costs["energy"], 3.0);
+    assert_eq!(costs["cost"], 1.5);
+    assert_eq!(costs["rooms"][0]["switches"].as_array().unwrap().len(), 1);
+    assert_eq!(costs["rooms"][0]["switches"][0]["device"], "Kettle");
+
+    let (status, report) = request(&server, "GET", "/report?from=0&to=2500", None);
+    assert_eq!(status, 200);
+    assert_eq!(report["costs"]["energy"], 1.0);
+
+    assert_eq!(request(&server, "GET", "/costs?to=now", None).0, 400);
+
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    write!(
+        stream,
+        "GET /costs?format=csv HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("text/csv"));
+    assert!(response.contains("level,room,device,energy_kwh,cost,currency"));
+    assert!(response.contains("switch,Kitchen,Kettle,3"));
+
+    server.shutdown();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,144 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{start_switch, start_thermometer_sender};
+use power_switch::power_switch::SwitchState;
+use smart_house::{
+    automation::{AuditLog, AutomationConfig, AutomationEngine, RuleSet},
+    events::HouseEvents,
+    monitor::StateMonitor,
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::{DeviceBinding, SmartHouse},
+};
+use thermometer::metrics::{Measurements, Metric};
+
+mod common;
+
+const RULES: &str = r#"{
+    "rules": [
+        {
+            "name": "Bathroom heating on",
+            "when": [{ "room": "Bathroom", "device": "therm1", "below": 19.0, "hysteresis": 0.5 }],
+            "then": [{ "room": "Bathroom", "device": "switch1", "turn": "on" }]
+        },
+        {
+            "name": "Bathroom heating off",
+            "when": [{ "room": "Bathroom", "device": "therm1", "above": 22.0, "hysteresis": 0.5 }],
+            "then": [{ "room": "Bathroom", "device": "switch1", "turn": "off" }]
+        }
+    ]
+}"#;
+
+struct Setup {
+    measurements: Arc<Mutex<Measurements>>,
+    switch: DeviceBinding,
+    audit: Arc<AuditLog>,
+    _engine: AutomationEngine,
+    _monitor: StateMonitor,
+}
+
+fn start(dry_run: bool) -> Setup {
+    let measurements = Arc::new(Mutex::new(Measurements::from([(
+        Metric::Temperature,
+        20.0,
+    )])));
+    let switch = DeviceBinding::PowerSwitch {
+        address: start_switch(SwitchState::Off, 1500.0),
+    };
+
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Bathroom", "therm1");
+    house.bind_device(
+        "Bathroom",
+        "therm1",
+        DeviceBinding::Thermometer {
+            sender: start_thermometer_sender(measurements.clone()),
+        },
+    );
+    house.add_device("Bathroom", "switch1");
+    house.bind_device("Bathroom", "switch1", switch.clone());
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(500)));
+    let events = Arc::new(HouseEvents::new());
+    let audit = Arc::new(AuditLog::new(10));
+
+    let config: AutomationConfig = serde_json::from_str(RULES).unwrap();
+    let engine = AutomationEngine::start(
+        RuleSet::new(config.rules),
+        house.clone(),
+        registry.clone(),
+        events.clone(),
+        audit.clone(),
+        dry_run,
+    );
+    let monitor = StateMonitor::start(house, registry, events, Duration::from_millis(100));
+
+    Setup {
+        measurements,
+        switch,
+        audit,
+        _engine: engine,
+        _monitor: monitor,
+    }
+}
+
+impl Setup {
+    fn set_temperature(&self, temperature: f64) {
+        _ = self
+            .measurements
+            .lock()
+            .unwrap()
+            .insert(Metric::Temperature, temperature);
+    }
+}
+
+fn is_enabled(switch: &DeviceBinding) -> bool {
+    match DeviceRegistry::default().state(switch).unwrap() {
+        DeviceState::PowerSwitch { enabled, .. } => enabled,
+        state => panic!("Unexpected state {state:?}"),
+    }
+}
+
+fn wait_for_entries(audit: &AuditLog, count: usize) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while audit.entries().len() < count {
+        assert!(Instant::now() < deadline, "Rule didn't fire");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_rules_turn_switch_on_and_off() {
+    let setup = start(false);
+
+    setup.set_temperature(18.0);
+    wait_for_entries(&setup.audit, 1);
+    assert!(is_enabled(&setup.switch));
+
+    setup.set_temperature(23.0);
+    wait_for_entries(&setup.audit, 2);
+    assert!(!is_enabled(&setup.switch));
+
+    let entries = setup.audit.entries();
+    assert_eq!(entries[0].rule, "Bathroom heating on");
+    assert_eq!(entries[1].rule, "Bathroom heating off");
+    assert!(entries.iter().all(|entry| !entry.dry_run));
+    assert_eq!(entries[1].actions[0].error, None);
+}
+
+#[test]
+fn test_dry_run_only_logs_actions() {
+    let setup = start(true);
+
+    setup.set_temperature(18.0);
+    wait_for_entries(&setup.audit, 1);
+
+    let entries = setup.audit.entries();
+    assert!(entries[0].dry_run);
+    assert_eq!(entries[0].actions[0].device, "switch1");
+    assert!(!is_enabled(&setup.switch));
+}
@@ -0,0 +1,50 @@
+//! Fixtures shared by integration tests, each test uses only some of them
+#![allow(dead_code)]
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use power_switch::{
+    power_switch::{PowerSwitch, SwitchState},
+    server::Server,
+};
+use thermometer::{
+    metrics::Measurements,
+    packet::{Packet, Request},
+};
+
+/// Starts power switch in `state` consuming `power` when it is on
+pub fn start_switch(state: SwitchState, power: f64) -> SocketAddr {
+    let server = Server::new(
+        "127.0.0.1:0",
+        PowerSwitch::from_settings("Switch", state, power),
+    )
+    .unwrap();
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || {
+        _ = server.run();
+    });
+    address
+}
+
+/// Starts thermometer sender answering requests with current `measurements`
+pub fn start_thermometer_sender(measurements: Arc<Mutex<Measurements>>) -> SocketAddr {
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = sender.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = [0; 64];
+        while let Ok((len, src_addr)) = sender.recv_from(&mut buf) {
+            if Request::decode(&buf[..len]).is_ok() {
+                let measurements = measurements.lock().unwrap().clone();
+                let bytes = Packet::new(measurements).encode().unwrap();
+                sender.send_to(&bytes, src_addr).unwrap();
+            }
+        }
+    });
+
+    address
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use discovery::{
+    announcement::{Announcement, DeviceKind},
+    announcer::Announcer,
+};
+use smart_house::{
+    device_discovery::DeviceDiscovery,
+    smart_house::{DeviceBinding, SmartHouse},
+};
+
+#[test]
+fn test_discovered_devices_are_added_to_rooms() {
+    let group = "239.255.42.99:47202".parse().unwrap();
+    let mut discovery = DeviceDiscovery::new(group).unwrap();
+
+    let switch = Announcement {
+        room: Some("Bathroom".to_owned()),
+        ..Announcement::new(
+            DeviceKind::PowerSwitch,
+            "switch1",
+            "Heater",
+            "127.0.0.1:53453".parse().unwrap(),
+        )
+    };
+    let thermometer = Announcement::new(
+        DeviceKind::Thermometer,
+        "therm3",
+        "Simulated thermometer",
+        "127.0.0.1:3333".parse().unwrap(),
+    );
+
+    let interval = Duration::from_millis(100);
+    let _switch = Announcer::start(&switch, group, interval).unwrap();
+    let _thermometer = Announcer::start(&thermometer, group, interval).unwrap();
+
+    assert_eq!(discovery.scan(Duration::from_millis(500)).unwrap().len(), 2);
+
+    let mut smart_house = SmartHouse::generate();
+    let added = discovery.add_to_house(&mut smart_house, "Hall");
+
+    assert_eq!(added, vec![("Hall".to_owned(), "therm3".to_owned())]);
+    assert!(smart_house.devices("Hall").unwrap().contains("therm3"));
+    assert_eq!(
+        smart_house.binding("Hall", "therm3"),
+        Some(&DeviceBinding::Thermometer {
+            sender: "127.0.0.1:3333".parse().unwrap()
+        })
+    );
+    assert_eq!(smart_house.binding("Bathroom", "switch1"), None);
+}
@@ -0,0 +1,96 @@
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use common::start_switch;
+use power_switch::power_switch::SwitchState;
+use smart_house::{
+    events::{EventFilter, EventKind, HouseEvent, HouseEvents},
+    load_shedding::LoadController,
+    registry::DeviceRegistry,
+    smart_house::{DeviceBinding, PowerBudget, SmartHouse},
+};
+
+mod common;
+
+/// Waits for the next decision of load shedding
+fn next_decision(events: &mpsc::Receiver<HouseEvent>) -> HouseEvent {
+    loop {
+        let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        if let EventKind::LoadShed { .. } | EventKind::LoadRestored { .. } = event.kind {
+            return event;
+        }
+    }
+}
+
+#[test]
+fn test_shed_and_restore_by_priority() {
+    let mut house = SmartHouse::new("Test house");
+
+    for (room, device, power) in [
+        ("Kitchen", "fridge", 500.0),
+        ("Kitchen", "kettle", 2000.0),
+        ("Hall", "heater", 1500.0),
+    ] {
+        house.add_device(room, device);
+        house.bind_device(
+            room,
+            device,
+            DeviceBinding::PowerSwitch {
+                address: start_switch(SwitchState::On, power),
+            },
+        );
+    }
+
+    let mut budget = PowerBudget::new(3000.0);
+    budget.set_priority("Hall", "heater", 1);
+    budget.set_priority("Kitchen", "kettle", 2);
+    house.set_power_budget(Some(budget));
+
+    let house = Arc::new(Mutex::new(house));
+    let events = Arc::new(HouseEvents::new());
+    let received = events.subscribe(EventFilter::default());
+
+    let controller = LoadController::start(
+        house.clone(),
+        Arc::new(DeviceRegistry::default()),
+        events.clone(),
+        Duration::from_millis(50),
+    );
+
+    let event = next_decision(&received);
+    assert_eq!(event.device, "heater");
+    assert_eq!(
+        event.kind,
+        EventKind::LoadShed {
+            power: 1500.0,
+            total_power: 4000.0,
+            limit: 3000.0
+        }
+    );
+    assert_eq!(controller.shed()[0].device, "heater");
+
+    let heater = events.states()[&("Hall".to_owned(), "heater".to_owned())].clone();
+    assert!(matches!(
+        heater.state,
+        Some(smart_house::registry::DeviceState::PowerSwitch { enabled: false, .. })
+    ));
+
+    house
+        .lock()
+        .unwrap()
+        .set_power_budget(Some(PowerBudget::new(4500.0)));
+
+    let event = next_decision(&received);
+    assert_eq!(event.device, "heater");
+    assert_eq!(
+        event.kind,
+        EventKind::LoadRestored {
+            power: 1500.0,
+            total_power: 2500.0,
+            limit: Some(4500.0)
+        }
+    );
+    assert!(controller.shed().is_empty());
+}
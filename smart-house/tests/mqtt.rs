@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{start_switch, start_thermometer_sender};
+use power_switch::power_switch::SwitchState;
+use serde_json::Value;
+use smart_house::{
+    events::HouseEvents,
+    monitor::StateMonitor,
+    mqtt::{topic_matches, BridgeSettings, Message, MqttBridge, MqttClient, Packet},
+    registry::{DeviceRegistry, DeviceState},
+    smart_house::{DeviceBinding, SmartHouse},
+};
+use thermometer::metrics::{Measurements, Metric};
+
+mod common;
+
+type Subscriptions = Arc<Mutex<Vec<(String, Arc<Mutex<TcpStream>>)>>>;
+
+/// Starts stand-in of MQTT broker supporting retained messages with QoS 0
+fn start_broker() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let retained = Arc::new(Mutex::new(HashMap::<String, Vec<u8>>::new()));
+    let subscriptions = Subscriptions::default();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let retained = retained.clone();
+            let subscriptions = subscriptions.clone();
+            thread::spawn(move || serve(stream, retained, subscriptions));
+        }
+    });
+
+    address
+}
+
+fn serve(
+    stream: TcpStream,
+    retained: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    subscriptions: Subscriptions,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let writer = Arc::new(Mutex::new(stream));
+    let send = |packet: Packet| {
+        _ = packet.write(&mut *writer.lock().unwrap());
+    };
+
+    while let Ok(packet) = Packet::read(&mut reader) {
+        match packet {
+            Packet::Connect { .. } => send(Packet::ConnAck { return_code: 0 }),
+            Packet::Subscribe { packet_id, filters } => {
+                send(Packet::SubAck {
+                    packet_id,
+                    return_codes: vec![0; filters.len()],
+                });
+                for filter in filters {
+                    for (topic, payload) in retained.lock().unwrap().iter() {
+                        if topic_matches(&filter, topic) {
+                            send(Packet::Publish {
+                                topic: topic.clone(),
+                                payload: payload.clone(),
+                                retain: true,
+                            });
+                        }
+                    }
+                    subscriptions.lock().unwrap().push((filter, writer.clone()));
+                }
+            }
+            Packet::Publish {
+                topic,
+                payload,
+                retain,
+            } => {
+                if retain {
+                    retained
+                        .lock()
+                        .unwrap()
+                        .insert(topic.clone(), payload.clone());
+                }
+                for (filter, subscriber) in subscriptions.lock().unwrap().iter() {
+                    if topic_matches(filter, &topic) {
+                        let packet = Packet::Publish {
+                            topic: topic.clone(),
+                            payload: payload.clone(),
+                            retain: false,
+                        };
+                        _ = packet.write(&mut *subscriber.lock().unwrap());
+                    }
+                }
+            }
+            Packet::PingReq => send(Packet::PingResp),
+            _ => return,
+        }
+    }
+}
+
+/// Describes client collecting the last payloads of topics
+struct Observer {
+    client: MqttClient,
+    messages: mpsc::Receiver<Message>,
+    seen: HashMap<String, String>,
+}
+
+impl Observer {
+    fn connect(broker: SocketAddr, filter: &str) -> Self {
+        let (client, messages) =
+            MqttClient::connect(broker, "observer", Duration::from_secs(30)).unwrap();
+        client.subscribe(filter).unwrap();
+
+        Self {
+            client,
+            messages,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Waits for `topic` and returns its last payload satisfying `predicate`
+    fn wait_for(&mut self, topic: &str, predicate: impl Fn(&str) -> bool) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        loop {
+            if let Some(payload) = self.seen.get(topic).filter(|p| predicate(p)) {
+                return payload.clone();
+            }
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let message = self
+                .messages
+                .recv_timeout(timeout)
+                .unwrap_or_else(|_| panic!("No expected message in {topic}"));
+            self.seen
+                .insert(message.topic, String::from_utf8(message.payload).unwrap());
+        }
+    }
+
+    fn expect(&mut self, topic: &str, expected: &str) {
+        self.wait_for(topic, |payload| payload == expected);
+    }
+
+    fn config(&mut self, topic: &str) -> Value {
+        serde_json::from_str(&self.wait_for(topic, |_| true)).unwrap()
+    }
+}
+
+/// Starts bridge and monitor of the house with heater, thermometer
+/// and unreachable lamp in the hall
+fn start_bridge(broker: SocketAddr) -> (MqttBridge, StateMonitor, SocketAddr) {
+    let switch = start_switch(SwitchState::Off, 1500.0);
+    let mut house = SmartHouse::new("Test house");
+
+    for (device, binding) in [
+        ("Heater", DeviceBinding::PowerSwitch { address: switch }),
+        (
+            "therm1",
+            DeviceBinding::Thermometer {
+                sender: start_thermometer_sender(Arc::new(Mutex::new(Measurements::from([(
+                    Metric::Temperature,
+                    21.5,
+                )])))),
+            },
+        ),
+        (
+            "lamp",
+            DeviceBinding::PowerSwitch {
+                address: "127.0.0.1:1".parse().unwrap(),
+            },
+        ),
+    ] {
+        house.add_device("Hall", device);
+        house.bind_device("Hall", device, binding);
+    }
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(500)));
+    let events = Arc::new(HouseEvents::new());
+
+    let (client, messages) =
+        MqttClient::connect(broker, "bridge", Duration::from_secs(30)).unwrap();
+    let bridge = MqttBridge::start(
+        client,
+        messages,
+        house.clone(),
+        registry.clone(),
+        events.clone(),
+        BridgeSettings::default(),
+    )
+    .unwrap();
+    let monitor = StateMonitor::start(house, registry, events, Duration::from_millis(200));
+
+    (bridge, monitor, switch)
+}
+
+#[test]
+fn test_publish_states_and_discovery_payloads() {
+    let broker = start_broker();
+    let mut observer = Observer::connect(broker, "#");
+    let _bridge = start_bridge(broker);
+
+    let config = observer.config("homeassistant/switch/hall_heater/config");
+    assert_eq!(config["command_topic"], "smart-house/hall/heater/set");
+    assert_eq!(config["state_topic"], "smart-house/hall/heater/state");
+    assert_eq!(config["device"]["suggested_area"], "Hall");
+
+    let config = observer.config("homeassistant/sensor/hall_therm1_temperature/config");
+    assert_eq!(config["device_class"], "temperature");
+    assert_eq!(config["state_topic"], "smart-house/hall/therm1/temperature");
+
+    observer.expect("smart-house/hall/heater/state", "OFF");
+    observer.expect("smart-house/hall/heater/power", "0");
+    observer.expect("smart-house/hall/heater/availability", "online");
+    observer.expect("smart-house/hall/therm1/temperature", "21.5");
+    observer.expect("smart-house/hall/lamp/availability", "offline");
+
+    // States are retained for late subscribers
+    let mut late = Observer::connect(broker, "smart-house/hall/heater/+");
+    late.expect("smart-house/hall/heater/state", "OFF");
+
+    _ = observer.client.disconnect();
+}
+
+#[test]
+fn test_commands_turn_switch_on_and_off() {
+    let broker = start_broker();
+    let mut observer = Observer::connect(broker, "smart-house/#");
+    let (_bridge, _monitor, switch) = start_bridge(broker);
+
+    observer.expect("smart-house/hall/heater/state", "OFF");
+
+    observer
+        .client
+        .publish("smart-house/hall/heater/set", b"ON", false)
+        .unwrap();
+
+    observer.expect("smart-house/hall/heater/state", "ON");
+    observer.expect("smart-house/hall/heater/power", "1500");
+
+    let binding = DeviceBinding::PowerSwitch { address: switch };
+    assert!(matches!(
+        DeviceRegistry::default().state(&binding).unwrap(),
+        DeviceState::PowerSwitch {
+            enabled: true,
+            power,
+            ..
+        } if power == 1500.0
+    ));
+
+    observer
+        .client
+        .publish("smart-house/hall/heater/set", b"off", false)
+        .unwrap();
+    observer.expect("smart-house/hall/heater/state", "OFF");
+}
+
+#[test]
+fn test_renamed_device_is_cleared_under_previous_name() {
+    let broker = start_broker();
+    let mut observer = Observer::connect(broker, "#");
+
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Hall", "Heater");
+    house.bind_device(
+        "Hall",
+        "Heater",
+        DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:1".parse().unwrap(),
+        },
+    );
+    let house = Arc::new(Mutex::new(house));
+    let events = Arc::new(HouseEvents::new());
+
+    let (client, messages) =
+        MqttClient::connect(broker, "bridge", Duration::from_secs(30)).unwrap();
+    let _bridge = MqttBridge::start(
+        client,
+        messages,
+        house.clone(),
+        Arc::new(DeviceRegistry::default()),
+        events.clone(),
+        BridgeSettings::default(),
+    )
+    .unwrap();
+
+    let state = |enabled| DeviceState::PowerSwitch {
+        enabled,
+        power: 0.0,
+        energy: None,
+    };
+    events.observe("Hall", "Heater", &Ok(state(true)));
+    observer.config("homeassistant/switch/hall_heater/config");
+    observer.expect("smart-house/hall/heater/state", "ON");
+
+    house
+        .lock()
+        .unwrap()
+        .rename_device("Hall", "Heater", "Radiator")
+        .unwrap();
+    events.rename("Hall", "Heater", "Hall", "Radiator");
+    events.observe("Hall", "Radiator", &Ok(state(false)));
+
+    observer.config("homeassistant/switch/hall_radiator/config");
+    for topic in [
+        "homeassistant/switch/hall_heater/config",
+        "homeassistant/sensor/hall_heater_power/config",
+        "smart-house/hall/heater/state",
+        "smart-house/hall/heater/power",
+        "smart-house/hall/heater/availability",
+    ] {
+        observer.expect(topic, "");
+    }
+
+    _ = observer.client.disconnect();
+}
+
+#[test]
+fn test_reconnect_after_broker_drops_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let broker = listener.local_addr().unwrap();
+    let (tx, received) = mpsc::channel();
+
+    // Broker drops the first connection after the first published message
+    thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+
+            while let Ok(packet) = Packet::read(&mut reader) {
+                match packet {
+                    Packet::Connect { .. } => {
+                        Packet::ConnAck { return_code: 0 }
+                            .write(&mut writer)
+                            .unwrap();
+                    }
+                    Packet::Subscribe { filters, .. } if index > 0 => {
+                        _ = tx.send(format!("subscribe {}", filters[0]));
+                    }
+                    Packet::Publish { .. } if index == 0 => {
+                        _ = writer.shutdown(Shutdown::Both);
+                        break;
+                    }
+                    Packet::Publish { topic, .. } => _ = tx.send(topic),
+                    _ => {}
+                }
+            }
+        }
+    });
+
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Hall", "Heater");
+    let events = Arc::new(HouseEvents::new());
+
+    let (client, messages) =
+        MqttClient::connect(broker, "bridge", Duration::from_secs(30)).unwrap();
+    let _bridge = MqttBridge::start(
+        client,
+        messages,
+        Arc::new(Mutex::new(house)),
+        Arc::new(DeviceRegistry::default()),
+        events.clone(),
+        BridgeSettings::default(),
+    )
+    .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut seen = Vec::new();
+
+    for enabled in [true, false].into_iter().cycle() {
+        assert!(
+            Instant::now() < deadline,
+            "Bridge didn't reconnect: {seen:?}"
+        );
+
+        let state = DeviceState::PowerSwitch {
+            enabled,
+            power: 0.0,
+            energy: Some(0.0),
+        };
+        events.observe("Hall", "Heater", &Ok(state));
+        seen.extend(received.recv_timeout(Duration::from_millis(100)));
+
+        if seen.contains(&"smart-house/hall/heater/state".to_owned()) {
+            break;
+        }
+    }
+
+    assert_eq!(seen[0], "subscribe smart-house/+/+/set");
+}
@@ -0,0 +1,143 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::start_thermometer_sender;
+use serde_json::Value;
+use smart_house::{
+    api::ApiServer,
+    recorder::{Query, Recorder, RetentionPolicy, SampleStore},
+    registry::DeviceRegistry,
+    smart_house::{DeviceBinding, SmartHouse},
+};
+use thermometer::metrics::{Measurements, Metric};
+
+mod common;
+
+fn get(server: &ApiServer, path: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1;
+
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn test_record_and_query_history() {
+    let path =
+        std::env::temp_dir().join(format!("smart-house-history-{}.jsonl", std::process::id()));
+    _ = std::fs::remove_file(&path);
+
+    let measurements = Measurements::from([(Metric::Temperature, 21.5), (Metric::Humidity, 40.0)]);
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Hall", "therm1");
+    house.bind_device(
+        "Hall",
+        "therm1",
+        DeviceBinding::Thermometer {
+            sender: start_thermometer_sender(Arc::new(Mutex::new(measurements))),
+        },
+    );
+    // Unavailable devices are not recorded
+    house.add_device("Hall", "switch1");
+    house.bind_device(
+        "Hall",
+        "switch1",
+        DeviceBinding::PowerSwitch {
+            address: "127.0.0.1:1".parse().unwrap(),
+        },
+    );
+
+    let house = Arc::new(Mutex::new(house));
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(200)));
+    let store = Arc::new(SampleStore::open(&path).unwrap());
+
+    let recorder = Recorder::start(
+        house.clone(),
+        registry.clone(),
+        store.clone(),
+        Duration::from_millis(50),
+        RetentionPolicy::default(),
+    );
+
+    let query = Query {
+        metric: Some("temperature".to_owned()),
+        ..Query::default()
+    };
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while store
+        .query(&query)
+        .unwrap()
+        .first()
+        .map_or(0, |s| s.points.len())
+        < 2
+    {
+        assert!(Instant::now() < deadline, "Readings are not recorded");
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(recorder);
+
+    // History survives restart of the store
+    let store = Arc::new(SampleStore::open(&path).unwrap());
+    let server = ApiServer::new("127.0.0.1:0", house.clone(), registry)
+        .unwrap()
+        .with_history(store);
+    let clone = server.clone();
+    thread::spawn(move || clone.run());
+
+    let (status, series) = get(&server, "/history?room=Hall&device=therm1");
+    assert_eq!(status, 200);
+
+    let series = series.as_array().unwrap();
+    let metrics: Vec<&str> = series
+        .iter()
+        .map(|s| s["metric"].as_str().unwrap())
+        .collect();
+    assert_eq!(metrics, vec!["humidity", "temperature"]);
+    assert_eq!(series[1]["points"][0][1], 21.5);
+    assert!(series[1]["points"].as_array().unwrap().len() >= 2);
+    let temperatures = series[1]["points"].clone();
+
+    // One bucket covers the whole range
+    let last = series[1]["points"].as_array().unwrap().last().unwrap()[0]
+        .as_u64()
+        .unwrap();
+    let (_, series) = get(
+        &server,
+        &format!("/history?metric=temperature&step={}", last + 1),
+    );
+    assert_eq!(series[0]["points"].as_array().unwrap().len(), 1);
+    assert_eq!(series[0]["points"][0][1], 21.5);
+
+    let (status, _) = get(&server, "/history?from=yesterday");
+    assert_eq!(status, 400);
+
+    // Renamed device keeps its history
+    house
+        .lock()
+        .unwrap()
+        .rename_device("Hall", "therm1", "therm3")
+        .unwrap();
+    let (_, renamed) = get(
+        &server,
+        "/history?room=Hall&device=therm3&metric=temperature",
+    );
+    assert_eq!(renamed[0]["device"], "therm3");
+    assert_eq!(renamed[0]["points"], temperatures);
+
+    server.shutdown();
+    _ = std::fs::remove_file(&path);
+}
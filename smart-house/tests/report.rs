@@ -1,11 +1,16 @@
+use std::{net::UdpSocket, time::Duration};
+
 use power_switch::power_switch::PowerSwitch;
 use smart_house::smart_house::{DeviceInfoProvider, SmartHouse};
-use thermometer::thermometer::Thermometer;
+use thermometer::{
+    alarm::Thresholds,
+    thermometer::{Settings, Thermometer},
+};
 
 const REPORT: &str = r#"Power Switch (state: Off, description: "Bathroom", power consumption: 0)
-Thermometer (temperature: 0)
+Thermometer (temperature: 0 °C)
 Power Switch (state: Off, description: "Dinning room", power consumption: 0)
-Thermometer (temperature: 0)
+Thermometer (temperature: 0 °C)
 "#;
 
 struct MyDeviceInfoProvider {
@@ -47,3 +52,36 @@ fn test_report() {
 
     assert_eq!(report, REPORT);
 }
+
+#[test]
+fn test_report_with_active_alarm() {
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let thermometer = Thermometer::from_settings(
+        "127.0.0.1:0",
+        &sender.local_addr().unwrap().to_string(),
+        Settings {
+            thresholds: Thresholds::new(Some(18.0), Some(30.0), 0.5),
+            ..Settings::default()
+        },
+    )
+    .unwrap();
+    let alarms = thermometer.subscribe_alarms();
+
+    sender
+        .send_to(&16.5f64.to_be_bytes(), thermometer.local_addr())
+        .unwrap();
+    alarms.recv_timeout(Duration::from_secs(3)).unwrap();
+
+    let smart_house = SmartHouse::generate();
+
+    let info_provider = MyDeviceInfoProvider {
+        switch1: PowerSwitch::new("Dinning room"),
+        switch2: PowerSwitch::new("Bathroom"),
+        thermometer1: Thermometer::new("127.0.0.1:0", "127.0.0.1:1").unwrap(),
+        thermometer2: thermometer,
+    };
+
+    let report = smart_house.create_report(&info_provider).unwrap();
+
+    assert!(report.contains("Thermometer (temperature: 16.5 °C, alarm: low temperature)"));
+}
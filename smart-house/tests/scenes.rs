@@ -0,0 +1,138 @@
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::Mutex,
+    time::Duration,
+};
+
+use common::start_switch;
+use power_switch::power_switch::SwitchState;
+use smart_house::{
+    events::HouseEvents,
+    registry::{DeviceRegistry, DeviceState},
+    scenes,
+    smart_house::{DeviceBinding, Scene, SmartHouse},
+};
+
+mod common;
+
+/// Returns address nobody listens at
+fn unreachable_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn is_enabled(address: SocketAddr) -> bool {
+    match DeviceRegistry::default()
+        .state(&DeviceBinding::PowerSwitch { address })
+        .unwrap()
+    {
+        DeviceState::PowerSwitch { enabled, .. } => enabled,
+        state => panic!("Unexpected state {state:?}"),
+    }
+}
+
+struct Setup {
+    house: Mutex<SmartHouse>,
+    lamp: SocketAddr,
+    heater: SocketAddr,
+    scene: Scene,
+}
+
+fn setup(with_broken: bool) -> Setup {
+    let lamp = start_switch(SwitchState::On, 60.0);
+    let heater = start_switch(SwitchState::Off, 60.0);
+
+    let mut house = SmartHouse::new("Test house");
+    let mut scene = Scene::new("Night");
+
+    for (room, device, address, enabled) in [
+        ("Hall", "lamp", lamp, false),
+        ("Bedroom", "heater", heater, true),
+    ] {
+        house.add_device(room, device);
+        house.bind_device(room, device, DeviceBinding::PowerSwitch { address });
+        scene.set_state(room, device, enabled);
+    }
+
+    if with_broken {
+        house.add_device("Kitchen", "kettle");
+        house.bind_device(
+            "Kitchen",
+            "kettle",
+            DeviceBinding::PowerSwitch {
+                address: unreachable_address(),
+            },
+        );
+        scene.set_state("Kitchen", "kettle", false);
+    }
+
+    Setup {
+        house: Mutex::new(house),
+        lamp,
+        heater,
+        scene,
+    }
+}
+
+#[test]
+fn test_apply_scene() {
+    let setup = setup(false);
+    let events = HouseEvents::new();
+
+    let report = scenes::apply(
+        &setup.scene,
+        &setup.house,
+        &DeviceRegistry::default(),
+        &events,
+        false,
+    );
+
+    assert!(report.applied);
+    assert!(!report.rolled_back);
+    assert!(report.devices.iter().all(|outcome| outcome.error.is_none()));
+    assert!(!is_enabled(setup.lamp));
+    assert!(is_enabled(setup.heater));
+    assert_eq!(events.states().len(), 2);
+}
+
+#[test]
+fn test_partial_failure_without_rollback() {
+    let setup = setup(true);
+
+    let report = scenes::apply(
+        &setup.scene,
+        &setup.house,
+        &DeviceRegistry::new(Duration::from_millis(500)),
+        &HouseEvents::new(),
+        false,
+    );
+
+    assert!(!report.applied);
+    assert!(report.devices[2].error.is_some());
+    assert!(!is_enabled(setup.lamp));
+    assert!(is_enabled(setup.heater));
+}
+
+#[test]
+fn test_rollback_on_failure() {
+    let setup = setup(true);
+
+    let report = scenes::apply(
+        &setup.scene,
+        &setup.house,
+        &DeviceRegistry::new(Duration::from_millis(500)),
+        &HouseEvents::new(),
+        true,
+    );
+
+    assert!(!report.applied);
+    assert!(report.rolled_back);
+    assert_eq!(report.devices[0].previous, Some(true));
+    assert!(report.devices[0].rolled_back);
+    assert!(report.devices[1].rolled_back);
+    assert!(!report.devices[2].rolled_back);
+    assert!(is_enabled(setup.lamp));
+    assert!(!is_enabled(setup.heater));
+}
@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use common::start_switch;
+use power_switch::power_switch::SwitchState;
+use smart_house::{
+    automation::AuditLog,
+    events::HouseEvents,
+    registry::{DeviceRegistry, DeviceState},
+    scheduler::{Job, ScheduleStore, Scheduler},
+    smart_house::{DeviceBinding, SmartHouse},
+};
+
+mod common;
+
+fn job(name: &str, missed: &str) -> Job {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "when": "* * * * *",
+        "then": [{ "room": "Kitchen", "device": "kettle", "turn": "on" }],
+        "missed": missed
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_missed_runs_after_downtime() {
+    let path =
+        std::env::temp_dir().join(format!("smart-house-scheduler-{}.json", std::process::id()));
+    _ = fs::remove_file(&path);
+
+    // Jobs ran the last time 5 minutes before the scheduler is started again
+    let last_run = Utc::now() - chrono::Duration::minutes(5);
+    {
+        let store = ScheduleStore::open(&path).unwrap();
+        store.add_job(job("Catch up", "run_once")).unwrap();
+        store.add_job(job("Skip", "skip")).unwrap();
+        store.record_run("Catch up", last_run).unwrap();
+        store.record_run("Skip", last_run).unwrap();
+    }
+
+    let switch = DeviceBinding::PowerSwitch {
+        address: start_switch(SwitchState::Off, 2000.0),
+    };
+    let mut house = SmartHouse::new("Test house");
+    house.add_device("Kitchen", "kettle");
+    house.bind_device("Kitchen", "kettle", switch.clone());
+
+    let store = Arc::new(ScheduleStore::open(&path).unwrap());
+    let audit = Arc::new(AuditLog::new(10));
+    let scheduler = Scheduler::start(
+        store.clone(),
+        Arc::new(Mutex::new(house)),
+        Arc::new(DeviceRegistry::default()),
+        Arc::new(HouseEvents::new()),
+        audit.clone(),
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while audit.entries().is_empty() {
+        assert!(Instant::now() < deadline, "Missed job didn't run");
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(scheduler);
+
+    let entries = audit.entries();
+    assert_eq!(entries[0].rule, "Catch up");
+    assert_eq!(entries[0].actions[0].error, None);
+    // Skipped job runs only on time, if a minute has just started
+    assert!(entries.iter().filter(|entry| entry.rule == "Skip").count() <= 1);
+
+    match DeviceRegistry::default().state(&switch).unwrap() {
+        DeviceState::PowerSwitch { enabled, .. } => assert!(enabled),
+        state => panic!("Unexpected state {state:?}"),
+    }
+
+    // Run is persisted
+    let store = ScheduleStore::open(&path).unwrap();
+    assert!(store.last_run("Catch up").unwrap() > last_run);
+
+    fs::remove_file(&path).unwrap();
+}
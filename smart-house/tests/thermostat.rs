@@ -0,0 +1,62 @@
+use std::{
+    net::UdpSocket,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::start_switch;
+use power_switch::power_switch::SwitchState;
+use smart_house::{
+    registry::DeviceRegistry,
+    thermostat::{RemoteSwitch, Thermostat, ThermostatMode, ThermostatSettings, ThermostatStatus},
+};
+use thermometer::thermometer::Thermometer;
+
+mod common;
+
+fn wait_for(thermostat: &Thermostat, check: impl Fn(&ThermostatStatus) -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if check(&thermostat.status()) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn test_thermostat_heats_room_by_remote_switch() {
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let thermometer =
+        Thermometer::new("127.0.0.1:0", &sender.local_addr().unwrap().to_string()).unwrap();
+    let receiver = thermometer.local_addr();
+
+    let registry = Arc::new(DeviceRegistry::new(Duration::from_millis(500)));
+    let switch = RemoteSwitch::new(start_switch(SwitchState::Off, 1500.0), registry);
+
+    let settings = ThermostatSettings {
+        mode: ThermostatMode::Heat,
+        target: 21.0,
+        ..ThermostatSettings::default()
+    };
+    let thermostat = Thermostat::start(thermometer, switch, settings, Duration::from_millis(50));
+
+    // No reading received yet
+    assert!(wait_for(&thermostat, |status| status.stale));
+
+    sender.send_to(&18.0f64.to_be_bytes(), receiver).unwrap();
+    assert!(wait_for(&thermostat, |status| status.enabled == Some(true)));
+
+    sender.send_to(&22.0f64.to_be_bytes(), receiver).unwrap();
+    assert!(wait_for(&thermostat, |status| status.enabled == Some(false)));
+
+    thermostat.set_mode(ThermostatMode::Cool);
+    assert!(wait_for(&thermostat, |status| status.enabled == Some(true)));
+
+    thermostat.set_mode(ThermostatMode::Off);
+    assert!(wait_for(&thermostat, |status| status.enabled
+        == Some(false)
+        && status.setpoint.is_none()));
+}
@@ -0,0 +1,253 @@
+//! Module describes temperature alarms of thermometer
+
+use std::{
+    fmt,
+    sync::{mpsc, Arc},
+    time::SystemTime,
+};
+
+/// Describes kind of temperature alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alarm {
+    /// Temperature dropped below low threshold
+    Low,
+    /// Temperature exceeded high threshold
+    High,
+}
+
+impl fmt::Display for Alarm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alarm::Low => write!(f, "low temperature"),
+            Alarm::High => write!(f, "high temperature"),
+        }
+    }
+}
+
+/// Describes whether alarm was raised or cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmState {
+    Raised,
+    Cleared,
+}
+
+/// Describes crossing of threshold or recovery from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlarmEvent {
+    pub alarm: Alarm,
+    pub state: AlarmState,
+    /// Temperature which caused the event in degrees Celsius
+    pub temperature: f64,
+    pub timestamp: SystemTime,
+}
+
+/// Describes thresholds of temperature alarms in degrees Celsius.
+/// Alarm is cleared only when temperature returns inside the thresholds
+/// by more than `hysteresis`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub low: Option<f64>,
+    pub high: Option<f64>,
+    pub hysteresis: f64,
+}
+
+impl Thresholds {
+    /// Creates new thresholds
+    pub fn new(low: Option<f64>, high: Option<f64>, hysteresis: f64) -> Self {
+        Self {
+            low,
+            high,
+            hysteresis,
+        }
+    }
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            low: None,
+            high: None,
+            hysteresis: 0.5,
+        }
+    }
+}
+
+/// Describes callback called on alarm events
+pub type AlarmCallback = Arc<dyn Fn(&AlarmEvent) + Send + Sync>;
+
+/// Describes monitor which tracks active alarm and notifies listeners
+#[derive(Default)]
+pub struct AlarmMonitor {
+    thresholds: Thresholds,
+    active: Option<Alarm>,
+    callbacks: Vec<AlarmCallback>,
+    subscribers: Vec<mpsc::Sender<AlarmEvent>>,
+}
+
+impl AlarmMonitor {
+    /// Creates new monitor with given `thresholds`
+    pub fn new(thresholds: Thresholds) -> Self {
+        Self {
+            thresholds,
+            ..Default::default()
+        }
+    }
+
+    /// Returns thresholds of the monitor
+    pub fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    /// Sets new thresholds. Active alarm is reevaluated with the next reading.
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Returns currently active alarm
+    pub fn active(&self) -> Option<Alarm> {
+        self.active
+    }
+
+    /// Registers callback called on each alarm event
+    pub fn add_callback(&mut self, callback: AlarmCallback) {
+        self.callbacks.push(callback);
+    }
+
+    /// Returns receiver of alarm events
+    pub fn subscribe(&mut self) -> mpsc::Receiver<AlarmEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Returns registered callbacks
+    pub fn callbacks(&self) -> Vec<AlarmCallback> {
+        self.callbacks.clone()
+    }
+
+    /// Updates state of alarms with new `temperature` in degrees Celsius,
+    /// sends events to subscribers and returns them.
+    /// Callbacks are not called here, so they may be called without lock
+    /// held on the monitor.
+    pub fn update(&mut self, temperature: f64) -> Vec<AlarmEvent> {
+        let mut events = Vec::new();
+        let Thresholds {
+            low,
+            high,
+            hysteresis,
+        } = self.thresholds;
+
+        let recovered = match (self.active, low, high) {
+            (Some(Alarm::Low), Some(low), _) => temperature >= low + hysteresis,
+            (Some(Alarm::High), _, Some(high)) => temperature <= high - hysteresis,
+            (Some(_), _, _) => true,
+            (None, _, _) => false,
+        };
+
+        if let (true, Some(alarm)) = (recovered, self.active) {
+            self.active = None;
+            events.push(Self::event(alarm, AlarmState::Cleared, temperature));
+        }
+
+        if self.active.is_none() {
+            let alarm = match (low, high) {
+                (Some(low), _) if temperature < low => Some(Alarm::Low),
+                (_, Some(high)) if temperature > high => Some(Alarm::High),
+                _ => None,
+            };
+
+            if let Some(alarm) = alarm {
+                self.active = Some(alarm);
+                events.push(Self::event(alarm, AlarmState::Raised, temperature));
+            }
+        }
+
+        for event in &events {
+            self.subscribers.retain(|s| s.send(*event).is_ok());
+        }
+
+        events
+    }
+
+    fn event(alarm: Alarm, state: AlarmState, temperature: f64) -> AlarmEvent {
+        AlarmEvent {
+            alarm,
+            state,
+            temperature,
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+impl fmt::Debug for AlarmMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlarmMonitor")
+            .field("thresholds", &self.thresholds)
+            .field("active", &self.active)
+            .field("callbacks", &self.callbacks.len())
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(events: &[AlarmEvent]) -> Vec<(Alarm, AlarmState)> {
+        events.iter().map(|e| (e.alarm, e.state)).collect()
+    }
+
+    #[test]
+    fn test_raise_and_clear_with_hysteresis() {
+        let mut monitor = AlarmMonitor::new(Thresholds::new(Some(18.0), Some(30.0), 1.0));
+
+        assert!(monitor.update(20.0).is_empty());
+        assert_eq!(
+            states(&monitor.update(17.5)),
+            vec![(Alarm::Low, AlarmState::Raised)]
+        );
+        assert!(monitor.update(18.5).is_empty());
+        assert_eq!(monitor.active(), Some(Alarm::Low));
+        assert_eq!(
+            states(&monitor.update(19.0)),
+            vec![(Alarm::Low, AlarmState::Cleared)]
+        );
+        assert_eq!(monitor.active(), None);
+    }
+
+    #[test]
+    fn test_jump_from_low_to_high() {
+        let mut monitor = AlarmMonitor::new(Thresholds::new(Some(18.0), Some(30.0), 1.0));
+
+        _ = monitor.update(10.0);
+        let events = monitor.update(35.0);
+
+        assert_eq!(
+            states(&events),
+            vec![
+                (Alarm::Low, AlarmState::Cleared),
+                (Alarm::High, AlarmState::Raised)
+            ]
+        );
+        assert_eq!(monitor.active(), Some(Alarm::High));
+    }
+
+    #[test]
+    fn test_subscribers_receive_events() {
+        let mut monitor = AlarmMonitor::new(Thresholds::new(None, Some(30.0), 0.5));
+        let events = monitor.subscribe();
+
+        _ = monitor.update(31.0);
+        _ = monitor.update(29.0);
+
+        let received: Vec<AlarmEvent> = events.try_iter().collect();
+        assert_eq!(
+            states(&received),
+            vec![
+                (Alarm::High, AlarmState::Raised),
+                (Alarm::High, AlarmState::Cleared)
+            ]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Module describes authentication of datagrams with pre-shared key
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Describes size of message authentication code
+pub const MAC_SIZE: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Describes key shared by sender and receiver for HMAC-SHA256
+#[derive(Clone, PartialEq, Eq)]
+pub struct SharedKey(Vec<u8>);
+
+impl SharedKey {
+    /// Creates new key from given `bytes`
+    pub fn new(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    /// Returns message authentication code of `data`
+    pub fn sign(&self, data: &[u8]) -> [u8; MAC_SIZE] {
+        let mut mac = self.hmac();
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Returns `true` if `mac` is valid code of `data`.
+    /// Comparison takes constant time.
+    pub fn verify(&self, data: &[u8], mac: &[u8]) -> bool {
+        let mut hmac = self.hmac();
+        hmac.update(data);
+        hmac.verify_slice(mac).is_ok()
+    }
+
+    fn hmac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.0).expect("HMAC accepts key of any size")
+    }
+}
+
+impl From<&str> for SharedKey {
+    fn from(passphrase: &str) -> Self {
+        Self::new(passphrase.as_bytes())
+    }
+}
+
+/// Doesn't reveal the key in logs
+impl fmt::Debug for SharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedKey(***)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SharedKey::from("secret");
+        let mac = key.sign(b"reading");
+
+        assert!(key.verify(b"reading", &mac));
+        assert!(!key.verify(b"tampered", &mac));
+        assert!(!SharedKey::from("other").verify(b"reading", &mac));
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        assert_eq!(format!("{:?}", SharedKey::from("secret")), "SharedKey(***)");
+    }
+}
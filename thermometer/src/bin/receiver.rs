@@ -1,7 +1,12 @@
 use std::{thread, time::Duration};
 
 use clap::Parser;
-use thermometer::thermometer::Thermometer;
+use thermometer::{
+    alarm::{AlarmState, Thresholds},
+    auth::SharedKey,
+    temperature::{Calibration, TemperatureUnit},
+    thermometer::{Mode, Settings, Thermometer},
+};
 
 /// Sender program for imitating the thermometer
 #[derive(Parser, Debug)]
@@ -14,15 +19,83 @@ struct Args {
     /// Address of sender of thermometer data
     #[clap(short, long, value_parser)]
     sender: String,
+
+    /// Unit of temperature: celsius, fahrenheit or kelvin
+    #[clap(short, long, value_parser, default_value = "celsius")]
+    unit: TemperatureUnit,
+
+    /// Number of digits after the decimal point
+    #[clap(short, long, value_parser)]
+    precision: Option<usize>,
+
+    /// Calibration offset added to each reading (in degrees Celsius)
+    #[clap(short, long, value_parser, default_value_t = 0.0)]
+    offset: f64,
+
+    /// Calibration scale factor applied to each reading
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    scale: f64,
+
+    /// Low temperature alarm threshold (in degrees Celsius)
+    #[clap(long, value_parser)]
+    low: Option<f64>,
+
+    /// High temperature alarm threshold (in degrees Celsius)
+    #[clap(long, value_parser)]
+    high: Option<f64>,
+
+    /// Hysteresis for clearing alarms (in degrees Celsius)
+    #[clap(long, value_parser, default_value_t = 0.5)]
+    hysteresis: f64,
+
+    /// Key shared with sender, only authenticated datagrams are accepted
+    #[clap(short, long, value_parser)]
+    key: Option<String>,
+
+    /// Request readings from sender when it is silent for given
+    /// number of milliseconds instead of waiting for pushed datagrams
+    #[clap(long, value_parser)]
+    pull: Option<u64>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let thermometer = Thermometer::new(&args.receiver, &args.sender).unwrap();
+    let settings = Settings {
+        unit: args.unit,
+        calibration: Calibration::new(args.offset, args.scale),
+        precision: args.precision,
+        thresholds: Thresholds::new(args.low, args.high, args.hysteresis),
+        key: args.key.as_deref().map(SharedKey::from),
+        mode: match args.pull {
+            Some(interval) => Mode::Pull {
+                interval: Duration::from_millis(interval),
+            },
+            None => Mode::Push,
+        },
+        ..Settings::default()
+    };
+
+    let thermometer = Thermometer::from_settings(&args.receiver, &args.sender, settings).unwrap();
+
+    thermometer.on_alarm(|event| match event.state {
+        AlarmState::Raised => println!("Alarm: {} ({} °C)", event.alarm, event.temperature),
+        AlarmState::Cleared => println!("Recovered: {} ({} °C)", event.alarm, event.temperature),
+    });
+
     for _ in 0..88 {
         thread::sleep(Duration::from_secs(2));
         let temperature = thermometer.temperature();
-        println!("The temperature is {temperature}");
+        match args.precision {
+            Some(precision) => println!("The temperature is {temperature:.precision$}"),
+            None => println!("The temperature is {temperature}"),
+        }
+        if let Some(dew_point) = thermometer.dew_point() {
+            println!("The dew point is {dew_point:.1}");
+        }
+        let rejected = thermometer.rejected_packets();
+        if rejected > 0 {
+            println!("Rejected datagrams: {rejected}");
+        }
     }
 }
@@ -0,0 +1,496 @@
+use std::{
+    error::Error,
+    io,
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Parser, ValueEnum};
+use discovery::{
+    announcement::{Announcement, DeviceKind},
+    announcer::{Announcer, DEFAULT_INTERVAL},
+    DEFAULT_GROUP,
+};
+use thermometer::{
+    auth::SharedKey,
+    metrics::Metric,
+    packet::{Packet, Request},
+    temperature::{Temperature, TemperatureUnit},
+};
+
+use crate::{
+    scenario::{Scenario, ScenarioKind},
+    simulator::{Faults, Output, Simulator},
+};
+
+mod random;
+mod scenario;
+mod simulator;
+
+/// Describes how sender delivers measurements to receiver
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Measurements are sent each interval
+    Push,
+    /// Measurements are sent only in response to requests
+    Pull,
+    /// Measurements are sent each interval and in response to requests
+    Both,
+}
+
+/// Sender program for imitating the thermometer
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Address for receiver: <ip>:<port>
+    #[clap(short, long, value_parser)]
+    receiver: String,
+
+    /// Address for binding: <ip>:<port>
+    #[clap(short, long, value_parser)]
+    bind: String,
+
+    /// Unit for printing generated temperature: celsius, fahrenheit or kelvin
+    #[clap(short, long, value_parser, default_value = "celsius")]
+    unit: TemperatureUnit,
+
+    /// Number of digits after the decimal point for printing
+    #[clap(short, long, value_parser)]
+    precision: Option<usize>,
+
+    /// Comma separated metrics to send: temperature, humidity, pressure, co2
+    #[clap(
+        short,
+        long,
+        value_parser,
+        use_value_delimiter = true,
+        default_value = "temperature"
+    )]
+    metrics: Vec<Metric>,
+
+    /// Identifier of the sensor for receivers serving many sensors.
+    /// With several sensors it is used as prefix: <id>-1, <id>-2...
+    #[clap(short, long, value_parser)]
+    id: Option<String>,
+
+    /// Scenario of temperature changes
+    #[clap(short, long, value_enum, default_value = "sine")]
+    scenario: ScenarioKind,
+
+    /// Base temperature of the scenario (in degrees Celsius)
+    #[clap(long, value_parser, default_value_t = 30.0)]
+    base: f64,
+
+    /// Amplitude of sine and bound of random walk around base (in degrees Celsius)
+    #[clap(long, value_parser, default_value_t = 5.0)]
+    amplitude: f64,
+
+    /// Period of sine in seconds
+    #[clap(long, value_parser, default_value_t = 12.566)]
+    period: f64,
+
+    /// Maximum change of temperature per tick for random walk
+    #[clap(long, value_parser, default_value_t = 0.5)]
+    max_step: f64,
+
+    /// Comma separated temperature levels for step scenario
+    #[clap(
+        long,
+        value_parser,
+        use_value_delimiter = true,
+        default_value = "20,25"
+    )]
+    levels: Vec<f64>,
+
+    /// Number of ticks each level of step scenario holds
+    #[clap(long, value_parser, default_value_t = 10)]
+    hold: u64,
+
+    /// CSV file for replay scenario, the first line names metrics
+    #[clap(long, value_parser)]
+    replay: Option<PathBuf>,
+
+    /// How measurements are delivered: push, pull or both
+    #[clap(long, value_enum, default_value = "both")]
+    mode: Mode,
+
+    /// Interval between datagrams in milliseconds
+    #[clap(long, value_parser, default_value_t = 1500)]
+    interval: u64,
+
+    /// Number of ticks to send, infinite by default
+    #[clap(long, value_parser)]
+    count: Option<u64>,
+
+    /// Number of simulated sensors
+    #[clap(long, value_parser, default_value_t = 1)]
+    sensors: usize,
+
+    /// Seed of pseudo random generator, random by default
+    #[clap(long, value_parser)]
+    seed: Option<u64>,
+
+    /// Probability of losing a datagram
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    dropout: f64,
+
+    /// Probability of sensor failure on each tick
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    failure: f64,
+
+    /// Number of ticks failed sensor stays silent
+    #[clap(long, value_parser, default_value_t = 5)]
+    failure_ticks: u64,
+
+    /// Probability of sending corrupt datagram
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    corrupt: f64,
+
+    /// Key shared with receiver for authentication of datagrams
+    #[clap(short, long, value_parser)]
+    key: Option<String>,
+
+    /// Room announced for discovery
+    #[clap(long, value_parser)]
+    room: Option<String>,
+
+    /// Multicast group for announcements: <ip>:<port>
+    #[clap(long, value_parser, default_value_t = DEFAULT_GROUP)]
+    group: SocketAddr,
+
+    /// Don't announce sensors for discovery
+    #[clap(long)]
+    no_announce: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    println!("Receiver address: {}", args.receiver);
+    println!("Binding to: {}", args.bind);
+
+    let receiver = args
+        .receiver
+        .parse::<SocketAddr>()
+        .expect("Failed to parse receiver address");
+
+    let bind = args
+        .bind
+        .parse::<SocketAddr>()
+        .expect("Failed to parse address for binding");
+
+    let socket = UdpSocket::bind(bind).expect("Failed to bind socket");
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let seed = args.seed.unwrap_or(now.as_nanos() as u64);
+
+    // Counter starts from current time, so it keeps growing after restart
+    // and receiver doesn't reject datagrams as replayed. It is shared by sent
+    // and answered datagrams, so it isn't a part of simulation
+    let counter = Arc::new(AtomicU64::new(now.as_millis() as u64));
+
+    let key = args.key.as_deref().map(SharedKey::from);
+
+    println!("Seed: {seed}");
+
+    let scenario = create_scenario(&args)?;
+    let faults = Faults {
+        dropout: args.dropout,
+        failure: args.failure,
+        failure_ticks: args.failure_ticks,
+        corrupt: args.corrupt,
+    };
+
+    let simulators: Vec<Simulator> = (0..args.sensors)
+        .map(|i| {
+            Simulator::new(
+                sensor_id(&args, i),
+                scenario.clone(),
+                args.metrics.clone(),
+                faults,
+                seed.wrapping_add(i as u64),
+            )
+        })
+        .collect();
+
+    let _announcers = if args.no_announce {
+        Vec::new()
+    } else {
+        announce(&simulators, socket.local_addr()?, &args)?
+    };
+
+    let simulators = Arc::new(Mutex::new(simulators));
+    let interval = Duration::from_millis(args.interval);
+    let start = Instant::now();
+
+    let responder = match args.mode {
+        Mode::Push => None,
+        Mode::Pull | Mode::Both => {
+            let socket = socket.try_clone()?;
+            let simulators = simulators.clone();
+            let counter = counter.clone();
+            let key = key.clone();
+            let args = args.clone();
+
+            println!("Answering requests of readings at {bind}");
+
+            Some(thread::spawn(move || {
+                answer_requests(&socket, &simulators, &counter, key.as_ref(), &args, start)
+            }))
+        }
+    };
+
+    if args.mode == Mode::Pull {
+        if let Some(responder) = responder {
+            _ = responder.join();
+        }
+        return Ok(());
+    }
+
+    println!(
+        "Start sending {:?} of {} sensor(s) from {bind} to {receiver}",
+        args.metrics, args.sensors
+    );
+
+    let mut tick = 0;
+
+    while args.count.is_none_or(|count| tick < count) {
+        let time = (interval * tick as u32).as_secs_f64();
+
+        for simulator in simulators.lock().unwrap().iter_mut() {
+            let output = simulator.tick(tick, time);
+            let name = simulator.id().unwrap_or("sensor");
+
+            send_output(
+                &socket,
+                &receiver,
+                name,
+                output,
+                &counter,
+                key.as_ref(),
+                &args,
+            );
+        }
+
+        tick += 1;
+
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+/// Announces each simulated sensor at `endpoint` answering requests of readings
+fn announce(
+    simulators: &[Simulator],
+    endpoint: SocketAddr,
+    args: &Args,
+) -> Result<Vec<Announcer>, Box<dyn Error>> {
+    let mut announcers = Vec::with_capacity(simulators.len());
+
+    for simulator in simulators {
+        let id = simulator
+            .id()
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("thermometer-{}", endpoint.port()));
+
+        let announcement = Announcement {
+            room: args.room.clone(),
+            ..Announcement::new(
+                DeviceKind::Thermometer,
+                &id,
+                "Simulated thermometer",
+                endpoint,
+            )
+        };
+
+        println!("Announcing to {}: {announcement}", args.group);
+
+        announcers.push(Announcer::start(
+            &announcement,
+            args.group,
+            DEFAULT_INTERVAL,
+        )?);
+    }
+
+    Ok(announcers)
+}
+
+/// Answers requests of readings with current measurements of requested sensors.
+/// In pull mode each request ticks the simulation, so faults are injected as for
+/// sent datagrams. Otherwise requests are answered with the latest sent
+/// measurements, so they don't change the sequence of sent datagrams
+fn answer_requests(
+    socket: &UdpSocket,
+    simulators: &Mutex<Vec<Simulator>>,
+    counter: &AtomicU64,
+    key: Option<&SharedKey>,
+    args: &Args,
+    start: Instant,
+) {
+    let mut buf = [0; 512];
+
+    loop {
+        let (len, src_addr) = match socket.recv_from(&mut buf) {
+            Ok(datagram) => datagram,
+            // Receiver which is not started yet makes sent datagrams bounce
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => continue,
+            Err(e) => {
+                println!("Failed to receive request: {e}");
+                continue;
+            }
+        };
+
+        let request = match Request::decode(&buf[..len]) {
+            Ok(request) => request,
+            Err(e) => {
+                println!("Ignored datagram from {src_addr}: {e}");
+                continue;
+            }
+        };
+
+        let elapsed = start.elapsed();
+        let tick = (elapsed.as_millis() / u128::from(args.interval.max(1))) as u64;
+
+        for simulator in simulators.lock().unwrap().iter_mut() {
+            if request.sensor_id.is_some() && simulator.id() != request.sensor_id.as_deref() {
+                continue;
+            }
+
+            let output = match args.mode {
+                Mode::Pull => simulator.tick(tick, elapsed.as_secs_f64()),
+                _ => match simulator.last_packet() {
+                    Some(packet) => Output::Packet(packet),
+                    None => Output::Failed,
+                },
+            };
+            let name = simulator.id().unwrap_or("sensor");
+
+            send_output(socket, &src_addr, name, output, counter, key, args);
+        }
+    }
+}
+
+/// Sends `output` of the sensor named `name`, valid packet gets the next counter
+fn send_output(
+    socket: &UdpSocket,
+    receiver: &SocketAddr,
+    name: &str,
+    output: Output,
+    counter: &AtomicU64,
+    key: Option<&SharedKey>,
+    args: &Args,
+) {
+    match output {
+        Output::Packet(mut packet) => {
+            packet.counter = Some(counter.fetch_add(1, Ordering::Relaxed) + 1);
+
+            if let Err(e) = send_packet(socket, receiver, &packet, key) {
+                println!("[{name}] Failed to send measurements: {e}");
+            }
+            print_packet(name, &packet, args);
+        }
+        Output::Corrupt(bytes) => {
+            if let Err(e) = socket.send_to(&bytes, receiver) {
+                println!("[{name}] Failed to send corrupt datagram: {e}");
+            }
+            println!("[{name}] Sended corrupt datagram");
+        }
+        Output::Dropped => println!("[{name}] Datagram dropped"),
+        Output::Failed => println!("[{name}] Sensor failed"),
+    }
+}
+
+fn create_scenario(args: &Args) -> Result<Scenario, Box<dyn Error>> {
+    let scenario = match args.scenario {
+        ScenarioKind::Constant => Scenario::Constant { value: args.base },
+        ScenarioKind::Sine => Scenario::Sine {
+            base: args.base,
+            amplitude: args.amplitude,
+            period: args.period,
+        },
+        ScenarioKind::RandomWalk => Scenario::RandomWalk {
+            base: args.base,
+            amplitude: args.amplitude,
+            max_step: args.max_step,
+            current: args.base,
+        },
+        ScenarioKind::Step => {
+            if args.levels.is_empty() {
+                return Err("Step scenario requires levels".into());
+            }
+            Scenario::Step {
+                levels: args.levels.clone(),
+                hold: args.hold,
+            }
+        }
+        ScenarioKind::Replay => {
+            let path = args
+                .replay
+                .as_ref()
+                .ok_or("Replay scenario requires CSV file")?;
+            Scenario::Replay {
+                rows: scenario::load_csv(path)?,
+            }
+        }
+    };
+
+    Ok(scenario)
+}
+
+fn sensor_id(args: &Args, index: usize) -> Option<String> {
+    match (&args.id, args.sensors) {
+        (id, 1) => id.clone(),
+        (Some(id), _) => Some(format!("{id}-{}", index + 1)),
+        (None, _) => Some(format!("sensor-{}", index + 1)),
+    }
+}
+
+fn print_packet(name: &str, packet: &Packet, args: &Args) {
+    for (metric, value) in &packet.measurements {
+        match metric {
+            Metric::Temperature => {
+                let temperature = Temperature::celsius(*value).convert(args.unit);
+                match args.precision {
+                    Some(precision) => println!("[{name}] Temperature: {temperature:.precision$}"),
+                    None => println!("[{name}] Temperature: {temperature}"),
+                }
+            }
+            _ => match args.precision {
+                Some(precision) => {
+                    println!("[{name}] {metric}: {value:.precision$} {}", metric.unit())
+                }
+                None => println!("[{name}] {metric}: {value} {}", metric.unit()),
+            },
+        }
+    }
+}
+
+fn send_packet(
+    socket: &UdpSocket,
+    receiver: &SocketAddr,
+    packet: &Packet,
+    key: Option<&SharedKey>,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = match key {
+        Some(key) => packet.encode_signed(key)?,
+        None => packet.encode()?,
+    };
+
+    let sent_bytes = socket.send_to(&bytes, receiver)?;
+
+    if sent_bytes != bytes.len() {
+        return Err("Datagram was truncated".into());
+    }
+
+    Ok(())
+}
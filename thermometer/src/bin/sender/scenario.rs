@@ -0,0 +1,153 @@
+use std::{error::Error, f64::consts::PI, fs, path::Path};
+
+use clap::ValueEnum;
+use thermometer::metrics::{Measurements, Metric};
+
+use crate::random::Random;
+
+/// Describes kind of scenario selected in command line
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ScenarioKind {
+    /// Temperature doesn't change
+    Constant,
+    /// Temperature oscillates around base value
+    Sine,
+    /// Temperature randomly drifts around base value
+    RandomWalk,
+    /// Temperature jumps between given levels
+    Step,
+    /// Measurements are replayed from CSV file
+    Replay,
+}
+
+/// Describes how measurements change over time
+#[derive(Debug, Clone)]
+pub enum Scenario {
+    Constant {
+        value: f64,
+    },
+    Sine {
+        base: f64,
+        amplitude: f64,
+        period: f64,
+    },
+    RandomWalk {
+        base: f64,
+        amplitude: f64,
+        max_step: f64,
+        current: f64,
+    },
+    Step {
+        levels: Vec<f64>,
+        hold: u64,
+    },
+    Replay {
+        rows: Vec<Measurements>,
+    },
+}
+
+impl Scenario {
+    /// Returns measurements for `tick` which happens `time` seconds
+    /// after the start of simulation
+    pub fn generate(&mut self, tick: u64, time: f64, random: &mut Random) -> Measurements {
+        let temperature = match self {
+            Scenario::Constant { value } => *value,
+            Scenario::Sine {
+                base,
+                amplitude,
+                period,
+            } => *base + *amplitude * (2.0 * PI * time / *period).cos(),
+            Scenario::RandomWalk {
+                base,
+                amplitude,
+                max_step,
+                current,
+            } => {
+                let next = *current + random.range(-*max_step, *max_step);
+                *current = next.clamp(*base - *amplitude, *base + *amplitude);
+                *current
+            }
+            Scenario::Step { levels, hold } => {
+                levels[(tick / (*hold).max(1)) as usize % levels.len()]
+            }
+            Scenario::Replay { rows } => return rows[tick as usize % rows.len()].clone(),
+        };
+
+        Measurements::from([(Metric::Temperature, temperature)])
+    }
+}
+
+/// Loads measurements from CSV file. The first line contains names
+/// of metrics, e.g. `temperature,humidity`, each next line contains values.
+pub fn load_csv(path: &Path) -> Result<Vec<Measurements>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let header = lines.next().ok_or("CSV file is empty")?;
+    let metrics = header
+        .split(',')
+        .map(|name| name.trim().parse::<Metric>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rows = Vec::new();
+
+    for line in lines {
+        let values = line
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if values.len() != metrics.len() {
+            return Err(format!("Invalid number of values in line: {line}").into());
+        }
+
+        rows.push(metrics.iter().copied().zip(values).collect());
+    }
+
+    if rows.is_empty() {
+        return Err("CSV file has no values".into());
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temperatures(scenario: &mut Scenario, seed: u64) -> Vec<f64> {
+        let mut random = Random::new(seed);
+        (0..10)
+            .map(|tick| scenario.generate(tick, tick as f64, &mut random)[&Metric::Temperature])
+            .collect()
+    }
+
+    #[test]
+    fn test_random_walk_is_deterministic_and_bounded() {
+        let scenario = Scenario::RandomWalk {
+            base: 20.0,
+            amplitude: 1.0,
+            max_step: 0.5,
+            current: 20.0,
+        };
+
+        let first = temperatures(&mut scenario.clone(), 42);
+        let second = temperatures(&mut scenario.clone(), 42);
+
+        assert_eq!(first, second);
+        assert!(first.iter().all(|t| (19.0..=21.0).contains(t)));
+    }
+
+    #[test]
+    fn test_step_changes() {
+        let mut scenario = Scenario::Step {
+            levels: vec![18.0, 25.0],
+            hold: 3,
+        };
+
+        assert_eq!(
+            temperatures(&mut scenario, 0),
+            vec![18.0, 18.0, 18.0, 25.0, 25.0, 25.0, 18.0, 18.0, 18.0, 25.0]
+        );
+    }
+}
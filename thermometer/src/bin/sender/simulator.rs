@@ -0,0 +1,218 @@
+use std::f64::consts::PI;
+
+use thermometer::{
+    metrics::{Measurements, Metric},
+    packet::Packet,
+};
+
+use crate::{random::Random, scenario::Scenario};
+
+/// Describes faults injected into simulated sensor
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Faults {
+    /// Probability of losing a single datagram
+    pub dropout: f64,
+    /// Probability of sensor failure
+    pub failure: f64,
+    /// Number of ticks failed sensor stays silent
+    pub failure_ticks: u64,
+    /// Probability of sending corrupt datagram
+    pub corrupt: f64,
+}
+
+/// Describes result of single tick of simulated sensor
+#[derive(Debug, PartialEq)]
+pub enum Output {
+    /// Valid datagram without counter, it is set by sender
+    Packet(Packet),
+    /// Datagram which receiver must reject
+    Corrupt(Vec<u8>),
+    /// Datagram is lost
+    Dropped,
+    /// Sensor is failed and sends nothing
+    Failed,
+}
+
+/// Describes simulated sensor
+pub struct Simulator {
+    id: Option<String>,
+    scenario: Scenario,
+    metrics: Vec<Metric>,
+    faults: Faults,
+    random: Random,
+    failed_ticks: u64,
+    /// Measurements of the latest tick the sensor wasn't failed at
+    last: Option<Measurements>,
+}
+
+impl Simulator {
+    pub fn new(
+        id: Option<String>,
+        scenario: Scenario,
+        metrics: Vec<Metric>,
+        faults: Faults,
+        seed: u64,
+    ) -> Self {
+        Self {
+            id,
+            scenario,
+            metrics,
+            faults,
+            random: Random::new(seed),
+            failed_ticks: 0,
+            last: None,
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns output of the sensor for `tick` which happens
+    /// `time` seconds after the start of simulation
+    pub fn tick(&mut self, tick: u64, time: f64) -> Output {
+        if self.failed_ticks > 0 {
+            self.failed_ticks -= 1;
+            return Output::Failed;
+        }
+
+        if self.random.chance(self.faults.failure) {
+            self.failed_ticks = self.faults.failure_ticks.saturating_sub(1);
+            self.last = None;
+            return Output::Failed;
+        }
+
+        let measurements = self.measurements(tick, time);
+        self.last = Some(measurements.clone());
+
+        if self.random.chance(self.faults.dropout) {
+            return Output::Dropped;
+        }
+
+        if self.random.chance(self.faults.corrupt) {
+            return Output::Corrupt(self.corrupt_datagram());
+        }
+
+        Output::Packet(Packet {
+            sensor_id: self.id.clone(),
+            counter: None,
+            measurements,
+        })
+    }
+
+    /// Returns packet with measurements of the latest tick without advancing
+    /// the simulation, so answered requests don't change sequence of ticks.
+    /// Failed sensor and sensor which hasn't ticked yet have nothing to answer
+    pub fn last_packet(&self) -> Option<Packet> {
+        self.last.as_ref().map(|measurements| Packet {
+            sensor_id: self.id.clone(),
+            counter: None,
+            measurements: measurements.clone(),
+        })
+    }
+
+    fn measurements(&mut self, tick: u64, time: f64) -> Measurements {
+        let mut measurements = self.scenario.generate(tick, time, &mut self.random);
+
+        if let Scenario::Replay { .. } = self.scenario {
+            return measurements;
+        }
+
+        for metric in &self.metrics {
+            let value = match metric {
+                Metric::Temperature => continue,
+                Metric::Humidity => Self::oscillate(50.0, 10.0, time),
+                Metric::Pressure => Self::oscillate(1013.0, 5.0, time),
+                Metric::Co2 => Self::oscillate(600.0, 200.0, time),
+            };
+            measurements.insert(*metric, value);
+        }
+
+        measurements.retain(|metric, _| self.metrics.contains(metric));
+
+        measurements
+    }
+
+    /// Returns value in range [`from` - `delta`; `from` + `delta`]
+    fn oscillate(from: f64, delta: f64, time: f64) -> f64 {
+        from + delta * (2.0 * PI * time / 20.0).sin()
+    }
+
+    /// Returns datagram which declares the maximum number of metrics
+    /// but contains only a few bytes, so it is never valid
+    fn corrupt_datagram(&mut self) -> Vec<u8> {
+        let len = 1 + (self.random.next_u64() % 6) as usize;
+        let mut bytes = vec![u8::MAX];
+        bytes.extend((0..len).map(|_| self.random.next_u64() as u8));
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrupt_datagram_is_rejected() {
+        let faults = Faults {
+            corrupt: 1.0,
+            ..Faults::default()
+        };
+        let scenario = Scenario::Constant { value: 20.0 };
+        let mut simulator = Simulator::new(None, scenario, vec![Metric::Temperature], faults, 7);
+
+        for tick in 0..20 {
+            match simulator.tick(tick, 0.0) {
+                Output::Corrupt(bytes) => assert!(Packet::decode(&bytes).is_err()),
+                _ => panic!("Expected corrupt datagram"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_sensor_stays_silent() {
+        let faults = Faults {
+            failure: 1.0,
+            failure_ticks: 3,
+            ..Faults::default()
+        };
+        let scenario = Scenario::Constant { value: 20.0 };
+        let mut simulator = Simulator::new(None, scenario, vec![Metric::Temperature], faults, 7);
+
+        assert!((0..6).all(|tick| matches!(simulator.tick(tick, 0.0), Output::Failed)));
+        assert_eq!(simulator.last_packet(), None);
+    }
+
+    #[test]
+    fn test_last_packet_does_not_change_ticks() {
+        let faults = Faults {
+            dropout: 0.2,
+            failure: 0.1,
+            failure_ticks: 2,
+            corrupt: 0.1,
+        };
+        let scenario = Scenario::RandomWalk {
+            base: 20.0,
+            amplitude: 5.0,
+            max_step: 0.5,
+            current: 20.0,
+        };
+        let metrics = vec![Metric::Temperature, Metric::Humidity];
+        let mut pushed = Simulator::new(None, scenario.clone(), metrics.clone(), faults, 7);
+        let mut pulled = Simulator::new(None, scenario, metrics, faults, 7);
+
+        for tick in 0..100 {
+            let time = tick as f64 * 1.5;
+            let output = pulled.tick(tick, time);
+
+            if let Output::Packet(packet) = &output {
+                assert_eq!(pulled.last_packet().as_ref(), Some(packet));
+            }
+            for _ in 0..3 {
+                _ = pulled.last_packet();
+            }
+
+            assert_eq!(pushed.tick(tick, time), output);
+        }
+    }
+}
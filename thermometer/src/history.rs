@@ -0,0 +1,231 @@
+//! Module describes bounded history of thermometer readings
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+/// Describes single reading of the thermometer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub timestamp: SystemTime,
+    pub value: f64,
+}
+
+impl Reading {
+    /// Creates new reading with given `value` taken at `timestamp`
+    pub fn new(timestamp: SystemTime, value: f64) -> Self {
+        Self { timestamp, value }
+    }
+}
+
+/// Describes ring buffer of the latest readings.
+/// When buffer is full, the oldest reading is dropped.
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    readings: VecDeque<Reading>,
+}
+
+impl History {
+    /// Creates new empty history which keeps at most `capacity` readings
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            readings: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns maximum number of readings kept in the history
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns number of readings in the history
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    /// Returns `true` if there are no readings in the history
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    /// Adds reading to the history, dropping the oldest one if history is full
+    pub fn push(&mut self, reading: Reading) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.readings.len() == self.capacity {
+            _ = self.readings.pop_front();
+        }
+
+        self.readings.push_back(reading);
+    }
+
+    /// Returns the latest reading
+    pub fn latest(&self) -> Option<&Reading> {
+        self.readings.back()
+    }
+
+    /// Returns all readings from the oldest to the latest
+    pub fn readings(&self) -> impl Iterator<Item = &Reading> {
+        self.readings.iter()
+    }
+
+    /// Returns readings taken during the last `window`
+    pub fn window(&self, window: Duration) -> impl Iterator<Item = &Reading> {
+        let since = SystemTime::now()
+            .checked_sub(window)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        self.readings.iter().filter(move |r| r.timestamp >= since)
+    }
+
+    /// Returns minimal value during the last `window`
+    pub fn min(&self, window: Duration) -> Option<f64> {
+        self.window(window).map(|r| r.value).reduce(f64::min)
+    }
+
+    /// Returns maximal value during the last `window`
+    pub fn max(&self, window: Duration) -> Option<f64> {
+        self.window(window).map(|r| r.value).reduce(f64::max)
+    }
+
+    /// Returns arithmetic mean of values during the last `window`
+    pub fn mean(&self, window: Duration) -> Option<f64> {
+        let (sum, count) = self
+            .window(window)
+            .fold((0.0, 0), |(sum, count), r| (sum + r.value, count + 1));
+
+        if count == 0 {
+            return None;
+        }
+
+        Some(sum / count as f64)
+    }
+
+    /// Returns median of values during the last `window`
+    pub fn median(&self, window: Duration) -> Option<f64> {
+        let mut values: Vec<f64> = self.window(window).map(|r| r.value).collect();
+
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_by(f64::total_cmp);
+
+        let middle = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            Some((values[middle - 1] + values[middle]) / 2.0)
+        } else {
+            Some(values[middle])
+        }
+    }
+
+    /// Returns rate of change per second during the last `window`,
+    /// calculated between the first and the last reading of the window
+    pub fn rate_of_change(&self, window: Duration) -> Option<f64> {
+        let mut readings = self.window(window);
+        let first = readings.next()?;
+        let last = readings.last()?;
+
+        let elapsed = last.timestamp.duration_since(first.timestamp).ok()?;
+        if elapsed.is_zero() {
+            return None;
+        }
+
+        Some((last.value - first.value) / elapsed.as_secs_f64())
+    }
+
+    /// Returns average of the latest `samples` readings
+    pub fn moving_average(&self, samples: usize) -> Option<f64> {
+        if samples == 0 || self.readings.is_empty() {
+            return None;
+        }
+
+        let count = samples.min(self.readings.len());
+        let sum: f64 = self
+            .readings
+            .iter()
+            .rev()
+            .take(count)
+            .map(|r| r.value)
+            .sum();
+
+        Some(sum / count as f64)
+    }
+
+    /// Returns copy of the history with `f` applied to each value
+    pub fn map_values(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            capacity: self.capacity,
+            readings: self
+                .readings
+                .iter()
+                .map(|r| Reading::new(r.timestamp, f(r.value)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history_of(values: &[f64], step: Duration) -> History {
+        let mut history = History::new(values.len());
+        let started = SystemTime::now() - step * values.len() as u32;
+
+        for (i, value) in values.iter().enumerate() {
+            history.push(Reading::new(started + step * (i as u32 + 1), *value));
+        }
+
+        history
+    }
+
+    #[test]
+    fn test_push_drops_oldest_reading() {
+        let mut history = History::new(2);
+
+        history.push(Reading::new(SystemTime::now(), 1.0));
+        history.push(Reading::new(SystemTime::now(), 2.0));
+        history.push(Reading::new(SystemTime::now(), 3.0));
+
+        let values: Vec<f64> = history.readings().map(|r| r.value).collect();
+        assert_eq!(values, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_statistics_over_window() {
+        let history = history_of(&[10.0, 20.0, 22.0, 21.0, 23.0], Duration::from_secs(60));
+        let window = Duration::from_secs(60 * 2 + 30);
+
+        assert_eq!(history.min(window), Some(21.0));
+        assert_eq!(history.max(window), Some(23.0));
+        assert_eq!(history.mean(window), Some(22.0));
+        assert_eq!(history.median(window), Some(22.0));
+        assert_eq!(history.median(Duration::from_secs(3600)), Some(21.0));
+    }
+
+    #[test]
+    fn test_rate_of_change() {
+        let history = history_of(&[20.0, 21.0, 23.0], Duration::from_secs(10));
+
+        assert_eq!(
+            history.rate_of_change(Duration::from_secs(3600)),
+            Some(0.15)
+        );
+        assert_eq!(history.rate_of_change(Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let history = history_of(&[20.0, 21.0, 23.0], Duration::from_secs(10));
+
+        assert_eq!(history.moving_average(2), Some(22.0));
+        assert_eq!(history.moving_average(10), Some(64.0 / 3.0));
+        assert_eq!(History::new(5).moving_average(2), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! Module describes hub which receives data of many sensors on one socket
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+};
+
+use crate::{
+    packet::Packet,
+    thermometer::{Settings, State, Thermometer},
+    worker::Worker,
+};
+
+/// Describes number of known sensors above which new senders aren't discovered,
+/// so datagrams of arbitrary sources can't exhaust memory of the hub
+pub const MAX_SENSORS: usize = 256;
+
+/// Describes how the hub distinguishes sensors
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SensorKey {
+    /// Sensor sends its id in each datagram
+    Id(String),
+    /// Sensor without id is identified by source address of datagrams
+    Address(SocketAddr),
+}
+
+impl fmt::Display for SensorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorKey::Id(id) => write!(f, "{id}"),
+            SensorKey::Address(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+/// Describes sensor known by the hub
+#[derive(Debug)]
+struct Sensor {
+    state: Arc<State>,
+    settings: Settings,
+}
+
+/// Describes data of the hub shared with the receiving thread
+#[derive(Debug)]
+struct Shared {
+    sensors: Mutex<BTreeMap<SensorKey, Sensor>>,
+    default_settings: Settings,
+    discovery: Mutex<Vec<mpsc::Sender<SensorKey>>>,
+    rejected: AtomicU64,
+}
+
+impl Shared {
+    fn handle(&self, src_addr: SocketAddr, bytes: &[u8]) {
+        let key = match Packet::peek_sensor_id(bytes) {
+            Some(id) => SensorKey::Id(id.to_owned()),
+            None => SensorKey::Address(src_addr),
+        };
+
+        let (known, full) = {
+            let sensors = self.sensors.lock().unwrap();
+            let known = sensors.get(&key).map(|sensor| sensor.state.clone());
+            (known, sensors.len() >= MAX_SENSORS)
+        };
+
+        if let Some(state) = known {
+            if !state.receive(src_addr, bytes) {
+                self.rejected.fetch_add(1, Ordering::SeqCst);
+            }
+            return;
+        }
+
+        if full {
+            self.rejected.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+
+        // Sensor is discovered only after its first datagram is accepted
+        let settings = self.default_settings.clone();
+        let state = Arc::new(State::new(&settings));
+
+        if !state.receive(src_addr, bytes) {
+            self.rejected.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+
+        self.sensors
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert(Sensor { state, settings });
+
+        self.discovery
+            .lock()
+            .unwrap()
+            .retain(|s| s.send(key.clone()).is_ok());
+    }
+}
+
+/// Describes data owned by all handles of the same hub
+#[derive(Debug)]
+struct Inner {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+    worker: Mutex<Option<Worker>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.get_mut().unwrap().take() {
+            worker.shutdown();
+        }
+    }
+}
+
+/// Describes hub which listens on one socket and demultiplexes datagrams
+/// of many sensors by sensor id or by source address.
+///
+/// New senders are discovered automatically up to [`MAX_SENSORS`]
+/// known sensors. Hub is a shared handle,
+/// it keeps receiving while any clone of it or any of its thermometers exists.
+#[derive(Debug, Clone)]
+pub struct ThermometerHub {
+    inner: Arc<Inner>,
+}
+
+impl ThermometerHub {
+    /// Creates new hub which receives data at given `address`
+    pub fn new(address: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_settings(address, Settings::default())
+    }
+
+    /// Creates new hub with `settings` applied to discovered sensors.
+    /// If settings have key, only senders of authenticated datagrams
+    /// are discovered.
+    pub fn from_settings(address: &str, settings: Settings) -> Result<Self, Box<dyn Error>> {
+        let address = address.parse::<SocketAddr>()?;
+        let socket = UdpSocket::bind(address)?;
+        let local_addr = socket.local_addr()?;
+
+        let shared = Arc::new(Shared {
+            sensors: Mutex::new(BTreeMap::new()),
+            default_settings: settings,
+            discovery: Mutex::new(Vec::new()),
+            rejected: AtomicU64::new(0),
+        });
+        let shared_clone = shared.clone();
+
+        let worker = Worker::spawn(socket, move |datagram| match datagram {
+            Ok((src_addr, bytes)) => shared_clone.handle(src_addr, bytes),
+            Err(err) => println!("Failed to receive datagram: {err}"),
+        })?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                shared,
+                local_addr,
+                worker: Mutex::new(Some(worker)),
+            }),
+        })
+    }
+
+    /// Returns address the hub receives data at
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+
+    /// Returns keys of all known sensors
+    pub fn sensors(&self) -> Vec<SensorKey> {
+        self.inner
+            .shared
+            .sensors
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns thermometer of the known sensor with given `key`
+    pub fn thermometer(&self, key: &SensorKey) -> Option<Thermometer> {
+        let sensors = self.inner.shared.sensors.lock().unwrap();
+        let sensor = sensors.get(key)?;
+
+        Some(Thermometer::from_hub(
+            self.clone(),
+            key.clone(),
+            sensor.state.clone(),
+            sensor.settings.clone(),
+        ))
+    }
+
+    /// Registers sensor with given `key` and `settings` before it sends
+    /// any data and returns its thermometer. If sensor is already known,
+    /// its readings are kept and new settings are applied.
+    pub fn register(&self, key: SensorKey, settings: Settings) -> Thermometer {
+        let mut sensors = self.inner.shared.sensors.lock().unwrap();
+
+        let state = match sensors.get(&key) {
+            Some(sensor) => {
+                sensor.state.configure(&settings);
+                sensor.state.clone()
+            }
+            None => Arc::new(State::new(&settings)),
+        };
+
+        sensors.insert(
+            key.clone(),
+            Sensor {
+                state: state.clone(),
+                settings: settings.clone(),
+            },
+        );
+
+        Thermometer::from_hub(self.clone(), key, state, settings)
+    }
+
+    /// Sends datagram from the socket of the hub to given `target`
+    pub(crate) fn send_to(&self, bytes: &[u8], target: SocketAddr) -> Result<(), Box<dyn Error>> {
+        self.inner
+            .worker
+            .lock()
+            .unwrap()
+            .as_ref()
+            .ok_or("Hub is shut down")?
+            .send_to(bytes, target)?;
+
+        Ok(())
+    }
+
+    /// Returns number of datagrams rejected by the hub,
+    /// including datagrams of unknown senders and of senders
+    /// which aren't discovered because the hub is full
+    pub fn rejected_packets(&self) -> u64 {
+        self.inner.shared.rejected.load(Ordering::SeqCst)
+    }
+
+    /// Returns receiver of keys of newly discovered sensors
+    pub fn subscribe_discovery(&self) -> mpsc::Receiver<SensorKey> {
+        let (tx, rx) = mpsc::channel();
+        self.inner.shared.discovery.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Stops receiving data for all sensors of the hub,
+    /// waits for the receiving thread and releases the socket
+    pub fn shutdown(&self) {
+        let worker = self.inner.worker.lock().unwrap().take();
+
+        if let Some(worker) = worker {
+            worker.shutdown();
+        }
+    }
+
+    /// Returns `true` if hub still receives data
+    pub fn is_running(&self) -> bool {
+        self.inner.worker.lock().unwrap().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metrics::{Measurements, Metric},
+        packet::Request,
+    };
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn send(socket: &UdpSocket, hub: &ThermometerHub, packet: Packet) {
+        socket
+            .send_to(&packet.encode().unwrap(), hub.local_addr())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_discover_sensors_by_id_and_address() {
+        let hub = ThermometerHub::new("127.0.0.1:0").unwrap();
+        let discovery = hub.subscribe_discovery();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let timeout = Duration::from_secs(3);
+
+        let measurements = Measurements::from([(Metric::Temperature, 19.0)]);
+        send(
+            &socket,
+            &hub,
+            Packet::with_sensor_id("kitchen", measurements),
+        );
+        let key = discovery.recv_timeout(timeout).unwrap();
+        assert_eq!(key, SensorKey::Id("kitchen".to_owned()));
+
+        let measurements = Measurements::from([(Metric::Temperature, 23.0)]);
+        send(&socket, &hub, Packet::new(measurements));
+        let key = discovery.recv_timeout(timeout).unwrap();
+        assert_eq!(key, SensorKey::Address(socket.local_addr().unwrap()));
+
+        let kitchen = hub
+            .thermometer(&SensorKey::Id("kitchen".to_owned()))
+            .unwrap();
+        let other = hub.thermometer(&key).unwrap();
+
+        assert_eq!(kitchen.temperature().value(), 19.0);
+        assert_eq!(other.temperature().value(), 23.0);
+        assert_eq!(hub.sensors().len(), 2);
+    }
+
+    #[test]
+    fn test_discovery_stops_at_max_sensors() {
+        let hub = ThermometerHub::new("127.0.0.1:0").unwrap();
+        let discovery = hub.subscribe_discovery();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let measurements = Measurements::from([(Metric::Temperature, 19.0)]);
+
+        for i in 0..=MAX_SENSORS {
+            let id = format!("sensor{i}");
+            send(
+                &socket,
+                &hub,
+                Packet::with_sensor_id(&id, measurements.clone()),
+            );
+            // Waits for each sensor, so the socket buffer isn't overflown
+            if i < MAX_SENSORS {
+                discovery.recv_timeout(Duration::from_secs(3)).unwrap();
+            }
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while hub.rejected_packets() == 0 {
+            assert!(Instant::now() < deadline, "Sensor is discovered");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(hub.sensors().len(), MAX_SENSORS);
+        assert!(discovery.try_recv().is_err());
+
+        // Registered sensors aren't limited
+        hub.register(SensorKey::Id("extra".to_owned()), Settings::default());
+        assert_eq!(hub.sensors().len(), MAX_SENSORS + 1);
+    }
+
+    #[test]
+    fn test_thermometer_keeps_hub_running() {
+        let hub = ThermometerHub::new("127.0.0.1:0").unwrap();
+        let address = hub.local_addr();
+        let thermometer = hub.register(SensorKey::Id("bathroom".to_owned()), Settings::default());
+
+        drop(hub);
+        assert!(thermometer.is_running());
+
+        drop(thermometer);
+        assert!(UdpSocket::bind(address).is_ok());
+    }
+
+    #[test]
+    fn test_read_now_requests_sensor_by_id() {
+        let hub = ThermometerHub::new("127.0.0.1:0").unwrap();
+        let discovery = hub.subscribe_discovery();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let measurements = Measurements::from([(Metric::Temperature, 19.0)]);
+        send(&socket, &hub, Packet::with_sensor_id("hall", measurements));
+        let key = discovery.recv_timeout(Duration::from_secs(3)).unwrap();
+        let thermometer = hub.thermometer(&key).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 64];
+            let (len, src_addr) = socket.recv_from(&mut buf).unwrap();
+            assert_eq!(Request::decode(&buf[..len]), Ok(Request::new(Some("hall"))));
+
+            let measurements = Measurements::from([(Metric::Temperature, 20.5)]);
+            let bytes = Packet::with_sensor_id("hall", measurements)
+                .encode()
+                .unwrap();
+            socket.send_to(&bytes, src_addr).unwrap();
+        });
+
+        assert_eq!(thermometer.read_now().unwrap().value(), 20.5);
+        handle.join().unwrap();
+    }
+}
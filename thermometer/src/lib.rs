@@ -1 +1,9 @@
+pub mod alarm;
+pub mod auth;
+pub mod history;
+pub mod hub;
+pub mod metrics;
+pub mod packet;
+pub mod temperature;
 pub mod thermometer;
+mod worker;
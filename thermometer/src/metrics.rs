@@ -0,0 +1,230 @@
+//! Module describes metrics measured by environmental sensor
+//! and their encoding in datagrams
+//!
+//! Datagram consists of number of metrics followed by pairs of metric code
+//! and big endian `f64` value: `[count][code][value]...[code][value]`.
+//! Legacy datagram of exactly 8 bytes contains only temperature value.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// Describes size of legacy datagram with temperature only
+pub const LEGACY_DATAGRAM_SIZE: usize = 8;
+
+/// Describes size of encoded metric: code and value
+const METRIC_SIZE: usize = 9;
+
+/// Describes kind of value measured by environmental sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Metric {
+    /// Temperature in degrees Celsius
+    Temperature,
+    /// Relative humidity in percents
+    Humidity,
+    /// Atmospheric pressure in hectopascals
+    Pressure,
+    /// CO2 concentration in parts per million
+    Co2,
+}
+
+impl Metric {
+    /// Returns all known metrics
+    pub fn all() -> [Metric; 4] {
+        [
+            Metric::Temperature,
+            Metric::Humidity,
+            Metric::Pressure,
+            Metric::Co2,
+        ]
+    }
+
+    /// Returns name of the metric
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::Pressure => "pressure",
+            Metric::Co2 => "co2",
+        }
+    }
+
+    /// Returns symbol of unit of the metric
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Metric::Temperature => "°C",
+            Metric::Humidity => "%",
+            Metric::Pressure => "hPa",
+            Metric::Co2 => "ppm",
+        }
+    }
+}
+
+impl TryFrom<u8> for Metric {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Metric::Temperature),
+            1 => Ok(Metric::Humidity),
+            2 => Ok(Metric::Pressure),
+            3 => Ok(Metric::Co2),
+            _ => Err("Failed to convert value to Metric"),
+        }
+    }
+}
+
+impl From<Metric> for u8 {
+    fn from(metric: Metric) -> Self {
+        match metric {
+            Metric::Temperature => 0,
+            Metric::Humidity => 1,
+            Metric::Pressure => 2,
+            Metric::Co2 => 3,
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::all()
+            .into_iter()
+            .find(|m| m.name() == s.to_lowercase())
+            .ok_or_else(|| format!("Unknown metric: {s}"))
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Describes values of metrics received from the sensor
+pub type Measurements = BTreeMap<Metric, f64>;
+
+/// Encodes measurements into datagram
+pub fn encode(measurements: &Measurements) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(1 + measurements.len() * METRIC_SIZE);
+
+    bytes.push(measurements.len() as u8);
+
+    for (metric, value) in measurements {
+        bytes.push((*metric).into());
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    bytes
+}
+
+/// Decodes measurements from datagram
+pub fn decode(bytes: &[u8]) -> Result<Measurements, &'static str> {
+    if bytes.len() == LEGACY_DATAGRAM_SIZE {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(bytes);
+        return Ok(Measurements::from([(
+            Metric::Temperature,
+            f64::from_be_bytes(buf),
+        )]));
+    }
+
+    let (count, body) = bytes.split_first().ok_or("Empty datagram")?;
+
+    if body.len() != *count as usize * METRIC_SIZE {
+        return Err("Invalid datagram size");
+    }
+
+    let mut measurements = Measurements::new();
+
+    for chunk in body.chunks_exact(METRIC_SIZE) {
+        let metric = Metric::try_from(chunk[0])?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&chunk[1..]);
+        measurements.insert(metric, f64::from_be_bytes(buf));
+    }
+
+    Ok(measurements)
+}
+
+/// Returns dew point in degrees Celsius for given `temperature`
+/// in degrees Celsius and relative `humidity` in percents (Magnus formula)
+pub fn dew_point(temperature: f64, humidity: f64) -> f64 {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+
+    let gamma = (humidity / 100.0).ln() + A * temperature / (B + temperature);
+    B * gamma / (A - gamma)
+}
+
+/// Returns heat index in degrees Celsius for given `temperature`
+/// in degrees Celsius and relative `humidity` in percents
+/// (regression of the US National Weather Service)
+pub fn heat_index(temperature: f64, humidity: f64) -> f64 {
+    let t = temperature * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+
+    let index = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut index = -42.379 + 2.04901523 * t + 10.14333127 * rh
+            - 0.22475541 * t * rh
+            - 0.00683783 * t * t
+            - 0.05481717 * rh * rh
+            + 0.00122874 * t * t * rh
+            + 0.00085282 * t * rh * rh
+            - 0.00000199 * t * t * rh * rh;
+
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+
+        index
+    };
+
+    (index - 32.0) * 5.0 / 9.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_measurements() {
+        let measurements = Measurements::from([
+            (Metric::Temperature, 21.5),
+            (Metric::Humidity, 45.0),
+            (Metric::Co2, 800.0),
+        ]);
+
+        let bytes = encode(&measurements);
+
+        assert_eq!(bytes.len(), 28);
+        assert_eq!(decode(&bytes), Ok(measurements));
+    }
+
+    #[test]
+    fn test_decode_legacy_datagram() {
+        let measurements = decode(&21.5f64.to_be_bytes()).unwrap();
+
+        assert_eq!(measurements.len(), 1);
+        assert_eq!(measurements[&Metric::Temperature], 21.5);
+    }
+
+    #[test]
+    fn test_decode_invalid_datagram() {
+        assert!(decode(&[]).is_err());
+        assert!(decode(&[2, 0, 0]).is_err());
+        assert!(decode(&[1, 42, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_derived_values() {
+        assert!((dew_point(25.0, 60.0) - 16.7).abs() < 0.1);
+        assert!((heat_index(32.0, 70.0) - 40.5).abs() < 0.5);
+        assert!((heat_index(20.0, 50.0) - 19.6).abs() < 0.5);
+    }
+}
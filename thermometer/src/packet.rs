@@ -0,0 +1,307 @@
+//! Module describes datagrams sent by thermometer senders
+//!
+//! Datagram without sensor id is plain encoded measurements
+//! (see [`crate::metrics`]), so senders unaware of sensor ids keep working.
+//! Datagram with sensor id starts with magic bytes `TH`, followed by length
+//! of the id, the id in UTF-8 and encoded measurements:
+//! `[T][H][id length][id]...[measurements]...`.
+//!
+//! Authenticated datagram starts with magic bytes `TS`, followed by length
+//! of the id (zero if sensor has no id), the id, big endian `u64` counter,
+//! encoded measurements and HMAC-SHA256 of all previous bytes:
+//! `[T][S][id length][id]...[counter]...[measurements]...[mac]...`.
+//!
+//! Request of reading sent to sender in pull mode starts with magic bytes
+//! `TQ`, followed by length of the id of requested sensor (zero for all
+//! sensors of the sender) and the id: `[T][Q][id length][id]...`.
+
+use crate::{
+    auth::{SharedKey, MAC_SIZE},
+    metrics::{self, Measurements},
+};
+
+/// Describes magic bytes of datagram with sensor id
+pub const MAGIC: [u8; 2] = *b"TH";
+
+/// Describes magic bytes of authenticated datagram
+pub const SIGNED_MAGIC: [u8; 2] = *b"TS";
+
+/// Describes magic bytes of request of reading
+pub const REQUEST_MAGIC: [u8; 2] = *b"TQ";
+
+/// Describes size of counter of authenticated datagram
+const COUNTER_SIZE: usize = 8;
+
+/// Describes datagram with measurements of a sensor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    /// Identifier of the sensor which is unique for the receiver
+    pub sensor_id: Option<String>,
+    /// Monotonic counter of authenticated datagram preventing replay
+    pub counter: Option<u64>,
+    pub measurements: Measurements,
+}
+
+impl Packet {
+    /// Creates new packet without sensor id
+    pub fn new(measurements: Measurements) -> Self {
+        Self {
+            sensor_id: None,
+            counter: None,
+            measurements,
+        }
+    }
+
+    /// Creates new packet of sensor with given `sensor_id`
+    pub fn with_sensor_id(sensor_id: &str, measurements: Measurements) -> Self {
+        Self {
+            sensor_id: Some(sensor_id.to_owned()),
+            counter: None,
+            measurements,
+        }
+    }
+
+    /// Encodes packet into datagram without authentication
+    pub fn encode(&self) -> Result<Vec<u8>, &'static str> {
+        let body = metrics::encode(&self.measurements);
+
+        let sensor_id = match &self.sensor_id {
+            Some(sensor_id) => sensor_id,
+            None => return Ok(body),
+        };
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + sensor_id.len() + body.len());
+        bytes.extend_from_slice(&MAGIC);
+        Self::push_sensor_id(&mut bytes, sensor_id)?;
+        bytes.extend_from_slice(&body);
+
+        Ok(bytes)
+    }
+
+    /// Encodes packet into authenticated datagram signed with `key`.
+    /// Packet must have counter.
+    pub fn encode_signed(&self, key: &SharedKey) -> Result<Vec<u8>, &'static str> {
+        let counter = self
+            .counter
+            .ok_or("Authenticated packet requires counter")?;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&SIGNED_MAGIC);
+        Self::push_sensor_id(&mut bytes, self.sensor_id.as_deref().unwrap_or_default())?;
+        bytes.extend_from_slice(&counter.to_be_bytes());
+        bytes.extend_from_slice(&metrics::encode(&self.measurements));
+
+        let mac = key.sign(&bytes);
+        bytes.extend_from_slice(&mac);
+
+        Ok(bytes)
+    }
+
+    /// Decodes packet from datagram of any format.
+    /// Authentication code is not verified, see [`Packet::decode_signed`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, &'static str> {
+        if let Some(rest) = bytes.strip_prefix(&SIGNED_MAGIC) {
+            let body = rest
+                .len()
+                .checked_sub(MAC_SIZE)
+                .map(|len| &rest[..len])
+                .ok_or("Missing authentication code")?;
+            return Self::decode_signed_body(body);
+        }
+
+        let rest = match bytes.strip_prefix(&MAGIC) {
+            Some(rest) => rest,
+            None => return metrics::decode(bytes).map(Self::new),
+        };
+
+        let (sensor_id, rest) = Self::split_sensor_id(rest)?;
+        let measurements = metrics::decode(rest)?;
+
+        Ok(Self::with_sensor_id(sensor_id, measurements))
+    }
+
+    /// Decodes authenticated datagram and verifies it is signed with `key`
+    pub fn decode_signed(bytes: &[u8], key: &SharedKey) -> Result<Self, &'static str> {
+        if !bytes.starts_with(&SIGNED_MAGIC) {
+            return Err("Datagram is not authenticated");
+        }
+
+        let data_len = bytes
+            .len()
+            .checked_sub(MAC_SIZE)
+            .filter(|len| *len >= SIGNED_MAGIC.len())
+            .ok_or("Missing authentication code")?;
+        let (data, mac) = bytes.split_at(data_len);
+
+        if !key.verify(data, mac) {
+            return Err("Invalid authentication code");
+        }
+
+        Self::decode_signed_body(&data[SIGNED_MAGIC.len()..])
+    }
+
+    /// Returns sensor id of datagram without decoding measurements
+    pub fn peek_sensor_id(bytes: &[u8]) -> Option<&str> {
+        let rest = bytes
+            .strip_prefix(&MAGIC)
+            .or_else(|| bytes.strip_prefix(&SIGNED_MAGIC))?;
+
+        Self::split_sensor_id(rest)
+            .ok()
+            .map(|(sensor_id, _)| sensor_id)
+            .filter(|sensor_id| !sensor_id.is_empty())
+    }
+
+    fn decode_signed_body(body: &[u8]) -> Result<Self, &'static str> {
+        let (sensor_id, rest) = Self::split_sensor_id(body)?;
+
+        if rest.len() < COUNTER_SIZE {
+            return Err("Missing counter");
+        }
+
+        let (counter, rest) = rest.split_at(COUNTER_SIZE);
+        let mut buf = [0u8; COUNTER_SIZE];
+        buf.copy_from_slice(counter);
+
+        Ok(Self {
+            sensor_id: (!sensor_id.is_empty()).then(|| sensor_id.to_owned()),
+            counter: Some(u64::from_be_bytes(buf)),
+            measurements: metrics::decode(rest)?,
+        })
+    }
+
+    fn push_sensor_id(bytes: &mut Vec<u8>, sensor_id: &str) -> Result<(), &'static str> {
+        let id_len = u8::try_from(sensor_id.len()).map_err(|_| "Sensor id is too long")?;
+        bytes.push(id_len);
+        bytes.extend_from_slice(sensor_id.as_bytes());
+        Ok(())
+    }
+
+    fn split_sensor_id(bytes: &[u8]) -> Result<(&str, &[u8]), &'static str> {
+        let (id_len, rest) = bytes.split_first().ok_or("Missing sensor id")?;
+        let id_len = *id_len as usize;
+
+        if rest.len() < id_len {
+            return Err("Invalid sensor id length");
+        }
+
+        let sensor_id = std::str::from_utf8(&rest[..id_len]).map_err(|_| "Invalid sensor id")?;
+
+        Ok((sensor_id, &rest[id_len..]))
+    }
+}
+
+/// Describes request of reading sent to sender in pull mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Identifier of requested sensor, `None` requests all sensors of sender
+    pub sensor_id: Option<String>,
+}
+
+impl Request {
+    /// Creates new request of sensor with given `sensor_id`
+    pub fn new(sensor_id: Option<&str>) -> Self {
+        Self {
+            sensor_id: sensor_id.map(str::to_owned),
+        }
+    }
+
+    /// Encodes request into datagram
+    pub fn encode(&self) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = REQUEST_MAGIC.to_vec();
+        Packet::push_sensor_id(&mut bytes, self.sensor_id.as_deref().unwrap_or_default())?;
+        Ok(bytes)
+    }
+
+    /// Decodes request from datagram
+    pub fn decode(bytes: &[u8]) -> Result<Self, &'static str> {
+        let rest = bytes
+            .strip_prefix(&REQUEST_MAGIC)
+            .ok_or("Datagram is not a request")?;
+
+        let (sensor_id, rest) = Packet::split_sensor_id(rest)?;
+
+        if !rest.is_empty() {
+            return Err("Invalid request size");
+        }
+
+        Ok(Self::new((!sensor_id.is_empty()).then_some(sensor_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metric;
+
+    #[test]
+    fn test_encode_decode_packet_with_sensor_id() {
+        let packet = Packet::with_sensor_id(
+            "bathroom",
+            Measurements::from([(Metric::Temperature, 22.0), (Metric::Humidity, 60.0)]),
+        );
+
+        let bytes = packet.encode().unwrap();
+
+        assert!(bytes.starts_with(b"TH\x08bathroom"));
+        assert_eq!(Packet::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn test_decode_packet_without_sensor_id() {
+        let packet = Packet::decode(&21.0f64.to_be_bytes()).unwrap();
+
+        assert_eq!(packet.sensor_id, None);
+        assert_eq!(packet.measurements[&Metric::Temperature], 21.0);
+    }
+
+    #[test]
+    fn test_encode_decode_signed_packet() {
+        let key = SharedKey::from("secret");
+        let packet = Packet {
+            sensor_id: Some("bathroom".to_owned()),
+            counter: Some(42),
+            measurements: Measurements::from([(Metric::Temperature, 22.0)]),
+        };
+
+        let bytes = packet.encode_signed(&key).unwrap();
+
+        assert_eq!(Packet::decode_signed(&bytes, &key), Ok(packet.clone()));
+        assert_eq!(Packet::decode(&bytes), Ok(packet));
+        assert_eq!(Packet::peek_sensor_id(&bytes), Some("bathroom"));
+        assert!(Packet::decode_signed(&bytes, &SharedKey::from("other")).is_err());
+    }
+
+    #[test]
+    fn test_decode_signed_rejects_tampered_packet() {
+        let key = SharedKey::from("secret");
+        let packet = Packet {
+            sensor_id: None,
+            counter: Some(1),
+            measurements: Measurements::from([(Metric::Temperature, 22.0)]),
+        };
+
+        let mut bytes = packet.encode_signed(&key).unwrap();
+        bytes[12] ^= 0x01;
+
+        assert!(Packet::decode_signed(&bytes, &key).is_err());
+        assert!(Packet::decode_signed(&packet.encode().unwrap(), &key).is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_request() {
+        let request = Request::new(Some("kitchen"));
+        let bytes = request.encode().unwrap();
+
+        assert_eq!(bytes, b"TQ\x07kitchen");
+        assert_eq!(Request::decode(&bytes), Ok(request));
+        assert_eq!(Request::decode(b"TQ\x00"), Ok(Request::new(None)));
+        assert!(Packet::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_decode_truncated_packet() {
+        assert!(Packet::decode(b"TH").is_err());
+        assert!(Packet::decode(b"TH\x08bath").is_err());
+    }
+}
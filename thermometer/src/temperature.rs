@@ -0,0 +1,178 @@
+//! Module describes temperature values and their units of measurement
+
+use std::{fmt, str::FromStr};
+
+/// Describes unit of temperature measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Returns symbol of the unit
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+            TemperatureUnit::Kelvin => "K",
+        }
+    }
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "c" | "celsius" => Ok(Self::Celsius),
+            "f" | "fahrenheit" => Ok(Self::Fahrenheit),
+            "k" | "kelvin" => Ok(Self::Kelvin),
+            _ => Err(format!("Unknown temperature unit: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for TemperatureUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Describes temperature value in some unit of measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Temperature {
+    value: f64,
+    unit: TemperatureUnit,
+}
+
+impl Temperature {
+    /// Creates new temperature with given `value` in `unit`
+    pub fn new(value: f64, unit: TemperatureUnit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Creates new temperature in degrees Celsius
+    pub fn celsius(value: f64) -> Self {
+        Self::new(value, TemperatureUnit::Celsius)
+    }
+
+    /// Creates new temperature in degrees Fahrenheit
+    pub fn fahrenheit(value: f64) -> Self {
+        Self::new(value, TemperatureUnit::Fahrenheit)
+    }
+
+    /// Creates new temperature in kelvins
+    pub fn kelvin(value: f64) -> Self {
+        Self::new(value, TemperatureUnit::Kelvin)
+    }
+
+    /// Returns numeric value of the temperature in its unit
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns unit of the temperature
+    pub fn unit(&self) -> TemperatureUnit {
+        self.unit
+    }
+
+    /// Returns value of the temperature in degrees Celsius
+    pub fn to_celsius(&self) -> f64 {
+        match self.unit {
+            TemperatureUnit::Celsius => self.value,
+            TemperatureUnit::Fahrenheit => (self.value - 32.0) * 5.0 / 9.0,
+            TemperatureUnit::Kelvin => self.value - 273.15,
+        }
+    }
+
+    /// Returns the same temperature expressed in given `unit`
+    pub fn convert(&self, unit: TemperatureUnit) -> Self {
+        let celsius = self.to_celsius();
+
+        let value = match unit {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        };
+
+        Self::new(value, unit)
+    }
+}
+
+/// Uses precision of the formatter if given, e.g. `{:.1}` gives `21.5 °C`
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, self.value, self.unit),
+            None => write!(f, "{} {}", self.value, self.unit),
+        }
+    }
+}
+
+/// Describes calibration of the sensor applied to raw readings:
+/// `calibrated = raw * scale + offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub offset: f64,
+    pub scale: f64,
+}
+
+impl Calibration {
+    /// Creates new calibration with given `offset` and `scale`
+    pub fn new(offset: f64, scale: f64) -> Self {
+        Self { offset, scale }
+    }
+
+    /// Applies calibration to the raw reading
+    pub fn apply(&self, raw: f64) -> f64 {
+        raw * self.scale + self.offset
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_temperature() {
+        let temperature = Temperature::celsius(100.0);
+
+        assert_eq!(
+            temperature.convert(TemperatureUnit::Fahrenheit),
+            Temperature::fahrenheit(212.0)
+        );
+        assert_eq!(
+            temperature.convert(TemperatureUnit::Kelvin),
+            Temperature::kelvin(373.15)
+        );
+        assert_eq!(Temperature::fahrenheit(212.0).to_celsius(), 100.0);
+    }
+
+    #[test]
+    fn test_display_temperature_with_precision() {
+        let temperature = Temperature::celsius(21.456);
+
+        assert_eq!(format!("{:.1}", temperature), "21.5 °C");
+        assert_eq!(format!("{}", Temperature::kelvin(0.0)), "0 K");
+    }
+
+    #[test]
+    fn test_apply_calibration() {
+        let calibration = Calibration::new(-0.5, 1.5);
+
+        assert_eq!(calibration.apply(20.0), 29.5);
+        assert_eq!(Calibration::default().apply(20.0), 20.0);
+    }
+}
@@ -1,104 +1,836 @@
-//! Module describes thermometer device for smart house
+//! Module describes thermometer device for smart house.
+//! Besides temperature thermometer receives other environmental metrics
+//! when sender provides them.
 
 use std::{
     error::Error,
-    fmt,
+    fmt, io,
     net::{SocketAddr, UdpSocket},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Condvar, Mutex,
     },
-    thread,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
-/// Describes smart thermometer
+
+use crate::{
+    alarm::{Alarm, AlarmEvent, AlarmMonitor, Thresholds},
+    auth::SharedKey,
+    history::{History, Reading},
+    hub::{SensorKey, ThermometerHub},
+    metrics::{self, Measurements, Metric},
+    packet::{Packet, Request},
+    temperature::{Calibration, Temperature, TemperatureUnit},
+    worker::Worker,
+};
+
+/// Describes default number of readings kept in the history
+pub const DEFAULT_HISTORY_CAPACITY: usize = 4096;
+
+/// Describes how long sender may stay silent in push mode
+/// before its measurements are forgotten
+const PUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Describes how thermometer gets data from sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Sender sends datagrams on its own
+    #[default]
+    Push,
+    /// Thermometer requests reading when no data was received
+    /// during `interval`. Measurements are forgotten when request
+    /// is not answered before the next one.
+    ///
+    /// Sensors of [`ThermometerHub`] are not polled,
+    /// they can be queried by [`Thermometer::read_now`].
+    Pull { interval: Duration },
+}
+
+/// Describes settings of the thermometer
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Unit in which temperature is returned and displayed
+    pub unit: TemperatureUnit,
+    /// Calibration applied to each reading received from the sender
+    /// (in degrees Celsius)
+    pub calibration: Calibration,
+    /// Number of digits after the decimal point for display,
+    /// `None` means the value is displayed as is
+    pub precision: Option<usize>,
+    /// Maximum number of readings kept in the history
+    pub history_capacity: usize,
+    /// Thresholds of temperature alarms (in degrees Celsius)
+    pub thresholds: Thresholds,
+    /// Key shared with sender. When set, only authenticated datagrams
+    /// are accepted.
+    pub key: Option<SharedKey>,
+    /// How thermometer gets data from sender
+    pub mode: Mode,
+    /// Time to wait for response to request of reading
+    pub request_timeout: Duration,
+    /// Number of repeated requests when sender doesn't respond
+    pub retries: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            unit: TemperatureUnit::default(),
+            calibration: Calibration::default(),
+            precision: None,
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            thresholds: Thresholds::default(),
+            key: None,
+            mode: Mode::default(),
+            request_timeout: Duration::from_secs(1),
+            retries: 2,
+        }
+    }
+}
+
+/// Describes state of the thermometer shared with the receiving thread
+#[derive(Debug)]
+pub(crate) struct State {
+    measurements: Mutex<Measurements>,
+    history: Mutex<History>,
+    alarms: Mutex<AlarmMonitor>,
+    calibration: Mutex<Calibration>,
+    key: Mutex<Option<SharedKey>>,
+    last_counter: Mutex<Option<u64>>,
+    rejected: AtomicU64,
+    source: Mutex<Option<SocketAddr>>,
+    generation: Mutex<u64>,
+    updated: Condvar,
+    last_update: Mutex<Option<SystemTime>>,
+}
+
+impl State {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        Self {
+            measurements: Mutex::new(Measurements::new()),
+            history: Mutex::new(History::new(settings.history_capacity)),
+            alarms: Mutex::new(AlarmMonitor::new(settings.thresholds)),
+            calibration: Mutex::new(settings.calibration),
+            key: Mutex::new(settings.key.clone()),
+            last_counter: Mutex::new(None),
+            rejected: AtomicU64::new(0),
+            source: Mutex::new(None),
+            generation: Mutex::new(0),
+            updated: Condvar::new(),
+            last_update: Mutex::new(None),
+        }
+    }
+
+    /// Applies calibration, thresholds and key of `settings`
+    pub(crate) fn configure(&self, settings: &Settings) {
+        *self.calibration.lock().unwrap() = settings.calibration;
+        *self.key.lock().unwrap() = settings.key.clone();
+        self.alarms
+            .lock()
+            .unwrap()
+            .set_thresholds(settings.thresholds);
+    }
+
+    /// Decodes datagram received from sender at `src_addr` and stores
+    /// its measurements. Returns `false` if datagram was rejected.
+    pub(crate) fn receive(&self, src_addr: SocketAddr, bytes: &[u8]) -> bool {
+        match self.accept(bytes) {
+            Ok(packet) => {
+                *self.source.lock().unwrap() = Some(src_addr);
+                self.update(packet.measurements);
+                true
+            }
+            Err(err) => {
+                self.rejected.fetch_add(1, Ordering::SeqCst);
+                println!("Rejected datagram from sender: {err}");
+                false
+            }
+        }
+    }
+
+    /// Returns number of rejected datagrams
+    pub(crate) fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::SeqCst)
+    }
+
+    /// Returns address of the last accepted datagram
+    pub(crate) fn source(&self) -> Option<SocketAddr> {
+        *self.source.lock().unwrap()
+    }
+
+    /// Returns number of accepted datagrams
+    fn generation(&self) -> u64 {
+        *self.generation.lock().unwrap()
+    }
+
+    /// Waits until datagram is accepted after given `generation`.
+    /// Returns `false` on timeout.
+    fn wait_update(&self, generation: u64, timeout: Duration) -> bool {
+        let guard = self.generation.lock().unwrap();
+        let (guard, _) = self
+            .updated
+            .wait_timeout_while(guard, timeout, |current| *current == generation)
+            .unwrap();
+
+        *guard != generation
+    }
+
+    /// Decodes datagram verifying its authentication code if key is set,
+    /// and rejects authenticated datagram with counter not greater than previous one.
+    /// Counters of datagrams accepted without key are not tracked,
+    /// since anyone could forge them.
+    fn accept(&self, bytes: &[u8]) -> Result<Packet, &'static str> {
+        let packet = match &*self.key.lock().unwrap() {
+            Some(key) => Packet::decode_signed(bytes, key)?,
+            None => return Packet::decode(bytes),
+        };
+
+        if let Some(counter) = packet.counter {
+            let mut last_counter = self.last_counter.lock().unwrap();
+
+            if last_counter.is_some_and(|last| counter <= last) {
+                return Err("Replayed datagram");
+            }
+
+            *last_counter = Some(counter);
+        }
+
+        Ok(packet)
+    }
+
+    /// Stores measurements received from sender, records temperature
+    /// in the history and checks alarms
+    fn update(&self, mut measurements: Measurements) {
+        let calibration = *self.calibration.lock().unwrap();
+        let temperature = measurements
+            .get_mut(&Metric::Temperature)
+            .map(|temperature| {
+                *temperature = calibration.apply(*temperature);
+                *temperature
+            });
+
+        *self.measurements.lock().unwrap() = measurements;
+        *self.last_update.lock().unwrap() = Some(SystemTime::now());
+
+        if let Some(temperature) = temperature {
+            self.history
+                .lock()
+                .unwrap()
+                .push(Reading::new(SystemTime::now(), temperature));
+            self.check_alarms(temperature);
+        }
+
+        *self.generation.lock().unwrap() += 1;
+        self.updated.notify_all();
+    }
+
+    /// Forgets measurements when sender is not available
+    fn clear(&self) {
+        self.measurements.lock().unwrap().clear();
+    }
+
+    fn check_alarms(&self, temperature: f64) {
+        let (events, callbacks) = {
+            let mut alarms = self.alarms.lock().unwrap();
+            (alarms.update(temperature), alarms.callbacks())
+        };
+
+        for event in &events {
+            for callback in &callbacks {
+                callback(event);
+            }
+        }
+    }
+}
+
+/// Describes where thermometer receives data from
+#[derive(Debug)]
+enum Source {
+    /// Own socket served by own thread
+    Socket {
+        worker: Mutex<Option<Worker>>,
+        local_addr: SocketAddr,
+        sender: SocketAddr,
+    },
+    /// Socket of the hub shared with other sensors
+    Hub { hub: ThermometerHub, key: SensorKey },
+}
+
+/// Describes data owned by all handles of the same thermometer
+#[derive(Debug)]
+struct Inner {
+    state: Arc<State>,
+    settings: Settings,
+    source: Source,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Source::Socket { worker, .. } = &mut self.source {
+            if let Some(worker) = worker.get_mut().unwrap().take() {
+                worker.shutdown();
+            }
+        }
+    }
+}
+
+/// Describes smart thermometer.
+///
+/// Thermometer is a shared handle: all clones observe the same readings,
+/// receiving is stopped when the last clone is dropped
+/// or explicitly by [`Thermometer::shutdown`].
 #[derive(Debug, Clone)]
 pub struct Thermometer {
-    temperature: Arc<Mutex<f64>>,
-    stop: Arc<AtomicBool>,
+    inner: Arc<Inner>,
 }
 
 impl Thermometer {
     /// Creates new thermometer which receives data at given `recevier`
     pub fn new(receiver: &str, sender: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_settings(receiver, sender, Settings::default())
+    }
+
+    /// Creates new thermometer with full setting
+    pub fn from_settings(
+        receiver: &str,
+        sender: &str,
+        settings: Settings,
+    ) -> Result<Self, Box<dyn Error>> {
         let receiver = receiver.parse::<SocketAddr>()?;
         let sender = sender.parse::<SocketAddr>()?;
 
         let socket = UdpSocket::bind(receiver)?;
-        socket.set_read_timeout(Some(Duration::from_secs(3)))?;
-
-        let stop = Arc::new(AtomicBool::new(false));
-        let temperature = Arc::new(Mutex::new(0.0));
+        let local_addr = socket.local_addr()?;
+        let requests = socket.try_clone()?;
 
-        let temperature_clone = temperature.clone();
-        let stop_clone = stop.clone();
+        let state = Arc::new(State::new(&settings));
+        let state_clone = state.clone();
 
-        thread::spawn(move || {
-            let socket = socket;
-            loop {
-                if stop_clone.load(Ordering::SeqCst) {
-                    return;
-                }
+        let worker = match settings.mode {
+            Mode::Push => {
+                socket.set_read_timeout(Some(PUSH_TIMEOUT))?;
 
-                let val = match Self::recv_temperature(&socket, &sender, stop_clone.clone()) {
+                Worker::spawn(socket, move |datagram| match datagram {
                     Err(err) => {
                         println!("Failed to receive temperature from sender: {err}");
-                        0.0
+                        state_clone.clear();
                     }
-                    Ok(None) => return,
-                    Ok(Some(val)) => val,
-                };
+                    Ok((src_addr, _)) if src_addr != sender => {}
+                    Ok((src_addr, bytes)) => _ = state_clone.receive(src_addr, bytes),
+                })?
+            }
+            Mode::Pull { interval } => {
+                socket.set_read_timeout(Some(interval))?;
+
+                let request = Request::new(None).encode()?;
+                let first_request = request.clone();
+                let mut pending = true;
 
-                *temperature_clone.lock().unwrap() = val;
+                let worker = Worker::spawn(socket, move |datagram| match datagram {
+                    Err(err) if Self::is_timeout(&err) => {
+                        if pending {
+                            println!("Sender didn't respond to request of reading");
+                            state_clone.clear();
+                        }
+                        if let Err(err) = requests.send_to(&request, sender) {
+                            println!("Failed to request reading from sender: {err}");
+                        }
+                        pending = true;
+                    }
+                    Err(err) => println!("Failed to receive temperature from sender: {err}"),
+                    Ok((src_addr, _)) if src_addr != sender => {}
+                    Ok((src_addr, bytes)) => {
+                        if state_clone.receive(src_addr, bytes) {
+                            pending = false;
+                        }
+                    }
+                })?;
+
+                worker.send_to(&first_request, sender)?;
+                worker
             }
-        });
+        };
 
-        Ok(Self { temperature, stop })
+        Ok(Self {
+            inner: Arc::new(Inner {
+                state,
+                settings,
+                source: Source::Socket {
+                    worker: Mutex::new(Some(worker)),
+                    local_addr,
+                    sender,
+                },
+            }),
+        })
     }
 
-    /// Returns current temperature of the thermometer
-    pub fn temperature(&self) -> f64 {
-        *self.temperature.lock().unwrap()
+    /// Creates handle of the sensor with given `key` served by the `hub`
+    pub(crate) fn from_hub(
+        hub: ThermometerHub,
+        key: SensorKey,
+        state: Arc<State>,
+        settings: Settings,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state,
+                settings,
+                source: Source::Hub { hub, key },
+            }),
+        }
     }
 
-    fn recv_temperature(
-        socket: &UdpSocket,
-        sender: &SocketAddr,
-        stop: Arc<AtomicBool>,
-    ) -> Result<Option<f64>, Box<dyn Error>> {
-        let mut buf = [0; 8];
-        let mut recv_buf = [0; 8];
-        let mut recv_count = 0;
+    /// Stops receiving data for all handles of the thermometer,
+    /// waits for the receiving thread and releases the socket.
+    /// Last received values remain available.
+    ///
+    /// For thermometer received from [`ThermometerHub`] the whole hub
+    /// is shut down, since the socket is shared by all its sensors.
+    pub fn shutdown(&self) {
+        match &self.inner.source {
+            Source::Socket { worker, .. } => {
+                let worker = worker.lock().unwrap().take();
 
-        while recv_count < 8 {
-            if stop.load(Ordering::SeqCst) {
-                return Ok(None);
+                if let Some(worker) = worker {
+                    worker.shutdown();
+                }
             }
+            Source::Hub { hub, .. } => hub.shutdown(),
+        }
+    }
 
-            let (bytes_received, src_addr) = socket.recv_from(&mut recv_buf)?;
+    /// Returns address the thermometer receives data at
+    pub fn local_addr(&self) -> SocketAddr {
+        match &self.inner.source {
+            Source::Socket { local_addr, .. } => *local_addr,
+            Source::Hub { hub, .. } => hub.local_addr(),
+        }
+    }
 
-            if src_addr != *sender {
-                continue;
+    /// Returns `true` if thermometer still receives data from sender
+    pub fn is_running(&self) -> bool {
+        match &self.inner.source {
+            Source::Socket { worker, .. } => worker.lock().unwrap().is_some(),
+            Source::Hub { hub, .. } => hub.is_running(),
+        }
+    }
+
+    /// Requests reading from sender and waits for the response,
+    /// repeating request configured number of times.
+    /// Returns temperature in configured unit, other metrics
+    /// of the response are available as usual.
+    ///
+    /// Works in both modes, any datagram accepted after request
+    /// is considered as the response.
+    pub fn read_now(&self) -> Result<Temperature, Box<dyn Error>> {
+        let settings = &self.inner.settings;
+        let state = &self.inner.state;
+
+        let (target, sensor_id) = match &self.inner.source {
+            Source::Socket { sender, .. } => (*sender, None),
+            Source::Hub {
+                key: SensorKey::Address(address),
+                ..
+            } => (*address, None),
+            Source::Hub {
+                key: SensorKey::Id(id),
+                ..
+            } => (
+                state.source().ok_or("Address of the sensor is unknown")?,
+                Some(id.as_str()),
+            ),
+        };
+
+        let request = Request::new(sensor_id).encode()?;
+
+        for _ in 0..=settings.retries {
+            let generation = state.generation();
+
+            self.send_request(&request, target)?;
+
+            if state.wait_update(generation, settings.request_timeout) {
+                return Ok(self.temperature());
             }
+        }
+
+        Err("Sender didn't respond to request of reading".into())
+    }
 
-            buf[recv_count..recv_count + bytes_received]
-                .copy_from_slice(&recv_buf[0..bytes_received]);
+    /// Returns current temperature of the thermometer in configured unit
+    pub fn temperature(&self) -> Temperature {
+        let celsius = self.metric(Metric::Temperature).unwrap_or(0.0);
+        Temperature::celsius(celsius).convert(self.inner.settings.unit)
+    }
 
-            recv_count += bytes_received;
+    /// Returns current value of given `metric` if sender provides it.
+    /// Temperature is returned calibrated and in degrees Celsius.
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        self.inner
+            .state
+            .measurements
+            .lock()
+            .unwrap()
+            .get(&metric)
+            .copied()
+    }
+
+    /// Returns time of the last accepted reading, `None` if nothing was received
+    pub fn last_update(&self) -> Option<SystemTime> {
+        *self.inner.state.last_update.lock().unwrap()
+    }
+
+    /// Returns current values of all metrics provided by sender
+    pub fn measurements(&self) -> Measurements {
+        self.inner.state.measurements.lock().unwrap().clone()
+    }
+
+    /// Returns current relative humidity in percents
+    pub fn humidity(&self) -> Option<f64> {
+        self.metric(Metric::Humidity)
+    }
+
+    /// Returns current atmospheric pressure in hectopascals
+    pub fn pressure(&self) -> Option<f64> {
+        self.metric(Metric::Pressure)
+    }
+
+    /// Returns current CO2 concentration in parts per million
+    pub fn co2(&self) -> Option<f64> {
+        self.metric(Metric::Co2)
+    }
+
+    /// Returns dew point in configured unit
+    /// if both temperature and humidity are provided
+    pub fn dew_point(&self) -> Option<Temperature> {
+        self.derived(metrics::dew_point)
+    }
+
+    /// Returns heat index in configured unit
+    /// if both temperature and humidity are provided
+    pub fn heat_index(&self) -> Option<Temperature> {
+        self.derived(metrics::heat_index)
+    }
+
+    /// Returns snapshot of the history of readings with values in configured unit.
+    /// Failed receptions are not recorded in the history.
+    pub fn history(&self) -> History {
+        let unit = self.inner.settings.unit;
+        self.inner
+            .state
+            .history
+            .lock()
+            .unwrap()
+            .map_values(|v| Temperature::celsius(v).convert(unit).value())
+    }
+
+    /// Returns number of datagrams rejected as invalid,
+    /// not authenticated or replayed
+    pub fn rejected_packets(&self) -> u64 {
+        self.inner.state.rejected()
+    }
+
+    /// Returns currently active temperature alarm
+    pub fn active_alarm(&self) -> Option<Alarm> {
+        self.inner.state.alarms.lock().unwrap().active()
+    }
+
+    /// Returns thresholds of temperature alarms
+    pub fn thresholds(&self) -> Thresholds {
+        self.inner.state.alarms.lock().unwrap().thresholds()
+    }
+
+    /// Sets thresholds of temperature alarms
+    pub fn set_thresholds(&self, thresholds: Thresholds) {
+        self.inner
+            .state
+            .alarms
+            .lock()
+            .unwrap()
+            .set_thresholds(thresholds);
+    }
+
+    /// Registers `callback` called from the receiving thread
+    /// when threshold is crossed and on recovery
+    pub fn on_alarm(&self, callback: impl Fn(&AlarmEvent) + Send + Sync + 'static) {
+        self.inner
+            .state
+            .alarms
+            .lock()
+            .unwrap()
+            .add_callback(Arc::new(callback));
+    }
+
+    /// Returns receiver of alarm events
+    pub fn subscribe_alarms(&self) -> mpsc::Receiver<AlarmEvent> {
+        self.inner.state.alarms.lock().unwrap().subscribe()
+    }
+
+    /// Returns settings of the thermometer
+    pub fn settings(&self) -> &Settings {
+        &self.inner.settings
+    }
+
+    fn send_request(&self, request: &[u8], target: SocketAddr) -> Result<(), Box<dyn Error>> {
+        match &self.inner.source {
+            Source::Socket { worker, .. } => worker
+                .lock()
+                .unwrap()
+                .as_ref()
+                .ok_or("Thermometer is shut down")?
+                .send_to(request, target)?,
+            Source::Hub { hub, .. } => hub.send_to(request, target)?,
         }
 
-        Ok(Some(f64::from_be_bytes(buf)))
+        Ok(())
+    }
+
+    fn is_timeout(err: &io::Error) -> bool {
+        matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        )
+    }
+
+    fn derived(&self, f: fn(f64, f64) -> f64) -> Option<Temperature> {
+        let measurements = self.inner.state.measurements.lock().unwrap();
+        let temperature = measurements.get(&Metric::Temperature)?;
+        let humidity = measurements.get(&Metric::Humidity)?;
+
+        Some(Temperature::celsius(f(*temperature, *humidity)).convert(self.inner.settings.unit))
     }
 }
 
+/// Describes thermometer which measures several environmental metrics
+pub type EnvironmentalSensor = Thermometer;
+
 impl fmt::Display for Thermometer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Thermometer (temperature: {})", self.temperature())
+        let precision = self.inner.settings.precision;
+
+        match precision {
+            Some(precision) => write!(
+                f,
+                "Thermometer (temperature: {:.*}",
+                precision,
+                self.temperature()
+            )?,
+            None => write!(f, "Thermometer (temperature: {}", self.temperature())?,
+        }
+
+        for (metric, value) in self.measurements() {
+            if metric == Metric::Temperature {
+                continue;
+            }
+
+            match precision {
+                Some(precision) => write!(f, ", {metric}: {value:.precision$} {}", metric.unit())?,
+                None => write!(f, ", {metric}: {value} {}", metric.unit())?,
+            }
+        }
+
+        if let Some(alarm) = self.active_alarm() {
+            write!(f, ", alarm: {alarm}")?;
+        }
+
+        let rejected = self.rejected_packets();
+        if rejected > 0 {
+            write!(f, ", rejected packets: {rejected}")?;
+        }
+
+        write!(f, ")")
     }
 }
 
-impl Drop for Thermometer {
-    fn drop(&mut self) {
-        self.stop.store(true, Ordering::SeqCst);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Instant};
+
+    fn wait_for_temperature(thermometer: &Thermometer, expected: f64) -> bool {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(3) {
+            if thermometer.temperature().value() == expected {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    /// Binds sender socket at free port and returns it with its address
+    fn bind_sender() -> (UdpSocket, String) {
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = sender.local_addr().unwrap().to_string();
+        (sender, address)
+    }
+
+    #[test]
+    fn test_dropping_clone_keeps_receiving() {
+        let (sender, sender_addr) = bind_sender();
+        let thermometer = Thermometer::new("127.0.0.1:0", &sender_addr).unwrap();
+
+        drop(thermometer.clone());
+        assert_eq!(thermometer.last_update(), None);
+
+        sender
+            .send_to(&21.5f64.to_be_bytes(), thermometer.local_addr())
+            .unwrap();
+
+        assert!(thermometer.is_running());
+        assert!(wait_for_temperature(&thermometer, 21.5));
+        assert!(thermometer.last_update().is_some());
+    }
+
+    #[test]
+    fn test_shutdown_releases_socket() {
+        let (_sender, sender_addr) = bind_sender();
+        let thermometer = Thermometer::new("127.0.0.1:0", &sender_addr).unwrap();
+        let clone = thermometer.clone();
+
+        let started = Instant::now();
+        thermometer.shutdown();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!clone.is_running());
+        assert!(UdpSocket::bind(thermometer.local_addr()).is_ok());
+    }
+
+    #[test]
+    fn test_dropping_last_handle_releases_socket() {
+        let (_sender, sender_addr) = bind_sender();
+        let thermometer = Thermometer::new("127.0.0.1:0", &sender_addr).unwrap();
+        let local_addr = thermometer.local_addr();
+        let clone = thermometer.clone();
+
+        drop(thermometer);
+        drop(clone);
+
+        assert!(UdpSocket::bind(local_addr).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_unauthenticated_and_replayed_datagrams() {
+        let key = SharedKey::from("secret");
+        let settings = Settings {
+            key: Some(key.clone()),
+            ..Settings::default()
+        };
+        let (sender, sender_addr) = bind_sender();
+        let thermometer =
+            Thermometer::from_settings("127.0.0.1:0", &sender_addr, settings).unwrap();
+        let receiver = thermometer.local_addr();
+
+        let packet = Packet {
+            sensor_id: None,
+            counter: Some(10),
+            measurements: Measurements::from([(Metric::Temperature, 20.0)]),
+        };
+        let signed = packet.encode_signed(&key).unwrap();
+
+        sender.send_to(&packet.encode().unwrap(), receiver).unwrap();
+        sender.send_to(&signed, receiver).unwrap();
+        sender.send_to(&signed, receiver).unwrap();
+
+        let started = Instant::now();
+        while thermometer.rejected_packets() < 2 && started.elapsed() < Duration::from_secs(3) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(thermometer.temperature().value(), 20.0);
+        assert_eq!(thermometer.rejected_packets(), 2);
+        assert!(thermometer.to_string().contains("rejected packets: 2"));
+    }
+
+    #[test]
+    fn test_unverified_counter_does_not_block_signed_datagrams() {
+        let key = SharedKey::from("secret");
+        let src_addr = "127.0.0.1:1".parse().unwrap();
+        let state = State::new(&Settings::default());
+
+        let forged = Packet {
+            sensor_id: None,
+            counter: Some(u64::MAX),
+            measurements: Measurements::from([(Metric::Temperature, 99.0)]),
+        };
+        assert!(state.receive(
+            src_addr,
+            &forged.encode_signed(&SharedKey::from("other")).unwrap()
+        ));
+
+        state.configure(&Settings {
+            key: Some(key.clone()),
+            ..Settings::default()
+        });
+        let packet = Packet {
+            sensor_id: None,
+            counter: Some(1),
+            measurements: Measurements::from([(Metric::Temperature, 20.0)]),
+        };
+
+        assert!(state.receive(src_addr, &packet.encode_signed(&key).unwrap()));
+        assert_eq!(state.rejected(), 0);
+    }
+
+    fn answer_request(sender: &UdpSocket, temperature: f64) {
+        let mut buf = [0; 64];
+        let (len, src_addr) = sender.recv_from(&mut buf).unwrap();
+        assert_eq!(Request::decode(&buf[..len]), Ok(Request::new(None)));
+
+        let measurements = Measurements::from([(Metric::Temperature, temperature)]);
+        let bytes = Packet::new(measurements).encode().unwrap();
+        sender.send_to(&bytes, src_addr).unwrap();
+    }
+
+    #[test]
+    fn test_read_now_retries_request() {
+        let settings = Settings {
+            request_timeout: Duration::from_millis(200),
+            ..Settings::default()
+        };
+        let (sender, sender_addr) = bind_sender();
+        let thermometer =
+            Thermometer::from_settings("127.0.0.1:0", &sender_addr, settings).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 64];
+            sender.recv_from(&mut buf).unwrap();
+            answer_request(&sender, 18.0);
+        });
+
+        assert_eq!(thermometer.read_now().unwrap().value(), 18.0);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_read_now_fails_without_response() {
+        let settings = Settings {
+            request_timeout: Duration::from_millis(50),
+            retries: 1,
+            ..Settings::default()
+        };
+        let (_sender, sender_addr) = bind_sender();
+        let thermometer =
+            Thermometer::from_settings("127.0.0.1:0", &sender_addr, settings).unwrap();
+
+        assert!(thermometer.read_now().is_err());
+    }
+
+    #[test]
+    fn test_pull_mode_requests_readings() {
+        let settings = Settings {
+            mode: Mode::Pull {
+                interval: Duration::from_millis(100),
+            },
+            ..Settings::default()
+        };
+        let (sender, sender_addr) = bind_sender();
+        let thermometer =
+            Thermometer::from_settings("127.0.0.1:0", &sender_addr, settings).unwrap();
+
+        answer_request(&sender, 22.0);
+        assert!(wait_for_temperature(&thermometer, 22.0));
+
+        answer_request(&sender, 23.0);
+        assert!(wait_for_temperature(&thermometer, 23.0));
     }
 }
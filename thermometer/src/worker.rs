@@ -0,0 +1,108 @@
+//! Module describes background thread which receives datagrams from socket
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Describes maximum size of datagram received from senders
+pub const MAX_DATAGRAM_SIZE: usize = 512;
+
+/// Describes background thread which passes each received datagram
+/// or receiving error to the handler
+#[derive(Debug)]
+pub(crate) struct Worker {
+    socket: UdpSocket,
+    stop: Arc<AtomicBool>,
+    local_addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    /// Spawns thread which receives datagrams from `socket`
+    pub(crate) fn spawn<F>(socket: UdpSocket, mut handler: F) -> io::Result<Self>
+    where
+        F: FnMut(io::Result<(SocketAddr, &[u8])>) + Send + 'static,
+    {
+        let local_addr = socket.local_addr()?;
+        let sending_socket = socket.try_clone()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0; MAX_DATAGRAM_SIZE];
+
+            loop {
+                if stop_clone.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                let res = socket.recv_from(&mut buf);
+
+                if stop_clone.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                handler(res.map(|(bytes_received, src_addr)| (src_addr, &buf[..bytes_received])));
+            }
+        });
+
+        Ok(Self {
+            socket: sending_socket,
+            stop,
+            local_addr,
+            handle,
+        })
+    }
+
+    /// Sends datagram from the socket of the worker to given `target`
+    pub(crate) fn send_to(&self, bytes: &[u8], target: SocketAddr) -> io::Result<()> {
+        self.socket.send_to(bytes, target)?;
+        Ok(())
+    }
+
+    /// Stops the thread and waits until it releases the socket
+    pub(crate) fn shutdown(self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Err(err) = Self::wake(self.local_addr) {
+            println!("Failed to wake receiving thread: {err}");
+        }
+
+        // Thread can't join itself, e.g. when the last handle
+        // is dropped inside alarm callback
+        if self.handle.thread().id() != thread::current().id() {
+            _ = self.handle.join();
+        }
+    }
+
+    /// Sends empty datagram to the socket, so blocked thread checks
+    /// `stop` flag without waiting for read timeout
+    fn wake(local_addr: SocketAddr) -> io::Result<()> {
+        let (target, bind) = match local_addr {
+            SocketAddr::V4(_) => (
+                IpAddr::from(Ipv4Addr::LOCALHOST),
+                IpAddr::from(Ipv4Addr::UNSPECIFIED),
+            ),
+            SocketAddr::V6(_) => (
+                IpAddr::from(Ipv6Addr::LOCALHOST),
+                IpAddr::from(Ipv6Addr::UNSPECIFIED),
+            ),
+        };
+
+        let target = if local_addr.ip().is_unspecified() {
+            SocketAddr::new(target, local_addr.port())
+        } else {
+            local_addr
+        };
+
+        UdpSocket::bind(SocketAddr::new(bind, 0))?.send_to(&[], target)?;
+
+        Ok(())
+    }
+}